@@ -1,22 +1,66 @@
 use anyhow::{Context, Result};
 use clipboard::ClipboardProvider;
-use nostr_sdk::nips::nip59;
 use nostr_sdk::prelude::*;
-use nrc_mls::groups::NostrGroupConfigData;
+use nrc_mls::groups::{NostrGroupConfigData, NostrGroupDataUpdate};
 use nrc_mls::{messages::MessageProcessingResult, NostrMls};
 use nrc_mls_sqlite_storage::NostrMlsSqliteStorage;
 use openmls::group::GroupId;
-use std::collections::HashMap;
+use openmls::prelude::{MlsGroup, OpenMlsProvider};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::{mpsc, watch, Mutex};
 
-use crate::config::get_default_relays;
-use crate::events::{AppEvent, NetworkCommand};
-use crate::key_storage::KeyStorage;
-use crate::ops::{spawn_orchestrator, CreateDmStep, OperationKind, OpsCommand, OpsStore};
+use crate::commit_race::{
+    commit_order, AppliedCommit, CommitLedger, MlsSnapshots, COMMIT_RETENTION,
+};
+use crate::config::{Config, KeyRotationPolicy};
+use crate::contacts::{fetch_follow_list, follow_list, ContactStore};
+use crate::delivery::{Delivery, DeliveryStatus, DeliveryStore};
+use crate::events::{AppEvent, ConnectionStatus, NetworkCommand, Nip05Action};
+use crate::group_changes::{GroupChange, GroupSnapshot};
+use crate::group_messages::{self, ProposedChange};
+use crate::group_meta::{GroupMetaStore, HeldProposal};
+use crate::key_packages::{key_package_relays_event, KeyPackagePolicy, KeyPackageStore};
+use crate::key_storage::{AccountInfo, KeyStorage, MIN_PASSWORD_LEN};
+use crate::nip05::{Nip05Address, Nip05Resolver};
+use crate::ops::{
+    ensure_relays, pair_welcomes, spawn_orchestrator, CreateDmStep, CreateGroupStep,
+    GroupEvolutionStep, InviteMemberStep, OperationKind, OpsCommand, OpsStore, RotateGroupKeyStep,
+};
 use crate::profiles::Profiles;
-use crate::ui_state::{GroupSummary, Message, Modal, OpsItem, Page, PageType};
+use crate::relay_status::{spawn_relay_monitor, RelayActivity};
+use crate::replies::{chat_rumor, quote_parents, reply_target};
+use crate::signer::AccountSigner;
+use crate::ui_state::{
+    Contact, ExportStage, GroupSummary, Message, Modal, ModalAction, OnboardingMode, OpsItem, Page,
+    PageType, PasswordStep, QuotedMessage, RelayInfo, ReplyTo,
+};
+
+/// How long `/export` shows a key (and leaves it on the clipboard)
+pub const EXPORT_CLEAR_AFTER: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Asks the binary to restart the session on another account's storage
+#[derive(Clone, Debug)]
+pub enum AccountSwitch {
+    // Show the unlock screen for this npub
+    Unlock(String),
+    // Onboard a new account
+    Create,
+    // Imported keys, still to be given a password
+    Import(Keys),
+    // Logged in through a NIP-46 bunker, still to be given a password
+    Bunker(Box<AccountSigner>),
+}
+
+/// What the lock screen hides, restored on unlock
+struct LockedSession {
+    page: Page,
+    previous_page: Option<Page>,
+    // Events that need the keys (incoming messages, ops callbacks), replayed on unlock
+    backlog: Vec<AppEvent>,
+}
 
 pub struct App {
     pub current_page: Page,
@@ -29,7 +73,8 @@ pub struct App {
 
     pub storage: Arc<NostrMls<NostrMlsSqliteStorage>>,
     pub client: Client,
-    pub keys: Keys,
+    // Our identity: local keys or a NIP-46 bunker
+    pub signer: AccountSigner,
     pub key_storage: KeyStorage,
 
     state_tx: watch::Sender<Page>,
@@ -46,6 +91,55 @@ pub struct App {
     pub ops_store: OpsStore,
     pub ops_cmd_tx: mpsc::UnboundedSender<OpsCommand>,
 
+    // Local group bookkeeping (archived groups, system lines)
+    pub group_meta: GroupMetaStore,
+    // Delivery status of the messages we send
+    pub deliveries: DeliveryStore,
+    // Kind 445 subscription per group and the relays it runs on
+    group_subscriptions: HashMap<GroupId, (SubscriptionId, Vec<RelayUrl>)>,
+    // Recently applied commits and the MLS state needed to undo them (NIP-EE races)
+    pub commit_ledger: CommitLedger,
+    mls_snapshots: MlsSnapshots,
+    // Settings from config.toml (plus CLI overrides)
+    pub config: Config,
+    // When to issue self-update commits for forward secrecy
+    pub key_rotation: KeyRotationPolicy,
+    // Our KeyPackages and how many we keep on relays
+    pub key_packages: KeyPackageStore,
+    pub key_package_policy: KeyPackagePolicy,
+    last_key_package_reconcile: Option<Instant>,
+    // Latest relay pool state from the relay monitor
+    pub relays: Vec<RelayInfo>,
+    pub connection_status: ConnectionStatus,
+    // Messages held in the ops queue until a relay is back
+    pub queued_sends: usize,
+    relay_activity: RelayActivity,
+    // NIP-42 challenges that arrived before our keys were unlocked
+    pending_auth_challenges: HashMap<RelayUrl, String>,
+
+    // NIP-05 lookups, and pubkeys with a check in flight
+    pub nip05: Nip05Resolver,
+    nip05_in_flight: HashSet<PublicKey>,
+
+    // Follows and local petnames; petnames override Kind 0 names everywhere
+    pub contacts: ContactStore,
+    pub petnames: HashMap<PublicKey, String>,
+
+    // Bumped on each /export reveal, so an older timer can't clear a newer one
+    export_generation: u64,
+    // Secret we put on the clipboard, to wipe it later only if it's still there
+    clipboard_secret: Option<String>,
+
+    // Last key press, for the idle lock
+    last_activity: Instant,
+    // Set while the TUI is locked; the keys are wiped until the password is entered again
+    locked: Option<LockedSession>,
+
+    // Stored accounts, for the unlock screen
+    pub accounts: Vec<AccountInfo>,
+    // Set when the user picks another account; the session is then rebuilt for it
+    pub account_switch: Option<AccountSwitch>,
+
     // Onboarding: hold display name until we can publish profile
     pending_display_name: Option<String>,
 }
@@ -54,18 +148,31 @@ impl App {
     pub async fn new(
         storage: Arc<NostrMls<NostrMlsSqliteStorage>>,
         client: Client,
-        keys: Keys,
+        signer: impl Into<AccountSigner>,
+        key_storage: KeyStorage,
+        initial_page: Page,
+    ) -> Result<Self> {
+        let config = Config::load(key_storage.datadir())?;
+        Self::with_config(storage, client, signer, key_storage, initial_page, config).await
+    }
+
+    pub async fn with_config(
+        storage: Arc<NostrMls<NostrMlsSqliteStorage>>,
+        client: Client,
+        signer: impl Into<AccountSigner>,
         key_storage: KeyStorage,
         initial_page: Page,
+        config: Config,
     ) -> Result<Self> {
+        let signer = signer.into();
         let (state_tx, state_rx) = watch::channel(initial_page.clone());
         let (event_tx, event_rx) = mpsc::unbounded_channel();
         let (command_tx, _command_rx) = mpsc::channel(100);
         let (ops_cmd_tx, ops_cmd_rx) = mpsc::unbounded_channel();
 
         // Add relays and connect (like master branch does)
-        for &relay in get_default_relays() {
-            if let Err(e) = client.add_relay(relay).await {
+        for relay in &config.relays {
+            if let Err(e) = client.add_relay(relay.as_str()).await {
                 log::warn!("Failed to add relay {relay}: {e}");
             }
         }
@@ -74,30 +181,55 @@ impl App {
 
         // Spawn notification handler to receive events from relays
         // This processes incoming welcomes, messages, etc in real-time
+        let relay_activity = RelayActivity::new();
         crate::notification_handler::spawn_notification_handler(
             client.clone(),
             event_tx.clone(),
-            keys.public_key(),
+            signer.public_key(),
+            relay_activity.clone(),
         );
+        spawn_relay_monitor(client.clone(), relay_activity.clone(), event_tx.clone());
 
         // Ensure we are persistently subscribed to GiftWrap events for welcomes
-        let giftwrap_filter = Filter::new().kind(Kind::GiftWrap).pubkey(keys.public_key());
+        let giftwrap_filter = Filter::new()
+            .kind(Kind::GiftWrap)
+            .pubkey(signer.public_key());
         if let Err(e) = client.subscribe(giftwrap_filter, None).await {
             log::warn!("Failed to subscribe to GiftWrap events: {e}");
         }
 
+        // Each account has its own ops queue and app tables
+        let account_dir = key_storage.account_datadir()?;
+        std::fs::create_dir_all(&account_dir)?;
+        let accounts = key_storage.list_accounts()?;
+
         // Initialize persistent ops store and background orchestrator
-        let ops_store = OpsStore::new(key_storage.datadir())?;
+        // Until the password is entered our keys are a placeholder: nothing may be signed
+        let awaiting_password = matches!(
+            initial_page,
+            Page::Onboarding {
+                mode: OnboardingMode::EnterPassword | OnboardingMode::SelectAccount,
+                ..
+            }
+        );
+        let ops_store = OpsStore::new(&account_dir)?;
         spawn_orchestrator(
             ops_store.clone(),
             client.clone(),
-            keys.clone(),
+            (!awaiting_password).then(|| signer.clone()),
             event_tx.clone(),
             ops_cmd_rx,
             key_storage.datadir().to_path_buf(),
         );
+        let group_meta = GroupMetaStore::new(&account_dir)?;
+        let deliveries = DeliveryStore::new(&account_dir)?;
+        let profiles = Profiles::load(&account_dir)?;
+        let mls_snapshots = MlsSnapshots::new(&account_dir);
+        let key_packages = KeyPackageStore::new(&account_dir)?;
+        let contacts = ContactStore::new(&account_dir)?;
+        let petnames = contacts.petnames()?;
 
-        Ok(Self {
+        let mut app = Self {
             current_page: initial_page,
             previous_page: None,
             flash: None,
@@ -106,19 +238,52 @@ impl App {
             is_processing: false,
             storage,
             client,
-            keys,
+            signer,
             key_storage,
             state_tx,
             state_rx,
             event_tx,
             event_rx: Some(event_rx),
             command_tx,
-            profiles: Profiles::new(),
+            profiles,
             welcome_rumors: Arc::new(Mutex::new(HashMap::new())),
             ops_store,
             ops_cmd_tx,
+            group_meta,
+            deliveries,
+            group_subscriptions: HashMap::new(),
+            commit_ledger: CommitLedger::new(),
+            mls_snapshots,
+            key_rotation: config.key_rotation_policy(),
+            key_packages,
+            key_package_policy: config.key_package_policy(),
+            config,
+            last_key_package_reconcile: None,
+            relays: Vec::new(),
+            connection_status: ConnectionStatus::Connecting,
+            queued_sends: 0,
+            relay_activity,
+            pending_auth_challenges: HashMap::new(),
+            nip05: Nip05Resolver::new(),
+            nip05_in_flight: HashSet::new(),
+            contacts,
+            petnames,
+            export_generation: 0,
+            clipboard_secret: None,
+            last_activity: Instant::now(),
+            locked: None,
+            accounts,
+            account_switch: None,
             pending_display_name: None,
-        })
+        };
+
+        // Listen to every group we're in on its own relays
+        app.subscribe_all_groups().await;
+        // Re-verify cached profiles whose NIP-05 result expired
+        for pk in app.profiles.pubkeys().await {
+            app.check_nip05(pk).await;
+        }
+        Ok(app)
     }
 
     pub async fn navigate_to(&mut self, page_type: PageType) -> Result<()> {
@@ -157,6 +322,7 @@ impl App {
                         input: String::new(),
                         scroll_offset: 0,
                         typing_members: vec![],
+                        selected_message: None,
                     })
                 } else if groups.is_empty() {
                     // No groups exist — show empty/help state in Chat view
@@ -185,6 +351,7 @@ impl App {
                         input: String::new(),
                         scroll_offset: 0,
                         typing_members: vec![],
+                        selected_message: None,
                     })
                 } else {
                     // No specific group requested — default to first existing group
@@ -207,6 +374,7 @@ impl App {
                         input: String::new(),
                         scroll_offset: 0,
                         typing_members: vec![],
+                        selected_message: None,
                     })
                 }
             }
@@ -219,6 +387,9 @@ impl App {
                     .list_all()?
                     .into_iter()
                     .map(|op| OpsItem {
+                        // Full payload for the detail pane (event ids, pubkeys, current step)
+                        detail: serde_json::to_string_pretty(&op.kind)
+                            .unwrap_or_else(|e| format!("Unserializable payload: {e}")),
                         id: op.id,
                         kind: match op.kind {
                             crate::ops::OperationKind::SendMessage { .. } => {
@@ -227,23 +398,134 @@ impl App {
                             crate::ops::OperationKind::PublishKeyPackage { .. } => {
                                 "PublishKeyPackage".to_string()
                             }
+                            crate::ops::OperationKind::PublishKeyPackageRelays { .. } => {
+                                "PublishKeyPackageRelays".to_string()
+                            }
+                            crate::ops::OperationKind::DeleteKeyPackages { event_ids } => {
+                                format!("DeleteKeyPackages ({})", event_ids.len())
+                            }
+                            crate::ops::OperationKind::ReconcileKeyPackages { .. } => {
+                                "ReconcileKeyPackages".to_string()
+                            }
                             crate::ops::OperationKind::CreateDm { .. } => "CreateDm".to_string(),
+                            crate::ops::OperationKind::CreateGroup { .. } => {
+                                "CreateGroup".to_string()
+                            }
+                            crate::ops::OperationKind::InviteMember { .. } => {
+                                "InviteMember".to_string()
+                            }
+                            crate::ops::OperationKind::GroupEvolution { description, .. } => {
+                                format!("GroupEvolution ({description})")
+                            }
+                            crate::ops::OperationKind::RotateGroupKey { .. } => {
+                                "RotateGroupKey".to_string()
+                            }
                         },
                         status: match op.status {
                             crate::ops::OpStatus::Pending => "Pending".to_string(),
                             crate::ops::OpStatus::InProgress => "InProgress".to_string(),
                             crate::ops::OpStatus::Success => "Success".to_string(),
                             crate::ops::OpStatus::Error => "Error".to_string(),
+                            crate::ops::OpStatus::Failed => "Failed".to_string(),
+                            crate::ops::OpStatus::Cancelled => "Cancelled".to_string(),
                         },
                         updated_at: op.updated_at,
                         last_error: op.last_error,
+                        attempts: op.attempts,
+                        next_attempt_at: op.next_attempt_at,
                     })
                     .collect::<Vec<OpsItem>>();
-                Ok(Page::OpsDashboard { items, selected: 0 })
+                Ok(Page::OpsDashboard {
+                    items,
+                    selected: 0,
+                    show_detail: false,
+                })
+            }
+            PageType::GroupInfo(group_id) => {
+                let group = self
+                    .storage
+                    .get_group(&group_id)?
+                    .ok_or_else(|| anyhow::anyhow!("Group not found"))?;
+                let members = self
+                    .storage
+                    .get_members(&group_id)?
+                    .iter()
+                    .map(|pk| self.display_label(pk))
+                    .collect();
+                let admins = group
+                    .admin_pubkeys
+                    .iter()
+                    .map(|pk| self.display_label(pk))
+                    .collect();
+                let rotation = self.group_meta.key_rotation(&group_id)?;
+                Ok(Page::GroupInfo {
+                    group_id,
+                    name: group.name,
+                    description: group.description,
+                    epoch: group.epoch,
+                    admins,
+                    members,
+                    last_key_rotation: rotation.last_rotated_at,
+                    messages_since_rotation: rotation.messages_since,
+                })
+            }
+            PageType::Relays => Ok(Page::Relays {
+                relays: self.relays.clone(),
+            }),
+            PageType::Contacts => {
+                let mut contacts = Vec::new();
+                for record in self.contacts.list()? {
+                    let metadata = self
+                        .profiles
+                        .get(&record.pubkey)
+                        .await
+                        .map(|cached| cached.metadata);
+                    contacts.push(Contact {
+                        public_key: record.pubkey,
+                        display_name: self.profiles.display_name(&record.pubkey),
+                        metadata,
+                        petname: record.petname,
+                        followed: record.followed,
+                    });
+                }
+                contacts.sort_by_cached_key(|c| {
+                    c.petname
+                        .clone()
+                        .or_else(|| c.display_name.clone())
+                        .unwrap_or_else(|| crate::utils::pubkey_to_bech32_safe(&c.public_key))
+                        .to_lowercase()
+                });
+                Ok(Page::Contacts {
+                    contacts,
+                    selected: 0,
+                })
+            }
+            PageType::Accounts => {
+                let accounts = self.key_storage.list_accounts()?;
+                let current = self.signer.public_key().to_bech32()?;
+                let selected = accounts.iter().position(|a| a.npub == current).unwrap_or(0);
+                Ok(Page::Accounts {
+                    accounts,
+                    current,
+                    selected,
+                })
             }
+            PageType::ChangePassword => Ok(Page::ChangePassword {
+                step: PasswordStep::Old,
+                input: String::new(),
+                old_password: String::new(),
+                new_password: String::new(),
+                error: None,
+            }),
+            PageType::ExportKey(include_nsec) => Ok(Page::ExportKey {
+                stage: ExportStage::Password,
+                input: String::new(),
+                include_nsec,
+                error: None,
+            }),
             PageType::Onboarding => Ok(Page::Onboarding {
                 input: String::new(),
-                mode: crate::ui_state::OnboardingMode::Choose,
+                mode: OnboardingMode::Choose,
                 error: None,
             }),
             PageType::Initializing => Ok(Page::Initializing {
@@ -266,6 +548,34 @@ impl App {
     }
 
     pub async fn handle_event(&mut self, event: AppEvent) -> Result<()> {
+        if matches!(event, AppEvent::KeyPress(_) | AppEvent::Paste(_)) {
+            self.last_activity = Instant::now();
+        }
+        if let Some(locked) = &mut self.locked {
+            match event {
+                // The lock screen itself
+                AppEvent::KeyPress(_)
+                | AppEvent::Paste(_)
+                | AppEvent::Resize
+                | AppEvent::FlashMessage(..)
+                | AppEvent::ClearFlash
+                | AppEvent::ConnectionStatusChanged(_)
+                | AppEvent::RelayStatusUpdated { .. }
+                | AppEvent::RelayAuthChallenge { .. }
+                | AppEvent::Nip05Checked { .. }
+                | AppEvent::Lock => {}
+                // Periodic, or about pages the lock replaced; unlocking refreshes anyway
+                AppEvent::ProcessPendingOperationsTick
+                | AppEvent::RefreshCurrentPage
+                | AppEvent::ClearExportedKey(_) => return Ok(()),
+                // Anything else may need the keys: keep it for after unlocking
+                event => {
+                    locked.backlog.push(event);
+                    return Ok(());
+                }
+            }
+        }
+
         match event {
             AppEvent::Navigate(page) => {
                 self.previous_page = Some(self.current_page.clone());
@@ -279,34 +589,98 @@ impl App {
                 self.handle_keypress(key_event).await?;
             }
             AppEvent::SendMessage(content) => {
+                let relays = self
+                    .current_group_id()
+                    .map(|g| self.group_relay_urls(&g))
+                    .unwrap_or_default();
                 if let Page::Chat {
                     group_id,
                     input,
                     messages,
+                    selected_message,
                     ..
                 } = &mut self.current_page
                 {
-                    if !content.is_empty() {
+                    if self.group_meta.is_archived(group_id).unwrap_or(false) {
+                        self.error = Some("You left this group".to_string());
+                        input.clear();
+                        let _ = self.state_tx.send(self.current_page.clone());
+                    } else if !content.is_empty() {
                         // Create the MLS message locally (storage-bound) and enqueue send op
-                        let rumor = EventBuilder::new(Kind::Custom(9), content.clone())
-                            .build(self.keys.public_key());
+                        // Replying to the selected message
+                        let reply_to = selected_message.take().map(|id| ReplyTo {
+                            id,
+                            parent: messages.iter().find(|m| m.id == Some(id)).map(|m| {
+                                QuotedMessage {
+                                    sender: m.sender,
+                                    content: m.content.clone(),
+                                }
+                            }),
+                        });
+                        let rumor = chat_rumor(
+                            self.signer.public_key(),
+                            &content,
+                            reply_to.as_ref().map(|r| r.id),
+                        );
+                        let rumor_id = rumor.id;
 
                         match self.storage.create_message(group_id, rumor) {
                             Ok(message_event) => {
+                                if let Err(e) = self.group_meta.count_message(group_id) {
+                                    log::warn!("Failed to count message for key rotation: {e}");
+                                }
+                                let event_id = message_event.id;
+
+                                // Enqueue a persistent send operation
+                                let kind = OperationKind::SendMessage {
+                                    event: message_event,
+                                    relays,
+                                };
+                                let enqueued = self.ops_store.enqueue(kind);
+                                let delivery = match &enqueued {
+                                    Ok(op_id) => {
+                                        if let Err(e) =
+                                            self.deliveries.track(&event_id, group_id, op_id)
+                                        {
+                                            log::warn!(
+                                                "Failed to track delivery of {event_id}: {e}"
+                                            );
+                                        }
+                                        Some(Delivery {
+                                            event_id,
+                                            op_id: op_id.clone(),
+                                            status: DeliveryStatus::Queued,
+                                            acked_by: vec![],
+                                        })
+                                    }
+                                    Err(e) => {
+                                        log::error!("Failed to enqueue message: {e}");
+                                        None
+                                    }
+                                };
+
                                 // Add to local messages immediately for UI feedback
                                 messages.push(Message {
+                                    id: rumor_id,
                                     content: content.clone(),
-                                    sender: self.keys.public_key(),
+                                    sender: self.signer.public_key(),
                                     timestamp: Timestamp::now(),
+                                    system: false,
+                                    delivery,
+                                    reply_to,
                                 });
 
-                                // Enqueue a persistent send operation
-                                let kind = OperationKind::SendMessage {
-                                    event: message_event,
-                                };
-                                if let Ok(op_id) = self.ops_store.enqueue(kind) {
+                                if let Ok(op_id) = enqueued {
                                     log::debug!("Enqueued SendMessage op {op_id}");
                                     let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+                                    if self.connection_status != ConnectionStatus::Connected {
+                                        self.queued_sends =
+                                            self.ops_store.queued_sends().unwrap_or(0);
+                                        self.flash = Some((
+                                            "No relay connected; message queued".to_string(),
+                                            Instant::now() + std::time::Duration::from_secs(3),
+                                        ));
+                                    }
                                 }
                             }
                             Err(e) => {
@@ -319,12 +693,44 @@ impl App {
                     }
                 }
             }
-            AppEvent::CreateGroup { name, members: _ } => {
-                let _ = self
-                    .command_tx
-                    .send(NetworkCommand::CreateGroup { name })
-                    .await;
-                self.navigate_to(PageType::Chat(None)).await?;
+            AppEvent::CreateGroup { name, members } => {
+                self.create_group_with(name, members).await?;
+            }
+            AppEvent::LeaveGroup(group_id) => {
+                if let Err(e) = self.leave_group(group_id).await {
+                    self.error = Some(format!("{e:#}"));
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
+            }
+            AppEvent::MessageDelivery {
+                event_id,
+                status,
+                relays,
+            } => {
+                let changed = match status {
+                    DeliveryStatus::Queued => self.deliveries.mark_queued(&event_id)?,
+                    DeliveryStatus::Sent => self.deliveries.mark_sent(&event_id)?,
+                    DeliveryStatus::Acked => self.deliveries.record_acks(&event_id, &relays)?,
+                    DeliveryStatus::Failed => self.deliveries.mark_failed(&event_id)?,
+                };
+                if changed {
+                    if status == DeliveryStatus::Failed {
+                        self.error = Some("Message failed to send; /retry to resend".to_string());
+                    }
+                    self.update_message_delivery(&event_id)?;
+                }
+            }
+            AppEvent::CancelOperation(op_id) => {
+                match self.cancel_operation(&op_id).await {
+                    Ok(()) => {
+                        self.flash = Some((
+                            "Operation cancelled".to_string(),
+                            Instant::now() + std::time::Duration::from_secs(3),
+                        ));
+                    }
+                    Err(e) => self.error = Some(format!("{e:#}")),
+                }
+                self.refresh_current_page().await?;
             }
             AppEvent::JoinGroup(group_id) => {
                 self.navigate_to(PageType::Chat(Some(group_id))).await?;
@@ -355,10 +761,7 @@ impl App {
                 }
             }
             AppEvent::RefreshCurrentPage => {
-                let page_type = self.current_page.page_type();
-                let refreshed = self.load_page_data(page_type).await?;
-                self.current_page = refreshed.clone();
-                let _ = self.state_tx.send(refreshed);
+                self.refresh_current_page().await?;
             }
             AppEvent::MessageReceived { group_id, message } => {
                 if let Page::Chat {
@@ -379,7 +782,7 @@ impl App {
                     log::info!("Processing incoming GiftWrap event: {}", gift_wrap.id);
 
                     // Use extract_rumor to properly unwrap the gift wrap (like whitenoise does)
-                    match nip59::extract_rumor(&self.keys, &gift_wrap).await {
+                    match self.signer.extract_rumor(&gift_wrap).await {
                         Ok(unwrapped) => {
                             // Check if it's a welcome event
                             if unwrapped.rumor.kind == Kind::MlsWelcome {
@@ -397,9 +800,11 @@ impl App {
                                     Ok(welcome) => {
                                         log::info!("Processed welcome for group '{}', now accepting to join", welcome.group_name);
 
-                                        // Get the group IDs from the welcome before accepting
+                                        // Get the group ID from the welcome before accepting
                                         let group_id = welcome.mls_group_id.clone();
-                                        let nostr_group_id = welcome.nostr_group_id;
+                                        // The KeyPackage(s) this welcome used
+                                        let used_key_packages: Vec<EventId> =
+                                            unwrapped.rumor.tags.event_ids().copied().collect();
 
                                         // Accept the welcome to actually join the group
                                         match self.storage.accept_welcome(&welcome) {
@@ -408,18 +813,14 @@ impl App {
                                                     "Successfully joined group: {group_id:?}"
                                                 );
 
-                                                // Subscribe to messages for this group
-                                                let h_tag_value = hex::encode(nostr_group_id);
-                                                let filter = Filter::new()
-                                                    .kind(Kind::MlsGroupMessage)
-                                                    .custom_tag(
-                                                        SingleLetterTag::lowercase(Alphabet::H),
-                                                        h_tag_value,
-                                                    )
-                                                    .limit(100);
-                                                self.client.subscribe(filter, None).await?;
+                                                // Subscribe to messages on the group's relays
+                                                self.subscribe_group(&group_id).await?;
                                                 log::info!("Subscribed to messages for new group");
 
+                                                // Used KeyPackages must not be offered again
+                                                self.retire_used_key_packages(&used_key_packages)
+                                                    .await?;
+
                                                 // Navigate to chat with the new group
                                                 self.navigate_to(PageType::Chat(Some(group_id)))
                                                     .await?;
@@ -430,7 +831,11 @@ impl App {
                                         }
                                     }
                                     Err(e) => {
+                                        // Keep the KeyPackage: it may belong to another client of ours
                                         log::error!("Failed to process welcome: {e}");
+                                        self.error = Some(format!(
+                                            "Couldn't join a group you were invited to. The invite may use a key package from another device. ({e})"
+                                        ));
                                     }
                                 }
                             } else {
@@ -448,12 +853,49 @@ impl App {
                     }
                 }
             }
-            AppEvent::RawMessagesReceived { events } => {
+            AppEvent::RawMessagesReceived { mut events } => {
                 // Process incoming MLS messages
                 log::info!("Received {} MLS message events", events.len());
 
+                // NIP-EE: competing commits apply lowest created_at first, then lowest id
+                events.sort_by(commit_order);
+                for stale in self.commit_ledger.expire(Instant::now(), COMMIT_RETENTION) {
+                    self.mls_snapshots.discard(&stale);
+                }
+                // MLS state of each group from before its first commit in this batch
+                let mut batch_snapshots: HashMap<GroupId, (PathBuf, usize)> = HashMap::new();
+
                 for event in events {
-                    match self.storage.process_message(&event) {
+                    // Our own commits echo back from relays
+                    if self.commit_ledger.is_known_commit(&event.id) {
+                        continue;
+                    }
+
+                    // Snapshot the group first so commits can be described afterwards
+                    let before = self.group_for_event(&event).map(|group_id| {
+                        let snapshot = self.snapshot_group(&group_id);
+                        (group_id, snapshot)
+                    });
+
+                    if let Some((group_id, _)) = &before {
+                        if !batch_snapshots.contains_key(group_id)
+                            && group_messages::is_commit(&self.storage, group_id, &event)
+                        {
+                            match self.mls_snapshots.take(group_id) {
+                                Ok(path) => {
+                                    let replay_from = self.commit_ledger.begin_batch();
+                                    batch_snapshots.insert(group_id.clone(), (path, replay_from));
+                                }
+                                Err(e) => log::warn!("Failed to snapshot MLS state: {e}"),
+                            }
+                        }
+                    }
+                    let snapshot = before
+                        .as_ref()
+                        .and_then(|(group_id, _)| batch_snapshots.get(group_id))
+                        .map(|(path, replay_from)| (path.clone(), *replay_from));
+                    let processed = self.apply_group_event(&event, snapshot);
+                    match processed {
                         Ok(result) => {
                             match result {
                                 MessageProcessingResult::ApplicationMessage(msg) => {
@@ -465,6 +907,14 @@ impl App {
 
                                     // Convert group ID for comparison
                                     let group_id = GroupId::from_slice(msg.mls_group_id.as_slice());
+                                    if let Err(e) = self.group_meta.count_message(&group_id) {
+                                        log::warn!("Failed to count message for key rotation: {e}");
+                                    }
+                                    let sender = msg.pubkey;
+                                    let mut message = Self::chat_message(msg, None);
+                                    if let Some(reply) = &mut message.reply_to {
+                                        reply.parent = self.quoted_message(&reply.id);
+                                    }
 
                                     // Update UI if this is the current chat
                                     if let Page::Chat {
@@ -474,29 +924,39 @@ impl App {
                                     } = &mut self.current_page
                                     {
                                         if *current_group_id == group_id {
-                                            // Add message to current chat
-                                            messages.push(Message {
-                                                content: msg.content.clone(),
-                                                sender: msg.pubkey,
-                                                timestamp: msg.created_at,
-                                            });
+                                            // Add message to current chat; replies that
+                                            // arrived before it can quote it now
+                                            messages.push(message);
+                                            quote_parents(messages);
                                             // Make sure we have their profile metadata
                                             let _ = self
                                                 .profiles
-                                                .ensure(&self.client, vec![msg.pubkey])
+                                                .ensure(&self.client, vec![sender])
                                                 .await;
                                             let _ = self.state_tx.send(self.current_page.clone());
                                         }
                                     }
                                 }
-                                MessageProcessingResult::Proposal(_) => {
-                                    log::info!("Received proposal (not yet handled)");
+                                MessageProcessingResult::Proposal(update) => {
+                                    log::info!("Received an admin's proposal");
+                                    if let Some((group_id, _)) = before {
+                                        self.commit_admin_proposal(
+                                            group_id,
+                                            update.evolution_event,
+                                        )?;
+                                    }
                                 }
                                 MessageProcessingResult::Commit => {
                                     log::info!("Received commit");
+                                    if let Some((group_id, Some(snapshot))) = before {
+                                        self.announce_group_changes(&group_id, &snapshot).await?;
+                                    }
                                 }
                                 MessageProcessingResult::Unprocessable => {
                                     log::debug!("Message was unprocessable (might be duplicate)");
+                                    if !self.handle_member_proposal(&event).await? {
+                                        self.resolve_commit_race(&event).await?;
+                                    }
                                 }
                                 MessageProcessingResult::ExternalJoinProposal => {
                                     log::info!("Received external join proposal (not yet handled)");
@@ -505,9 +965,56 @@ impl App {
                         }
                         Err(e) => {
                             log::error!("Failed to process message: {e}");
+                            // Might be a commit for an epoch we already left via a losing commit
+                            self.resolve_commit_race(&event).await?;
                         }
                     }
                 }
+
+                self.commit_ledger.end_batch();
+                for (snapshot, _) in batch_snapshots.into_values() {
+                    if !self.commit_ledger.uses_snapshot(&snapshot) {
+                        self.mls_snapshots.discard(&snapshot);
+                    }
+                }
+            }
+            AppEvent::MemberJoined {
+                group_id,
+                member,
+                by,
+            } => {
+                let name = member
+                    .display_name
+                    .clone()
+                    .unwrap_or_else(|| self.display_label(&member.public_key));
+                let line = match by {
+                    Some(by) => format!("{} added {name}", self.display_label(&by)),
+                    None => format!("{name} joined the group"),
+                };
+                self.record_system_message(&group_id, &line).await?;
+            }
+            AppEvent::MemberLeft {
+                group_id,
+                member,
+                by,
+            } => {
+                let line = if member == self.signer.public_key() {
+                    // We were removed; keep history but stop treating it as active
+                    self.group_meta.archive(&group_id)?;
+                    match by {
+                        Some(by) => {
+                            format!("{} removed you from the group", self.display_label(&by))
+                        }
+                        None => "You were removed from the group".to_string(),
+                    }
+                } else {
+                    let name = self.display_label(&member);
+                    match by {
+                        Some(by) => format!("{} removed {name}", self.display_label(&by)),
+                        None => format!("{name} left the group"),
+                    }
+                };
+                self.record_system_message(&group_id, &line).await?;
             }
             AppEvent::KeyPackageReceived { event } => {
                 // We don't need to store key packages - we fetch them when needed
@@ -516,11 +1023,134 @@ impl App {
                     event.pubkey.to_bech32().unwrap_or_default()
                 );
             }
-            AppEvent::ProfileMetadataReceived { pubkey, metadata } => {
-                // Cache latest profile metadata and refresh UI
-                self.profiles.cache(pubkey, metadata).await;
-                // Recompute current page to apply new labels
-                let _ = self.refresh_current_page().await;
+            AppEvent::ProfileMetadataReceived {
+                pubkey,
+                metadata,
+                created_at,
+            } => {
+                let before = self.profiles.display_name_async(&pubkey).await;
+                // Older or repeated events are ignored by the cache
+                if self.profiles.cache(pubkey, metadata, created_at).await
+                    && self.profiles.display_name_async(&pubkey).await != before
+                {
+                    // Recompute current page to apply the new label
+                    let _ = self.refresh_current_page().await;
+                }
+                self.check_nip05(pubkey).await;
+            }
+            AppEvent::RevealKey => {
+                if let Page::ExportKey {
+                    stage,
+                    include_nsec,
+                    ..
+                } = &mut self.current_page
+                {
+                    let npub = self.signer.public_key().to_bech32()?;
+                    let ncryptsec = self.key_storage.for_account(&npub).ncryptsec()?;
+                    let nsec = match self.signer.keys() {
+                        Some(keys) if *include_nsec => Some(keys.secret_key().to_bech32()?),
+                        _ => None,
+                    };
+                    *stage = ExportStage::Reveal { ncryptsec, nsec };
+                    let _ = self.state_tx.send(self.current_page.clone());
+
+                    self.export_generation += 1;
+                    let generation = self.export_generation;
+                    let event_tx = self.event_tx.clone();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(EXPORT_CLEAR_AFTER).await;
+                        let _ = event_tx.send(AppEvent::ClearExportedKey(generation));
+                    });
+                }
+            }
+            AppEvent::Lock => {
+                self.lock().await?;
+            }
+            AppEvent::BunkerConnectFinished { result } => match result {
+                // The stores belong to the bunker's account: restart on it
+                Ok(signer) => self.account_switch = Some(AccountSwitch::Bunker(signer)),
+                Err(e) => {
+                    let new_page = Page::Onboarding {
+                        input: String::new(),
+                        mode: OnboardingMode::EnterBunkerUri,
+                        error: Some(e),
+                    };
+                    self.current_page = new_page.clone();
+                    let _ = self.state_tx.send(new_page);
+                }
+            },
+            AppEvent::ClearExportedKey(generation) if generation == self.export_generation => {
+                self.clear_exported_key().await?;
+            }
+            AppEvent::PasswordChangeFinished { error: None } => {
+                if matches!(self.current_page, Page::ChangePassword { .. }) {
+                    self.navigate_back().await?;
+                }
+                self.flash = Some((
+                    "Password changed".to_string(),
+                    Instant::now() + std::time::Duration::from_secs(5),
+                ));
+                let _ = self.state_tx.send(self.current_page.clone());
+            }
+            AppEvent::PasswordChangeFinished { error: Some(e) } => {
+                if let Page::ChangePassword {
+                    step,
+                    input,
+                    old_password,
+                    new_password,
+                    error,
+                } = &mut self.current_page
+                {
+                    // Start over; nothing was changed
+                    *step = PasswordStep::Old;
+                    input.clear();
+                    old_password.clear();
+                    new_password.clear();
+                    *error = Some(e);
+                    let _ = self.state_tx.send(self.current_page.clone());
+                } else {
+                    self.error = Some(format!("Password not changed: {e}"));
+                }
+            }
+            AppEvent::Nip05Checked {
+                pubkey,
+                address,
+                verified,
+            } => {
+                self.nip05_in_flight.remove(&pubkey);
+                if self
+                    .profiles
+                    .record_nip05(pubkey, address, verified, Timestamp::now())
+                    .await
+                {
+                    log::info!("NIP-05 for {pubkey} verified: {verified}");
+                }
+            }
+            AppEvent::Nip05Resolved {
+                input,
+                action,
+                result,
+            } => {
+                let outcome = match result {
+                    Ok(pubkey) => match action {
+                        Nip05Action::Dm => self.dm_command(pubkey, &input).await,
+                        Nip05Action::Nick(petname) => {
+                            self.nick_command(pubkey, &input, petname).await
+                        }
+                    },
+                    Err(e) => Err(anyhow::anyhow!(e)),
+                };
+                self.show_command_outcome(outcome);
+                let _ = self.state_tx.send(self.current_page.clone());
+            }
+            AppEvent::FollowListReceived { event } if event.pubkey == self.signer.public_key() => {
+                let follows = follow_list(&event);
+                let count = self.contacts.import_follows(&follows)?;
+                log::info!("Imported {count} follows from our contact list");
+                let _ = self.profiles.ensure(&self.client, follows).await;
+                if matches!(self.current_page, Page::Contacts { .. }) {
+                    self.refresh_current_page().await?;
+                }
             }
             AppEvent::OpNeedsStorageCreateGroup {
                 op_id,
@@ -528,12 +1158,12 @@ impl App {
                 key_package,
                 group_name,
             } => {
+                // Cancelled from the dashboard while the key package was being fetched
+                if self.ops_store.load(&op_id)?.status == crate::ops::OpStatus::Cancelled {
+                    return Ok(());
+                }
                 // Perform storage-bound group creation and update the op
-                let relay_urls: Result<Vec<RelayUrl>, _> = get_default_relays()
-                    .iter()
-                    .map(|&url| RelayUrl::parse(url))
-                    .collect();
-                let relay_urls = relay_urls?;
+                let relay_urls = self.config.relay_urls()?;
 
                 let config = NostrGroupConfigData::new(
                     group_name.clone(),
@@ -542,11 +1172,11 @@ impl App {
                     None,
                     None,
                     relay_urls,
-                    vec![self.keys.public_key(), other_pubkey],
+                    vec![self.signer.public_key(), other_pubkey],
                 );
 
                 match self.storage.create_group(
-                    &self.keys.public_key(),
+                    &self.signer.public_key(),
                     vec![key_package.clone()],
                     config,
                 ) {
@@ -582,6 +1212,298 @@ impl App {
                     }
                 }
             }
+            AppEvent::OpNeedsStorageCreateMultiGroup {
+                op_id,
+                name,
+                members,
+                key_packages,
+            } => {
+                // Re-sent on every tick until the op moves on: create the group only once
+                if !self.ops_store.load(&op_id)?.awaits_group_creation() {
+                    log::debug!("Op {op_id} already created its group or was stopped");
+                    return Ok(());
+                }
+                let relay_urls = self.config.relay_urls()?;
+
+                // The creator is the only admin of a named group
+                let config = NostrGroupConfigData::new(
+                    name.clone(),
+                    "Group chat".to_string(),
+                    None,
+                    None,
+                    None,
+                    relay_urls,
+                    vec![self.signer.public_key()],
+                );
+
+                match self.storage.create_group(
+                    &self.signer.public_key(),
+                    key_packages.clone(),
+                    config,
+                ) {
+                    Ok(group_result) => {
+                        let nostr_group_id_hex = hex::encode(group_result.group.nostr_group_id);
+                        let welcomes = pair_welcomes(&key_packages, &group_result.welcome_rumors);
+
+                        let mut op = self.ops_store.load(&op_id)?;
+                        op.kind = OperationKind::CreateGroup {
+                            name,
+                            members: members.clone(),
+                            step: CreateGroupStep::SendWelcomes {
+                                nostr_group_id_hex: nostr_group_id_hex.clone(),
+                                welcomes,
+                            },
+                        };
+                        op.status = crate::ops::OpStatus::InProgress;
+                        self.ops_store.save(&op)?;
+                        let _ = self.ops_cmd_tx.send(OpsCommand::Updated(op_id));
+
+                        let group_id =
+                            GroupId::from_slice(group_result.group.mls_group_id.as_slice());
+
+                        // Subscribe to group messages right away
+                        if let Err(e) = self.subscribe_group(&group_id).await {
+                            log::warn!("Failed to subscribe to group messages: {e}");
+                        }
+                        self.navigate_to(PageType::Chat(Some(group_id))).await?;
+                        let _ = self.profiles.ensure(&self.client, members).await;
+                    }
+                    Err(e) => {
+                        log::error!("Failed to create group in storage: {e}");
+                        // Surface the failure in the ops dashboard
+                        self.ops_store.mark_error(
+                            &op_id,
+                            &format!("Failed to create group in storage: {e}"),
+                        )?;
+                    }
+                }
+            }
+            AppEvent::OpNeedsStorageAddMember {
+                op_id,
+                group_id,
+                key_package,
+            } => match self.storage.add_members(&group_id, &[key_package]) {
+                Ok(update) => {
+                    let welcome_rumor = update
+                        .welcome_rumors
+                        .and_then(|rumors| rumors.into_iter().next());
+                    let mut op = self.ops_store.load(&op_id)?;
+                    match (op.kind.clone(), welcome_rumor) {
+                        (
+                            OperationKind::InviteMember {
+                                mls_group_id_hex,
+                                invitee,
+                                ..
+                            },
+                            Some(welcome_rumor),
+                        ) => {
+                            op.kind = OperationKind::InviteMember {
+                                mls_group_id_hex,
+                                invitee,
+                                step: InviteMemberStep::PublishCommit {
+                                    commit_event: update.evolution_event,
+                                    welcome_rumor,
+                                    relays: self.group_relay_urls(&group_id),
+                                },
+                            };
+                            op.status = crate::ops::OpStatus::InProgress;
+                            self.ops_store.save(&op)?;
+                            let _ = self.ops_cmd_tx.send(OpsCommand::Updated(op_id));
+                        }
+                        (_, None) => {
+                            log::error!("No welcome rumor produced by storage.add_members");
+                            self.ops_store
+                                .mark_error(&op_id, "No welcome rumor produced by add_members")?;
+                        }
+                        _ => log::warn!("Op {op_id} is not an InviteMember op"),
+                    }
+                }
+                Err(e) => {
+                    log::error!("Failed to add member in storage: {e}");
+                    self.ops_store
+                        .mark_error(&op_id, &format!("Failed to add member in storage: {e}"))?;
+                }
+            },
+            AppEvent::OpNeedsStorageSelfUpdate { op_id, group_id } => {
+                match self.storage.self_update(&group_id) {
+                    Ok(update) => {
+                        let mut op = self.ops_store.load(&op_id)?;
+                        if let OperationKind::RotateGroupKey {
+                            mls_group_id_hex, ..
+                        } = op.kind.clone()
+                        {
+                            op.kind = OperationKind::RotateGroupKey {
+                                mls_group_id_hex,
+                                step: RotateGroupKeyStep::Publish {
+                                    commit_event: update.evolution_event,
+                                    relays: self.group_relay_urls(&group_id),
+                                },
+                            };
+                            op.status = crate::ops::OpStatus::InProgress;
+                            self.ops_store.save(&op)?;
+                            let _ = self.ops_cmd_tx.send(OpsCommand::Updated(op_id));
+                        } else {
+                            log::warn!("Op {op_id} is not a RotateGroupKey op");
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("Failed to build self-update commit: {e}");
+                        self.ops_store
+                            .mark_error(&op_id, &format!("Failed to build self-update: {e}"))?;
+                    }
+                }
+            }
+            AppEvent::ProcessPendingOperationsTick => {
+                // Keys aren't final until onboarding is done
+                if matches!(
+                    self.current_page,
+                    Page::Onboarding { .. } | Page::Initializing { .. }
+                ) {
+                    return Ok(());
+                }
+                self.queued_sends = self.ops_store.queued_sends().unwrap_or(0);
+                if let Err(e) = self.schedule_key_rotations() {
+                    log::warn!("Failed to schedule key rotations: {e}");
+                }
+                if let Err(e) = self.maintain_key_packages().await {
+                    log::warn!("Failed to maintain key packages: {e}");
+                }
+            }
+            AppEvent::KeyPackagePublished { event_id } => {
+                self.key_packages.mark_published(&event_id)?;
+            }
+            AppEvent::RelayStatusUpdated { relays } => {
+                self.relays = relays;
+                if matches!(self.current_page, Page::Relays { .. }) {
+                    self.refresh_current_page().await?;
+                }
+            }
+            AppEvent::RelayAuthChallenge {
+                relay_url,
+                challenge,
+            } => {
+                if !self.config.auth_enabled_for(&relay_url) {
+                    log::info!("{relay_url} requested AUTH; not enabled for it in config");
+                    self.relay_activity
+                        .record_error(&relay_url, "AUTH requested; not enabled in config");
+                } else if matches!(self.current_page, Page::Onboarding { .. }) {
+                    // Only the real keys may answer; wait until they're unlocked
+                    self.pending_auth_challenges.insert(relay_url, challenge);
+                } else {
+                    self.answer_auth_challenge(&relay_url, &challenge).await;
+                }
+            }
+            AppEvent::NetworkError { error } => {
+                log::warn!("Network error: {error}");
+                self.error = Some(error);
+                let _ = self.state_tx.send(self.current_page.clone());
+            }
+            AppEvent::ConnectionStatusChanged(status) => {
+                let was_connected = self.connection_status == ConnectionStatus::Connected;
+                self.connection_status = status;
+                if self.connection_status == ConnectionStatus::Connected && !was_connected {
+                    // Flush whatever queued up while we were offline
+                    let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+                }
+                self.queued_sends = self.ops_store.queued_sends().unwrap_or(0);
+            }
+            AppEvent::OpNeedsStorageMergeCommit {
+                op_id,
+                group_id,
+                commit_event,
+            } => {
+                if !self.ops_store.load(&op_id)?.awaits_merge() {
+                    log::debug!("Op {op_id} already merged its commit or was stopped");
+                    return Ok(());
+                }
+                // Keep the pre-merge state in case a competing commit wins later
+                let epoch_before = self.group_epoch(&group_id);
+                let snapshot = self.mls_snapshots.take(&group_id).ok();
+                match self.storage.merge_pending_commit(&group_id) {
+                    Ok(()) => {
+                        match (epoch_before, snapshot) {
+                            (Some(epoch), Some(snapshot)) => {
+                                let replay_from = self.commit_ledger.begin_batch();
+                                self.commit_ledger.record_commit(AppliedCommit {
+                                    group_id: group_id.clone(),
+                                    epoch,
+                                    event_id: commit_event.id,
+                                    created_at: commit_event.created_at,
+                                    snapshot,
+                                    replay_from,
+                                    op_id: Some(op_id.clone()),
+                                    applied_at: Instant::now(),
+                                });
+                            }
+                            (None, Some(snapshot)) => self.mls_snapshots.discard(&snapshot),
+                            _ => {}
+                        }
+
+                        let mut op = self.ops_store.load(&op_id)?;
+                        match op.kind.clone() {
+                            OperationKind::InviteMember {
+                                mls_group_id_hex,
+                                invitee,
+                                step: InviteMemberStep::MergeCommit { welcome_rumor, .. },
+                            } => {
+                                // Welcome can go out now that the Add commit is applied
+                                op.kind = OperationKind::InviteMember {
+                                    mls_group_id_hex,
+                                    invitee,
+                                    step: InviteMemberStep::SendWelcome { welcome_rumor },
+                                };
+                                op.status = crate::ops::OpStatus::InProgress;
+                                self.ops_store.save(&op)?;
+                                let _ = self.ops_cmd_tx.send(OpsCommand::Updated(op_id));
+                            }
+                            OperationKind::GroupEvolution {
+                                mls_group_id_hex,
+                                description,
+                                ..
+                            } => {
+                                op.kind = OperationKind::GroupEvolution {
+                                    mls_group_id_hex,
+                                    description,
+                                    step: GroupEvolutionStep::Done,
+                                };
+                                self.ops_store.save(&op)?;
+                                self.ops_store.mark_success(&op_id)?;
+                            }
+                            OperationKind::RotateGroupKey {
+                                mls_group_id_hex, ..
+                            } => {
+                                op.kind = OperationKind::RotateGroupKey {
+                                    mls_group_id_hex,
+                                    step: RotateGroupKeyStep::Done,
+                                };
+                                self.ops_store.save(&op)?;
+                                self.ops_store.mark_success(&op_id)?;
+                                self.group_meta.record_key_rotation(&group_id)?;
+                            }
+                            _ => log::warn!("Op {op_id} has no pending commit to merge"),
+                        }
+
+                        // The commit may have moved the group to other relays
+                        if let Err(e) = self.subscribe_group(&group_id).await {
+                            log::warn!("Failed to resubscribe to group messages: {e}");
+                        }
+
+                        // Membership changed; reload the chat if it's on screen
+                        if self.current_group_id().as_ref() == Some(&group_id) {
+                            self.refresh_current_page().await?;
+                        }
+                    }
+                    Err(e) => {
+                        if let Some(snapshot) = snapshot {
+                            self.mls_snapshots.discard(&snapshot);
+                        }
+                        log::error!("Failed to merge pending commit: {e}");
+                        // The pending commit is gone or stale; publishing it again won't help
+                        self.ops_store
+                            .mark_failed(&op_id, &format!("Failed to merge pending commit: {e}"))?;
+                    }
+                }
+            }
             _ => {}
         }
         Ok(())
@@ -597,6 +1519,11 @@ impl App {
             let _ = self.state_tx.send(self.current_page.clone());
         }
 
+        // An open modal captures all keys
+        if self.modal.is_some() {
+            return self.handle_modal_key(key_event.code);
+        }
+
         // Extract necessary data first to avoid borrowing conflicts
         let key_code = key_event.code;
         let key_modifiers = key_event.modifiers;
@@ -650,37 +1577,42 @@ impl App {
                     let _ = self.state_tx.send(self.current_page.clone());
                 }
             }
-            (Page::Chat { input, .. }, KeyCode::Enter) => {
-                if !input.is_empty() {
-                    let input_content = input.clone();
+            (Page::Chat { input, .. }, KeyCode::Enter) if !input.is_empty() => {
+                let input_content = input.clone();
 
-                    // Clear input immediately
-                    if let Page::Chat { input, .. } = &mut self.current_page {
-                        input.clear();
-                        let _ = self.state_tx.send(self.current_page.clone());
-                    }
+                // Clear input immediately
+                if let Page::Chat { input, .. } = &mut self.current_page {
+                    input.clear();
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
 
-                    // Check if it's a command
-                    if input_content.starts_with("/") {
-                        match self.process_command(input_content).await {
-                            Ok(CommandOutcome::Noop) => {
-                                self.error = None;
-                            }
-                            Ok(CommandOutcome::Flash(msg)) => {
-                                self.error = None;
-                                self.flash = Some((
-                                    msg,
-                                    std::time::Instant::now() + std::time::Duration::from_secs(5),
-                                ));
-                            }
-                            Err(e) => {
-                                self.error = Some(format!("{e:#}"));
-                            }
-                        }
-                    } else {
-                        // Regular message
-                        self.send_event(AppEvent::SendMessage(input_content))?;
-                    }
+                // Check if it's a command
+                if input_content.starts_with("/") {
+                    let outcome = self.process_command(input_content).await;
+                    self.show_command_outcome(outcome);
+                } else {
+                    // Regular message
+                    self.send_event(AppEvent::SendMessage(input_content))?;
+                }
+            }
+            (Page::Chat { .. }, KeyCode::Up | KeyCode::Down)
+                if key_modifiers.contains(KeyModifiers::ALT) =>
+            {
+                self.move_message_selection(key_code == KeyCode::Up);
+            }
+            (
+                Page::Chat {
+                    selected_message: Some(_),
+                    ..
+                },
+                KeyCode::Esc,
+            ) => {
+                if let Page::Chat {
+                    selected_message, ..
+                } = &mut self.current_page
+                {
+                    *selected_message = None;
+                    let _ = self.state_tx.send(self.current_page.clone());
                 }
             }
             (
@@ -715,6 +1647,206 @@ impl App {
                 }
             }
 
+            (Page::OpsDashboard { show_detail, .. }, KeyCode::Esc) if *show_detail => {
+                if let Page::OpsDashboard { show_detail, .. } = &mut self.current_page {
+                    *show_detail = false;
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
+            }
+            (Page::OpsDashboard { .. }, KeyCode::Up | KeyCode::Down | KeyCode::Enter) => {
+                if let Page::OpsDashboard {
+                    items,
+                    selected,
+                    show_detail,
+                } = &mut self.current_page
+                {
+                    match key_code {
+                        KeyCode::Up => *selected = selected.saturating_sub(1),
+                        KeyCode::Down => {
+                            *selected = (*selected + 1).min(items.len().saturating_sub(1))
+                        }
+                        _ => *show_detail = !*show_detail && !items.is_empty(),
+                    }
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
+            }
+            (
+                Page::OpsDashboard {
+                    items, selected, ..
+                },
+                KeyCode::Char('r'),
+            ) => {
+                if let Some(item) = items.get(*selected) {
+                    let op_id = item.id.clone();
+                    match self.retry_operation(&op_id).await {
+                        Ok(()) => {
+                            self.flash = Some((
+                                "Retrying operation".to_string(),
+                                std::time::Instant::now() + std::time::Duration::from_secs(3),
+                            ));
+                        }
+                        Err(e) => self.error = Some(format!("{e:#}")),
+                    }
+                }
+            }
+            (
+                Page::OpsDashboard {
+                    items, selected, ..
+                },
+                KeyCode::Char('c'),
+            ) => {
+                if let Some(item) = items.get(*selected) {
+                    self.modal = Some(Modal::Confirm {
+                        message: format!("Cancel {} operation {}?", item.kind, item.id),
+                        on_confirm: ModalAction::CancelOperation(item.id.clone()),
+                    });
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
+            }
+
+            (Page::Contacts { .. }, KeyCode::Up | KeyCode::Down) => {
+                if let Page::Contacts { contacts, selected } = &mut self.current_page {
+                    match key_code {
+                        KeyCode::Up => *selected = selected.saturating_sub(1),
+                        _ => *selected = (*selected + 1).min(contacts.len().saturating_sub(1)),
+                    }
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
+            }
+            (Page::Contacts { contacts, selected }, KeyCode::Enter) => {
+                if let Some(contact) = contacts.get(*selected) {
+                    let pubkey = contact.public_key;
+                    let label = self.display_label(&pubkey);
+                    match self.create_dm_with(pubkey).await {
+                        Ok(()) => {
+                            // Still here if the key package is being fetched in the background
+                            if matches!(self.current_page, Page::Contacts { .. }) {
+                                self.navigate_to(PageType::Chat(None)).await?;
+                                self.flash = Some((
+                                    format!("Starting DM with {label}..."),
+                                    std::time::Instant::now() + std::time::Duration::from_secs(5),
+                                ));
+                            }
+                        }
+                        Err(e) => self.error = Some(format!("{e:#}")),
+                    }
+                }
+            }
+
+            (Page::ChangePassword { step, .. }, KeyCode::Char(c))
+                if *step != PasswordStep::Saving =>
+            {
+                if let Page::ChangePassword { input, error, .. } = &mut self.current_page {
+                    input.push(c);
+                    *error = None;
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
+            }
+            (Page::ChangePassword { .. }, KeyCode::Backspace) => {
+                if let Page::ChangePassword { input, .. } = &mut self.current_page {
+                    input.pop();
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
+            }
+            (Page::ChangePassword { .. }, KeyCode::Enter) => {
+                self.advance_password_change()?;
+            }
+
+            (
+                Page::ExportKey {
+                    stage: ExportStage::Password,
+                    ..
+                },
+                KeyCode::Char(c),
+            ) => {
+                if let Page::ExportKey { input, error, .. } = &mut self.current_page {
+                    input.push(c);
+                    *error = None;
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
+            }
+            (
+                Page::ExportKey {
+                    stage: ExportStage::Password,
+                    ..
+                },
+                KeyCode::Backspace,
+            ) => {
+                if let Page::ExportKey { input, .. } = &mut self.current_page {
+                    input.pop();
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
+            }
+            (
+                Page::ExportKey {
+                    stage: ExportStage::Password,
+                    ..
+                },
+                KeyCode::Enter,
+            ) => {
+                self.confirm_export_password()?;
+            }
+            (
+                Page::ExportKey {
+                    stage: ExportStage::Reveal { ncryptsec, nsec },
+                    ..
+                },
+                KeyCode::Char(c @ ('c' | 'n')),
+            ) => {
+                let secret = if c == 'c' {
+                    Some(ncryptsec.clone())
+                } else {
+                    nsec.clone()
+                };
+                if let Some(secret) = secret {
+                    match self.copy_secret(secret) {
+                        Ok(()) => {
+                            self.flash = Some((
+                                format!(
+                                    "Copied; clipboard clears in {}s",
+                                    EXPORT_CLEAR_AFTER.as_secs()
+                                ),
+                                Instant::now() + std::time::Duration::from_secs(5),
+                            ));
+                        }
+                        Err(e) => self.error = Some(format!("{e:#}")),
+                    }
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
+            }
+
+            (Page::Accounts { .. }, KeyCode::Up | KeyCode::Down) => {
+                if let Page::Accounts {
+                    accounts, selected, ..
+                } = &mut self.current_page
+                {
+                    match key_code {
+                        KeyCode::Up => *selected = selected.saturating_sub(1),
+                        _ => *selected = (*selected + 1).min(accounts.len().saturating_sub(1)),
+                    }
+                    let _ = self.state_tx.send(self.current_page.clone());
+                }
+            }
+            (
+                Page::Accounts {
+                    accounts,
+                    current,
+                    selected,
+                },
+                KeyCode::Enter,
+            ) => {
+                if let Some(account) = accounts.get(*selected) {
+                    if account.npub == *current {
+                        self.navigate_back().await?;
+                    } else {
+                        self.account_switch = Some(AccountSwitch::Unlock(account.npub.clone()));
+                    }
+                }
+            }
+            (Page::Accounts { .. }, KeyCode::Char('n')) => {
+                self.account_switch = Some(AccountSwitch::Create);
+            }
+
             (_, KeyCode::Esc) => {
                 // Don't allow escape during onboarding or initialization
                 match self.current_page {
@@ -730,7 +1862,8 @@ impl App {
                     }
                 }
             }
-            (_, KeyCode::F(1)) => {
+            // Nothing but the password prompt while locked
+            (_, KeyCode::F(1)) if self.locked.is_none() => {
                 self.navigate_to(PageType::Help).await?;
             }
             // Optional: Ctrl+O to view ops dashboard
@@ -745,13 +1878,47 @@ impl App {
         Ok(())
     }
 
-    async fn handle_onboarding_enter(
-        &mut self,
-        input: String,
-        mode: crate::ui_state::OnboardingMode,
-    ) -> Result<()> {
-        use crate::ui_state::OnboardingMode;
+    fn handle_modal_key(&mut self, key_code: crossterm::event::KeyCode) -> Result<()> {
+        use crossterm::event::KeyCode;
+
+        // Taking the modal closes it; Error/Info modals close on any key
+        if let Some(Modal::Confirm {
+            message,
+            on_confirm,
+        }) = self.modal.take()
+        {
+            match key_code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => match on_confirm {
+                    ModalAction::LeaveGroup(group_id) => {
+                        self.send_event(AppEvent::LeaveGroup(group_id))?;
+                    }
+                    ModalAction::CancelOperation(op_id) => {
+                        self.send_event(AppEvent::CancelOperation(op_id))?;
+                    }
+                    ModalAction::RevealKey => {
+                        self.send_event(AppEvent::RevealKey)?;
+                    }
+                },
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    // Declining an export leaves the export page too
+                    if on_confirm == ModalAction::RevealKey {
+                        self.send_event(AppEvent::NavigateBack)?;
+                    }
+                }
+                _ => {
+                    // Keep asking until we get a clear answer
+                    self.modal = Some(Modal::Confirm {
+                        message,
+                        on_confirm,
+                    });
+                }
+            }
+        }
+        let _ = self.state_tx.send(self.current_page.clone());
+        Ok(())
+    }
 
+    async fn handle_onboarding_enter(&mut self, input: String, mode: OnboardingMode) -> Result<()> {
         match mode {
             OnboardingMode::Choose => match input.as_str() {
                 "1" => {
@@ -773,10 +1940,97 @@ impl App {
                     self.current_page = new_page.clone();
                     let _ = self.state_tx.send(new_page);
                 }
+                "3" => {
+                    let new_page = Page::Onboarding {
+                        input: String::new(),
+                        mode: OnboardingMode::EnterBunkerUri,
+                        error: None,
+                    };
+                    self.current_page = new_page.clone();
+                    let _ = self.state_tx.send(new_page);
+                }
+                _ => {
+                    let mut new_page = self.current_page.clone();
+                    if let Page::Onboarding { error, input, .. } = &mut new_page {
+                        *error = Some("Invalid choice. Enter 1, 2 or 3.".to_string());
+                        input.clear();
+                    }
+                    self.current_page = new_page.clone();
+                    let _ = self.state_tx.send(new_page);
+                }
+            },
+            OnboardingMode::SelectAccount => {
+                let choice = input.trim().parse::<usize>().ok();
+                match choice {
+                    Some(n) if n >= 1 && n <= self.accounts.len() => {
+                        let npub = self.accounts[n - 1].npub.clone();
+                        if self.key_storage.account().as_deref() == Some(npub.as_str()) {
+                            // This session already runs on that account's storage
+                            self.key_storage = self.key_storage.for_account(&npub);
+                            let new_page = Page::Onboarding {
+                                input: String::new(),
+                                mode: OnboardingMode::EnterPassword,
+                                error: None,
+                            };
+                            self.current_page = new_page.clone();
+                            let _ = self.state_tx.send(new_page);
+                        } else {
+                            self.account_switch = Some(AccountSwitch::Unlock(npub));
+                        }
+                    }
+                    Some(n) if n == self.accounts.len() + 1 => {
+                        self.account_switch = Some(AccountSwitch::Create);
+                    }
+                    _ => {
+                        let mut new_page = self.current_page.clone();
+                        if let Page::Onboarding { error, input, .. } = &mut new_page {
+                            *error = Some(format!(
+                                "Invalid choice. Enter 1 to {}.",
+                                self.accounts.len() + 1
+                            ));
+                            input.clear();
+                        }
+                        self.current_page = new_page.clone();
+                        let _ = self.state_tx.send(new_page);
+                    }
+                }
+            }
+            OnboardingMode::ImportExisting => match Keys::parse(input.trim()) {
+                // The orchestrator and stores belong to the imported account: restart on it
+                Ok(keys) => self.account_switch = Some(AccountSwitch::Import(keys)),
+                Err(_) => {
+                    let mut new_page = self.current_page.clone();
+                    if let Page::Onboarding { error, input, .. } = &mut new_page {
+                        *error = Some("Invalid private key. Paste an nsec.".to_string());
+                        input.clear();
+                    }
+                    self.current_page = new_page.clone();
+                    let _ = self.state_tx.send(new_page);
+                }
+            },
+            OnboardingMode::EnterBunkerUri => match NostrConnectURI::parse(input.trim()) {
+                Ok(uri @ NostrConnectURI::Bunker { .. }) => {
+                    // The bunker may wait for its user to approve us
+                    let new_page = Page::Initializing {
+                        message: "Waiting for your bunker to accept nrc...".to_string(),
+                        progress: 0.0,
+                    };
+                    self.current_page = new_page.clone();
+                    let _ = self.state_tx.send(new_page);
+
+                    let event_tx = self.event_tx.clone();
+                    tokio::spawn(async move {
+                        let result = AccountSigner::connect_bunker(uri, Keys::generate())
+                            .await
+                            .map(Box::new)
+                            .map_err(|e| format!("{e:#}"));
+                        let _ = event_tx.send(AppEvent::BunkerConnectFinished { result });
+                    });
+                }
                 _ => {
                     let mut new_page = self.current_page.clone();
                     if let Page::Onboarding { error, input, .. } = &mut new_page {
-                        *error = Some("Invalid choice. Enter 1 or 2.".to_string());
+                        *error = Some("Invalid URI. Paste a bunker:// URI.".to_string());
                         input.clear();
                     }
                     self.current_page = new_page.clone();
@@ -800,14 +2054,20 @@ impl App {
                 if input.len() >= 8 {
                     if mode == OnboardingMode::EnterPassword {
                         // Validate password by trying to load encrypted keys
-                        match self.key_storage.load_encrypted(&input) {
-                            Ok(loaded_keys) => {
+                        match self.key_storage.load_signer(&input) {
+                            Ok(signer) if self.locked.is_some() => {
+                                self.unlock(signer).await?;
+                            }
+                            Ok(signer) => {
                                 // Password is correct, update keys
-                                self.keys = loaded_keys;
+                                self.signer = signer;
+                                self.resume_signing().await;
                                 self.navigate_to(PageType::Initializing).await?;
+                                self.answer_pending_auth_challenges().await;
+                                self.sync_follow_list();
 
-                                // Initialize MLS and publish key package
-                                self.publish_key_package().await?;
+                                // Initialize MLS and top up our key packages
+                                self.maintain_key_packages().await?;
                                 // Publish profile if we captured a display name
                                 self.publish_profile_name_if_needed().await?;
 
@@ -836,9 +2096,11 @@ impl App {
                             });
 
                         self.navigate_to(PageType::Initializing).await?;
+                        self.answer_pending_auth_challenges().await;
+                        self.sync_follow_list();
 
-                        // Initialize MLS and publish key package
-                        self.publish_key_package().await?;
+                        // Initialize MLS and top up our key packages
+                        self.maintain_key_packages().await?;
                         // Publish profile if we captured a display name
                         self.publish_profile_name_if_needed().await?;
 
@@ -856,7 +2118,6 @@ impl App {
                     let _ = self.state_tx.send(new_page);
                 }
             }
-            _ => {}
         }
         Ok(())
     }
@@ -877,20 +2138,25 @@ impl App {
 
         for group in groups {
             let id = group.mls_group_id.clone();
+            // Groups left behind by a cancelled DM
+            if self.group_meta.is_discarded(&id)? {
+                continue;
+            }
             let messages = self.storage.get_messages(&id)?;
-            let last_message = messages.last().map(|m| Message {
-                content: m.content.clone(),
-                sender: m.pubkey,
-                timestamp: Timestamp::now(),
-            });
+            let last_message = messages.last().map(|m| Self::chat_message(m.clone(), None));
 
             // Compute a safe UI label for DMs:
             // - If we can infer peer from any admin entry not me, use that pk
             // - Else, infer from any message not sent by me
             // - Else, if the stored name is "DM with <npub>" and that npub != me, use it
             // - Label is the peer's display name if cached, otherwise "loading" (never show my own name)
-            let me = self.keys.public_key();
+            // Named (multi-member) groups keep their own name.
+            let me = self.signer.public_key();
+            let is_dm = group.description == "Direct message" || group.name.starts_with("DM with ");
             let mut label: Option<String> = None;
+            if !is_dm {
+                label = Some(group.name.clone());
+            }
             if label.is_none() {
                 if let Some(full_group) = self.storage.get_group(&id)? {
                     if let Some(pk) = full_group
@@ -899,35 +2165,42 @@ impl App {
                         .find(|pk| **pk != me)
                         .cloned()
                     {
-                        let name = self.profiles.display_name(&pk);
+                        let name = self.known_name(&pk);
                         dms_to_subscribe.push(pk);
                         label = Some(name.unwrap_or_else(|| "loading".to_string()));
                     }
                 }
             }
-            if let Some(pk) = messages.iter().map(|m| m.pubkey).find(|p| *p != me) {
-                let name = self.profiles.display_name(&pk);
+            if !is_dm {
+                // Label already set from the group name
+            } else if let Some(pk) = messages.iter().map(|m| m.pubkey).find(|p| *p != me) {
+                let name = self.known_name(&pk);
                 dms_to_subscribe.push(pk);
                 label = Some(name.unwrap_or_else(|| "loading".to_string()));
             } else if let Some(rest) = group.name.strip_prefix("DM with ") {
                 if let Ok(pk) = PublicKey::from_bech32(rest.trim()) {
                     if pk != me {
-                        let name = self.profiles.display_name(&pk);
+                        let name = self.known_name(&pk);
                         dms_to_subscribe.push(pk);
                         label = Some(name.unwrap_or_else(|| "loading".to_string()));
                     }
                 }
             }
 
+            let archived = self.group_meta.is_archived(&id)?;
             summaries.push(GroupSummary {
                 id,
                 name: label.unwrap_or_else(|| "loading".to_string()),
                 member_count: 0,
                 last_message,
                 unread_count: 0,
+                archived,
             });
         }
 
+        // Left groups sink to the bottom of the sidebar
+        summaries.sort_by_key(|g| g.archived);
+
         if !dms_to_subscribe.is_empty() {
             let _ = self.profiles.ensure(&self.client, dms_to_subscribe).await;
         }
@@ -937,23 +2210,26 @@ impl App {
 
     async fn load_chat_messages(&self, group_id: &GroupId, limit: usize) -> Result<Vec<Message>> {
         let stored_messages = self.storage.get_messages(group_id)?;
-        let messages: Vec<Message> = stored_messages
+        let deliveries = self.deliveries.for_group(group_id)?;
+        let mut all: Vec<Message> = stored_messages
             .into_iter()
-            .rev()
-            .take(limit)
-            .rev()
-            .map(|m| Message {
-                content: m.content,
-                sender: m.pubkey,
-                timestamp: Timestamp::now(),
+            .map(|m| {
+                let delivery = deliveries.get(&m.wrapper_event_id).cloned();
+                Self::chat_message(m, delivery)
             })
             .collect();
+        // Every stored message is loaded here, so replies find their parents without queries
+        quote_parents(&mut all);
+        // Interleave local membership/rename notices by time
+        all.extend(self.system_messages_for(group_id)?);
+        all.sort_by_key(|m| m.timestamp);
+        let messages: Vec<Message> = all.into_iter().rev().take(limit).rev().collect();
         // Ensure profile metadata for all observed senders
         let unique_senders: Vec<PublicKey> = {
             use std::collections::HashSet;
             let mut set = HashSet::new();
             let mut list = Vec::new();
-            for msg in &messages {
+            for msg in messages.iter().filter(|m| !m.system) {
                 if set.insert(msg.sender) {
                     list.push(msg.sender);
                 }
@@ -989,7 +2265,7 @@ impl App {
             "/npub" | "/n" => {
                 // Copy npub to clipboard
                 let npub = self
-                    .keys
+                    .signer
                     .public_key()
                     .to_bech32()
                     .context("Failed to convert public key to bech32")?;
@@ -1005,42 +2281,361 @@ impl App {
             }
             "/dm" | "/d" => {
                 if parts.len() < 2 {
-                    return Err(anyhow::anyhow!("Usage: /dm <npub>"));
+                    return Err(anyhow::anyhow!("Usage: /dm <npub|name@domain>"));
+                }
+
+                // An npub, or a NIP-05 address to resolve
+                let npub_str = parts[1];
+                match self.pubkey_or_lookup(npub_str, Nip05Action::Dm)? {
+                    Some(pubkey) => self.dm_command(pubkey, npub_str).await,
+                    None => Ok(CommandOutcome::Flash(format!("Looking up {npub_str}…"))),
+                }
+            }
+            "/export" => {
+                let include_nsec = match parts.get(1) {
+                    None => false,
+                    Some(&"nsec") => true,
+                    Some(_) => return Err(anyhow::anyhow!("Usage: /export [nsec]")),
+                };
+                if self.signer.is_bunker() {
+                    return Err(anyhow::anyhow!(
+                        "Your key is held by your bunker; back it up there"
+                    ));
+                }
+                self.navigate_to(PageType::ExportKey(include_nsec)).await?;
+                Ok(CommandOutcome::Noop)
+            }
+            "/lock" => {
+                self.send_event(AppEvent::Lock)?;
+                Ok(CommandOutcome::Noop)
+            }
+            "/password" => {
+                self.navigate_to(PageType::ChangePassword).await?;
+                Ok(CommandOutcome::Noop)
+            }
+            "/accounts" => {
+                self.navigate_to(PageType::Accounts).await?;
+                Ok(CommandOutcome::Noop)
+            }
+            "/contacts" => {
+                self.sync_follow_list();
+                self.navigate_to(PageType::Contacts).await?;
+                Ok(CommandOutcome::Noop)
+            }
+            "/nick" => {
+                if parts.len() < 2 {
+                    return Err(anyhow::anyhow!("Usage: /nick <npub|name@domain> [name]"));
+                }
+                let who = parts[1];
+                let petname = Some(parts[2..].join(" ")).filter(|p| !p.is_empty());
+                match self.pubkey_or_lookup(who, Nip05Action::Nick(petname.clone()))? {
+                    Some(pubkey) => self.nick_command(pubkey, who, petname).await,
+                    None => Ok(CommandOutcome::Flash(format!("Looking up {who}…"))),
+                }
+            }
+            "/invite" | "/i" => {
+                if parts.len() < 2 {
+                    return Err(anyhow::anyhow!("Usage: /invite <npub>"));
+                }
+
+                let npub_str = parts[1];
+                let pubkey = PublicKey::from_bech32(npub_str)
+                    .with_context(|| format!("'{npub_str}' is not a valid npub"))?;
+                let group_id = self
+                    .current_group_id()
+                    .ok_or_else(|| anyhow::anyhow!("Open a group before inviting members"))?;
+
+                self.invite_member(group_id, pubkey)
+                    .await
+                    .with_context(|| format!("Failed to invite {npub_str}"))?;
+
+                Ok(CommandOutcome::Flash(format!("Inviting {npub_str}...")))
+            }
+            "/kick" | "/k" => {
+                if parts.len() < 2 {
+                    return Err(anyhow::anyhow!("Usage: /kick <npub>"));
                 }
 
-                // Parse the npub
                 let npub_str = parts[1];
                 let pubkey = PublicKey::from_bech32(npub_str)
                     .with_context(|| format!("'{npub_str}' is not a valid npub"))?;
+                let group_id = self
+                    .current_group_id()
+                    .ok_or_else(|| anyhow::anyhow!("Open a group before removing members"))?;
 
-                // Fetch their key package and create group
-                self.create_dm_with(pubkey)
+                self.kick_member(group_id, pubkey)
                     .await
-                    .with_context(|| format!("Failed to create DM with {npub_str}"))?;
+                    .with_context(|| format!("Failed to remove {npub_str}"))?;
+
+                Ok(CommandOutcome::Flash(format!("Removing {npub_str}...")))
+            }
+            "/leave" => {
+                let group_id = self
+                    .current_group_id()
+                    .ok_or_else(|| anyhow::anyhow!("Open a group to leave it"))?;
+                let group_name = match &self.current_page {
+                    Page::Chat { group_info, .. } => group_info.name.clone(),
+                    _ => String::new(),
+                };
 
+                self.modal = Some(Modal::Confirm {
+                    message: format!("Leave '{group_name}'? You won't see new messages."),
+                    on_confirm: ModalAction::LeaveGroup(group_id),
+                });
+                Ok(CommandOutcome::Noop)
+            }
+            "/info" => {
+                let group_id = self
+                    .current_group_id()
+                    .ok_or_else(|| anyhow::anyhow!("Open a group first"))?;
+                self.navigate_to(PageType::GroupInfo(group_id)).await?;
+                Ok(CommandOutcome::Noop)
+            }
+            "/relays" if parts.len() == 1 => {
+                self.navigate_to(PageType::Relays).await?;
                 Ok(CommandOutcome::Noop)
             }
+            "/relays" => {
+                if parts.len() < 3 || !matches!(parts[1], "add" | "remove") {
+                    return Err(anyhow::anyhow!("Usage: /relays [add|remove <url>]"));
+                }
+                let url = RelayUrl::parse(parts[2])
+                    .with_context(|| format!("'{}' is not a valid relay URL", parts[2]))?;
+                let group_id = self
+                    .current_group_id()
+                    .ok_or_else(|| anyhow::anyhow!("Open a group first"))?;
+
+                let adding = parts[1] == "add";
+                self.update_group_relays(group_id, &url, adding).await?;
+                let verb = if adding { "Adding" } else { "Removing" };
+                Ok(CommandOutcome::Flash(format!("{verb} relay {url}...")))
+            }
+            "/retry" => {
+                let group_id = self
+                    .current_group_id()
+                    .ok_or_else(|| anyhow::anyhow!("Open a group first"))?;
+                match self.retry_failed_messages(&group_id)? {
+                    0 => Ok(CommandOutcome::Flash("No failed messages".to_string())),
+                    n => Ok(CommandOutcome::Flash(format!(
+                        "Resending {n} message(s)..."
+                    ))),
+                }
+            }
+            "/commit" => {
+                let group_id = self
+                    .current_group_id()
+                    .ok_or_else(|| anyhow::anyhow!("Open a group first"))?;
+                let count = self.commit_pending_proposals(group_id)?;
+                Ok(CommandOutcome::Flash(format!(
+                    "Committing {count} pending proposal(s)..."
+                )))
+            }
+            "/group" | "/g" => {
+                if parts.len() < 3 {
+                    return Err(anyhow::anyhow!("Usage: /group <name> <npub> [npub...]"));
+                }
+
+                let name = parts[1].to_string();
+                let mut members = Vec::new();
+                for npub_str in &parts[2..] {
+                    let pubkey = PublicKey::from_bech32(npub_str)
+                        .with_context(|| format!("'{npub_str}' is not a valid npub"))?;
+                    members.push(pubkey);
+                }
+
+                let count = self.create_group_with(name.clone(), members).await?;
+
+                Ok(CommandOutcome::Flash(format!(
+                    "Creating group '{name}' with {count} member(s)..."
+                )))
+            }
             _ => Err(anyhow::anyhow!("Unknown command: {}", parts[0])),
         };
 
         res
     }
 
-    async fn publish_key_package(&mut self) -> Result<()> {
-        let relays: Result<Vec<RelayUrl>, _> = get_default_relays()
-            .iter()
-            .map(|&url| RelayUrl::parse(url))
-            .collect();
-        let relays = relays.context("Invalid relay URLs")?;
+    /// Relays a group's events go to, as recorded in its MLS group data
+    fn group_relay_urls(&self, group_id: &GroupId) -> Vec<RelayUrl> {
+        match self.storage.get_relays(group_id) {
+            Ok(relays) if !relays.is_empty() => relays.into_iter().collect(),
+            _ => self.config.relay_urls().unwrap_or_default(),
+        }
+    }
+
+    /// Listen for a group's kind 445 events on its own relays.
+    ///
+    /// No-op if we're already subscribed on the same set, so it's safe to call after
+    /// every commit to pick up relay changes.
+    async fn subscribe_group(&mut self, group_id: &GroupId) -> Result<()> {
+        let relays = self.group_relay_urls(group_id);
+        if let Some((sub_id, current)) = self.group_subscriptions.get(group_id) {
+            if *current == relays {
+                return Ok(());
+            }
+            self.client.unsubscribe(sub_id).await;
+        }
+
+        let group = self
+            .storage
+            .get_group(group_id)?
+            .ok_or_else(|| anyhow::anyhow!("Group not found"))?;
+        let filter = Filter::new()
+            .kind(Kind::MlsGroupMessage)
+            .custom_tag(
+                SingleLetterTag::lowercase(Alphabet::H),
+                hex::encode(group.nostr_group_id),
+            )
+            .limit(100);
+
+        ensure_relays(&self.client, &relays).await;
+        let output = self
+            .client
+            .subscribe_to(relays.clone(), filter, None)
+            .await?;
+        self.group_subscriptions
+            .insert(group_id.clone(), (output.val, relays));
+        Ok(())
+    }
+
+    async fn subscribe_all_groups(&mut self) {
+        let groups = match self.storage.get_groups() {
+            Ok(groups) => groups,
+            Err(e) => {
+                log::warn!("Failed to load groups for subscriptions: {e}");
+                return;
+            }
+        };
+        for group in groups {
+            let group_id = GroupId::from_slice(group.mls_group_id.as_slice());
+            if self.group_meta.is_archived(&group_id).unwrap_or(false) {
+                continue;
+            }
+            if let Err(e) = self.subscribe_group(&group_id).await {
+                log::warn!("Failed to subscribe to group messages: {e}");
+            }
+        }
+    }
+
+    /// Add or remove one of the group's relays with a commit (admins only).
+    ///
+    /// The commit goes to the old and new relays alike so nobody misses it.
+    async fn update_group_relays(
+        &mut self,
+        group_id: GroupId,
+        url: &RelayUrl,
+        adding: bool,
+    ) -> Result<()> {
+        let me = self.signer.public_key();
+        let group = self
+            .storage
+            .get_group(&group_id)?
+            .ok_or_else(|| anyhow::anyhow!("Group not found"))?;
+        if !group.admin_pubkeys.contains(&me) {
+            anyhow::bail!("Only group admins can change relays");
+        }
+
+        let old = self.group_relay_urls(&group_id);
+        let mut new = old.clone();
+        if adding {
+            if new.contains(url) {
+                anyhow::bail!("{url} is already one of this group's relays");
+            }
+            new.push(url.clone());
+        } else {
+            if !new.contains(url) {
+                anyhow::bail!("{url} is not one of this group's relays");
+            }
+            new.retain(|r| r != url);
+            if new.is_empty() {
+                anyhow::bail!("A group needs at least one relay");
+            }
+        }
+
+        let update = self
+            .storage
+            .update_group_data(&group_id, NostrGroupDataUpdate::new().relays(new.clone()))
+            .context("Failed to create relay update commit")?;
+
+        let mut relays = old;
+        for r in new {
+            if !relays.contains(&r) {
+                relays.push(r);
+            }
+        }
+        let verb = if adding { "add" } else { "remove" };
+        let kind = OperationKind::GroupEvolution {
+            mls_group_id_hex: hex::encode(group_id.as_slice()),
+            description: format!("{verb} relay {url}"),
+            step: GroupEvolutionStep::Publish {
+                evolution_event: update.evolution_event,
+                is_commit: true,
+                relays,
+            },
+        };
+        let op_id = self
+            .ops_store
+            .enqueue(kind)
+            .context("Failed to enqueue relay update operation")?;
+        log::info!("Enqueued relay update op {op_id}");
+        let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+        Ok(())
+    }
+
+    async fn answer_auth_challenge(&mut self, relay_url: &RelayUrl, challenge: &str) {
+        if let Err(e) = crate::relay_auth::answer_auth_challenge(
+            &self.client,
+            &self.signer,
+            &self.relay_activity,
+            relay_url,
+            challenge,
+        )
+        .await
+        {
+            log::warn!("Failed to answer AUTH from {relay_url}: {e}");
+            self.relay_activity
+                .record_error(relay_url, format!("AUTH failed: {e}"));
+            self.error = Some(format!("AUTH failed on {relay_url}: {e}"));
+        }
+    }
+
+    async fn answer_pending_auth_challenges(&mut self) {
+        let pending: Vec<(RelayUrl, String)> = self.pending_auth_challenges.drain().collect();
+        for (relay_url, challenge) in pending {
+            self.answer_auth_challenge(&relay_url, &challenge).await;
+        }
+    }
+
+    /// Relays our KeyPackages go to; also what we advertise in kind 10051
+    fn key_package_relay_urls(&self) -> Result<Vec<RelayUrl>> {
+        self.config.relay_urls()
+    }
+
+    async fn publish_key_package_relays(&mut self) -> Result<()> {
+        let relays = self.key_package_relay_urls()?;
+        let unsigned = key_package_relays_event(&relays).build(self.signer.public_key());
+        let event = self.signer.sign(unsigned).await?;
+
+        let op_id = self
+            .ops_store
+            .enqueue(OperationKind::PublishKeyPackageRelays { event })?;
+        log::info!("Enqueued KeyPackage relay list publish op {op_id}");
+        let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+        Ok(())
+    }
+
+    async fn publish_key_package(&mut self) -> Result<()> {
+        let relays = self.key_package_relay_urls()?;
         let (key_package_content, tags) = self
             .storage
-            .create_key_package_for_event(&self.keys.public_key(), relays)?;
+            .create_key_package_for_event(&self.signer.public_key(), relays)?;
 
-        let event = EventBuilder::new(Kind::MlsKeyPackage, key_package_content)
+        let unsigned = EventBuilder::new(Kind::MlsKeyPackage, key_package_content)
             .tags(tags)
-            .build(self.keys.public_key())
-            .sign(&self.keys)
-            .await?;
+            .build(self.signer.public_key());
+        let event = self.signer.sign(unsigned).await?;
+
+        self.key_packages.add(&event)?;
 
         // Enqueue persistent publish operation (network in background)
         let kind = OperationKind::PublishKeyPackage { event };
@@ -1050,6 +2645,115 @@ impl App {
         Ok(())
     }
 
+    /// Keep `key_package_policy.target_count` fresh KeyPackages on relays.
+    ///
+    /// Rotates out packages past their lifetime, publishes replacements and
+    /// periodically checks that relays still have the ones we published.
+    async fn maintain_key_packages(&mut self) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        let plan = self
+            .key_package_policy
+            .plan(&self.key_packages.active()?, now);
+
+        if !plan.retire.is_empty() {
+            self.key_packages.mark_deleted(&plan.retire)?;
+            let op_id = self.ops_store.enqueue(OperationKind::DeleteKeyPackages {
+                event_ids: plan.retire,
+            })?;
+            log::info!("Enqueued key package rotation op {op_id}");
+        }
+        for _ in 0..plan.publish {
+            self.publish_key_package().await?;
+        }
+
+        let reconcile_due = self
+            .last_key_package_reconcile
+            .is_none_or(|at| at.elapsed() >= std::time::Duration::from_secs(60 * 60));
+        if reconcile_due {
+            // Once per session, make sure others can find where our packages live
+            if self.last_key_package_reconcile.is_none() {
+                self.publish_key_package_relays().await?;
+            }
+            self.last_key_package_reconcile = Some(Instant::now());
+            let published = self.key_packages.published_events()?;
+            if !published.is_empty() {
+                self.ops_store
+                    .enqueue(OperationKind::ReconcileKeyPackages { published })?;
+            }
+        }
+        let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+        Ok(())
+    }
+
+    /// Delete KeyPackages a welcome consumed (NIP-EE) and publish replacements.
+    async fn retire_used_key_packages(&mut self, event_ids: &[EventId]) -> Result<()> {
+        let mut ours = Vec::new();
+        for id in event_ids {
+            if self.key_packages.mark_consumed(id)? {
+                ours.push(*id);
+            }
+        }
+        if ours.is_empty() {
+            return Ok(());
+        }
+        self.key_packages.mark_deleted(&ours)?;
+        self.ops_store
+            .enqueue(OperationKind::DeleteKeyPackages { event_ids: ours })?;
+        self.maintain_key_packages().await
+    }
+
+    /// Enqueue a self-update commit for every active group whose rotation is due.
+    fn schedule_key_rotations(&mut self) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        let me = self.signer.public_key();
+
+        // Groups that already have a rotation in flight; a failed one doesn't hold up the next
+        let in_flight: Vec<String> = self
+            .ops_store
+            .list_unfinished("RotateGroupKey")?
+            .into_iter()
+            .filter(|op| op.status != crate::ops::OpStatus::Failed)
+            .filter_map(|op| match op.kind {
+                OperationKind::RotateGroupKey {
+                    mls_group_id_hex, ..
+                } => Some(mls_group_id_hex),
+                _ => None,
+            })
+            .collect();
+
+        for group in self.storage.get_groups()? {
+            let group_id = group.mls_group_id;
+            let hex_id = hex::encode(group_id.as_slice());
+            if in_flight.contains(&hex_id) || self.group_meta.is_archived(&group_id)? {
+                continue;
+            }
+            if !self
+                .storage
+                .get_members(&group_id)
+                .map(|m| m.contains(&me))
+                .unwrap_or(false)
+            {
+                continue;
+            }
+
+            let rotation = self.group_meta.key_rotation(&group_id)?;
+            if self.key_rotation.is_due(
+                rotation.last_rotated_at,
+                rotation.tracked_since,
+                rotation.messages_since,
+                now,
+            ) {
+                let op_id = self.ops_store.enqueue(OperationKind::RotateGroupKey {
+                    mls_group_id_hex: hex_id,
+                    step: RotateGroupKeyStep::RequestSelfUpdate,
+                })?;
+                log::info!("Enqueued key rotation op {op_id}");
+                let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+            }
+        }
+        Ok(())
+    }
+
     async fn publish_profile_name_if_needed(&mut self) -> Result<()> {
         let Some(name) = self.pending_display_name.take() else {
             return Ok(());
@@ -1060,10 +2764,8 @@ impl App {
         })
         .to_string();
 
-        let event = EventBuilder::new(Kind::Metadata, content)
-            .build(self.keys.public_key())
-            .sign(&self.keys)
-            .await?;
+        let unsigned = EventBuilder::new(Kind::Metadata, content).build(self.signer.public_key());
+        let event = self.signer.sign(unsigned).await?;
 
         // Publish directly
         if let Err(e) = self.client.send_event(&event).await {
@@ -1072,7 +2774,9 @@ impl App {
 
         // Cache locally for immediate UI benefit
         if let Ok(meta) = Metadata::from_json(&event.content) {
-            self.profiles.cache(self.keys.public_key(), meta).await;
+            self.profiles
+                .cache(self.signer.public_key(), meta, event.created_at)
+                .await;
         }
         Ok(())
     }
@@ -1122,11 +2826,7 @@ impl App {
                 let key_package = events.into_iter().next().unwrap();
 
                 // Create group locally
-                let relay_urls: Result<Vec<RelayUrl>, _> = get_default_relays()
-                    .iter()
-                    .map(|&url| RelayUrl::parse(url))
-                    .collect();
-                let relay_urls = relay_urls.context("Invalid relay URLs")?;
+                let relay_urls = self.config.relay_urls()?;
                 let config = NostrGroupConfigData::new(
                     format!(
                         "DM with {}",
@@ -1139,10 +2839,10 @@ impl App {
                     None,
                     None,
                     relay_urls,
-                    vec![self.keys.public_key(), other_pubkey],
+                    vec![self.signer.public_key(), other_pubkey],
                 );
                 match self.storage.create_group(
-                    &self.keys.public_key(),
+                    &self.signer.public_key(),
                     vec![key_package.clone()],
                     config,
                 ) {
@@ -1164,20 +2864,13 @@ impl App {
                             });
 
                             // Subscribe locally to group messages for immediate UX
-                            let filter = Filter::new()
-                                .kind(Kind::MlsGroupMessage)
-                                .custom_tag(
-                                    SingleLetterTag::lowercase(Alphabet::H),
-                                    hex::encode(group_result.group.nostr_group_id),
-                                )
-                                .limit(100);
-                            if let Err(e) = self.client.subscribe(filter, None).await {
+                            let group_id =
+                                GroupId::from_slice(group_result.group.mls_group_id.as_slice());
+                            if let Err(e) = self.subscribe_group(&group_id).await {
                                 log::warn!("Failed to subscribe to group messages: {e}");
                             }
 
                             // Navigate to chat
-                            let group_id =
-                                GroupId::from_slice(group_result.group.mls_group_id.as_slice());
                             self.navigate_to(PageType::Chat(Some(group_id))).await?;
                         } else {
                             log::error!("No welcome rumor produced by storage.create_group");
@@ -1209,29 +2902,1129 @@ impl App {
         Ok(())
     }
 
-    pub async fn load_older_messages(&mut self, limit: usize) -> Result<()> {
+    /// Enqueue a persistent CreateGroup op; returns the number of invitees.
+    async fn create_group_with(&mut self, name: String, members: Vec<PublicKey>) -> Result<usize> {
+        let me = self.signer.public_key();
+        let mut invitees: Vec<PublicKey> = Vec::new();
+        for pk in members {
+            if pk != me && !invitees.contains(&pk) {
+                invitees.push(pk);
+            }
+        }
+        if invitees.is_empty() {
+            anyhow::bail!("A group needs at least one other member");
+        }
+
+        // Running /group again resumes a failed attempt at the same group
+        if let Some(op_id) = self.ops_store.resume_create_group(&name, &invitees)? {
+            log::info!("Resuming CreateGroup op {op_id}");
+        } else {
+            let kind = OperationKind::CreateGroup {
+                name,
+                members: invitees.clone(),
+                step: CreateGroupStep::FetchKeyPackages {
+                    key_packages: vec![],
+                },
+            };
+            let op_id = self
+                .ops_store
+                .enqueue(kind)
+                .context("Failed to enqueue CreateGroup operation")?;
+            log::info!("Enqueued CreateGroup op {op_id}");
+        }
+        let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+
+        let count = invitees.len();
+        let _ = self.profiles.ensure(&self.client, invitees).await;
+        Ok(count)
+    }
+
+    /// Enqueue a persistent InviteMember op for the given group (admins only).
+    async fn invite_member(&mut self, group_id: GroupId, invitee: PublicKey) -> Result<()> {
+        let group = self
+            .storage
+            .get_group(&group_id)?
+            .ok_or_else(|| anyhow::anyhow!("Group not found"))?;
+        if !group.admin_pubkeys.contains(&self.signer.public_key()) {
+            anyhow::bail!("Only group admins can invite members");
+        }
+        if self.storage.get_members(&group_id)?.contains(&invitee) {
+            anyhow::bail!("Already a member of this group");
+        }
+
+        let kind = OperationKind::InviteMember {
+            mls_group_id_hex: hex::encode(group_id.as_slice()),
+            invitee,
+            step: InviteMemberStep::FetchKeyPackage,
+        };
+        let op_id = self
+            .ops_store
+            .enqueue(kind)
+            .context("Failed to enqueue InviteMember operation")?;
+        log::info!("Enqueued InviteMember op {op_id}");
+        let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+        let _ = self.profiles.ensure(&self.client, vec![invitee]).await;
+        Ok(())
+    }
+
+    /// Build a Remove commit and enqueue it for publishing (admins only).
+    async fn kick_member(&mut self, group_id: GroupId, member: PublicKey) -> Result<()> {
+        let me = self.signer.public_key();
+        if member == me {
+            anyhow::bail!("Use /leave to leave a group");
+        }
+        let group = self
+            .storage
+            .get_group(&group_id)?
+            .ok_or_else(|| anyhow::anyhow!("Group not found"))?;
+        if !group.admin_pubkeys.contains(&me) {
+            anyhow::bail!("Only group admins can remove members");
+        }
+        if !self.storage.get_members(&group_id)?.contains(&member) {
+            anyhow::bail!("Not a member of this group");
+        }
+        self.enqueue_removal(&group_id, &[member])
+    }
+
+    /// Build one Remove commit for `members` and enqueue it for publishing.
+    fn enqueue_removal(&mut self, group_id: &GroupId, members: &[PublicKey]) -> Result<()> {
+        let update = self
+            .storage
+            .remove_members(group_id, members)
+            .context("Failed to create remove commit")?;
+        let npubs: Vec<String> = members
+            .iter()
+            .map(crate::utils::pubkey_to_bech32_safe)
+            .collect();
+        let kind = OperationKind::GroupEvolution {
+            mls_group_id_hex: hex::encode(group_id.as_slice()),
+            description: format!("remove {}", npubs.join(", ")),
+            step: GroupEvolutionStep::Publish {
+                evolution_event: update.evolution_event,
+                is_commit: true,
+                relays: self.group_relay_urls(group_id),
+            },
+        };
+        let op_id = self
+            .ops_store
+            .enqueue(kind)
+            .context("Failed to enqueue remove operation")?;
+        log::info!("Enqueued remove-member op {op_id}");
+        let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+        Ok(())
+    }
+
+    /// Whether one of our unfinished commits already removes `member` from the group.
+    fn removal_pending(&self, group_id: &GroupId, member: &PublicKey) -> Result<bool> {
+        let group_hex = hex::encode(group_id.as_slice());
+        let npub = crate::utils::pubkey_to_bech32_safe(member);
+        Ok(self
+            .ops_store
+            .list_unfinished("GroupEvolution")?
+            .iter()
+            .any(|op| {
+                matches!(&op.kind, OperationKind::GroupEvolution { mls_group_id_hex, description, .. }
+                    if *mls_group_id_hex == group_hex
+                        && description.starts_with("remove ")
+                        && description.contains(&npub))
+            }))
+    }
+
+    /// Act on a proposal nrc-mls dropped because its sender isn't an admin.
+    ///
+    /// Only admins can commit, so when a member leaves an admin removes them.
+    /// Returns whether `event` was a member's proposal.
+    async fn handle_member_proposal(&mut self, event: &Event) -> Result<bool> {
+        let Some(group_id) = self.group_for_event(event) else {
+            return Ok(false);
+        };
+        let proposal = match group_messages::read_proposal(&self.storage, &group_id, event) {
+            Ok(Some(proposal)) => proposal,
+            Ok(None) => return Ok(false),
+            Err(e) => {
+                log::debug!("{} isn't a proposal we can read: {e}", event.id);
+                return Ok(false);
+            }
+        };
+        let me = self.signer.public_key();
+        let is_admin = self
+            .storage
+            .get_group(&group_id)?
+            .is_some_and(|g| g.admin_pubkeys.contains(&me));
+        if !is_admin {
+            log::debug!("Ignoring a member's proposal; only admins can commit it");
+            return Ok(true);
+        }
+
+        let who = self.display_label(&proposal.proposer);
+        match proposal.change {
+            ProposedChange::Leave => {
+                let leaver = proposal.proposer;
+                if self.removal_pending(&group_id, &leaver)? {
+                    log::debug!("Already removing {leaver}");
+                    return Ok(true);
+                }
+                self.enqueue_removal(&group_id, &[leaver])?;
+                self.send_event(AppEvent::MemberLeft {
+                    group_id,
+                    member: leaver,
+                    by: None,
+                })?;
+            }
+            ProposedChange::Remove(member) => {
+                let members = self.storage.get_members(&group_id)?;
+                if !members.contains(&member) || self.removal_pending(&group_id, &member)? {
+                    return Ok(true);
+                }
+                let held = HeldProposal {
+                    event: event.clone(),
+                    proposer: proposal.proposer,
+                    removes: member,
+                };
+                if self.group_meta.hold_proposal(&group_id, &held)? {
+                    let name = self.display_label(&member);
+                    self.record_system_message(
+                        &group_id,
+                        &format!("{who} proposed removing {name}; type /commit to apply it"),
+                    )
+                    .await?;
+                }
+            }
+            ProposedChange::Add(invitee) => {
+                let npub = crate::utils::pubkey_to_bech32_safe(&invitee);
+                self.record_system_message(
+                    &group_id,
+                    &format!("{who} proposed adding {npub}; use /invite to add them"),
+                )
+                .await?;
+            }
+            ProposedChange::Other => {
+                self.record_system_message(
+                    &group_id,
+                    &format!("{who} proposed a group change nrc can't apply"),
+                )
+                .await?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Publish a self-remove proposal and archive the group locally; an admin commits it.
+    async fn leave_group(&mut self, group_id: GroupId) -> Result<()> {
+        let update = self
+            .storage
+            .leave_group(&group_id)
+            .context("Failed to create leave proposal")?;
+        let kind = OperationKind::GroupEvolution {
+            mls_group_id_hex: hex::encode(group_id.as_slice()),
+            description: "leave".to_string(),
+            step: GroupEvolutionStep::Publish {
+                evolution_event: update.evolution_event,
+                is_commit: false,
+                relays: self.group_relay_urls(&group_id),
+            },
+        };
+        let op_id = self
+            .ops_store
+            .enqueue(kind)
+            .context("Failed to enqueue leave operation")?;
+        log::info!("Enqueued leave-group op {op_id}");
+        let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+
+        self.group_meta.archive(&group_id)?;
+        self.refresh_current_page().await?;
+        self.flash = Some((
+            "Left group".to_string(),
+            std::time::Instant::now() + std::time::Duration::from_secs(5),
+        ));
+        Ok(())
+    }
+
+    /// Run an operation again now instead of waiting for its backoff.
+    async fn retry_operation(&mut self, op_id: &str) -> Result<()> {
+        let op = self.ops_store.load(op_id)?;
+        if matches!(op.status, crate::ops::OpStatus::Success) {
+            return Err(anyhow::anyhow!("Operation already succeeded"));
+        }
+        self.ops_store.requeue(op_id)?;
+        let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+        self.refresh_current_page().await
+    }
+
+    /// Cancel an operation and undo what it already did locally.
+    ///
+    /// A CreateDm that got as far as creating the group leaves an MLS group the peer
+    /// will never be welcomed to; it's hidden and unsubscribed.
+    async fn cancel_operation(&mut self, op_id: &str) -> Result<()> {
+        let op = self.ops_store.load(op_id)?;
+        if !self.ops_store.cancel(op_id)? {
+            return Err(anyhow::anyhow!("Operation already finished"));
+        }
+        log::info!("Cancelled op {op_id}");
+
+        if let OperationKind::SendMessage { event, .. } = &op.kind {
+            if self.deliveries.mark_failed(&event.id)? {
+                self.update_message_delivery(&event.id)?;
+            }
+        }
+
+        if let OperationKind::CreateDm {
+            step:
+                CreateDmStep::SubscribeGroup {
+                    nostr_group_id_hex, ..
+                }
+                | CreateDmStep::SendWelcome {
+                    nostr_group_id_hex, ..
+                },
+            ..
+        } = &op.kind
+        {
+            let group_id = self
+                .storage
+                .get_groups()?
+                .into_iter()
+                .find(|g| hex::encode(g.nostr_group_id) == *nostr_group_id_hex)
+                .map(|g| g.mls_group_id);
+            if let Some(group_id) = group_id {
+                self.group_meta.discard(&group_id)?;
+                if let Some((sub_id, _)) = self.group_subscriptions.remove(&group_id) {
+                    self.client.unsubscribe(&sub_id).await;
+                }
+                log::info!("Discarded group {nostr_group_id_hex} of cancelled DM");
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify a profile's `nip05` in the background if it hasn't been (or the result expired).
+    async fn check_nip05(&mut self, pubkey: PublicKey) {
+        if self.nip05_in_flight.contains(&pubkey) {
+            return;
+        }
+        let Some(address) = self
+            .profiles
+            .nip05_to_check(&pubkey, Timestamp::now())
+            .await
+        else {
+            return;
+        };
+        self.nip05_in_flight.insert(pubkey);
+        let resolver = self.nip05.clone();
+        let event_tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            let verified = match resolver.verify(&pubkey, &address).await {
+                Ok(verified) => verified,
+                Err(e) => {
+                    log::debug!("NIP-05 lookup for {address} failed: {e}");
+                    false
+                }
+            };
+            let _ = event_tx.send(AppEvent::Nip05Checked {
+                pubkey,
+                address,
+                verified,
+            });
+        });
+    }
+
+    /// Check the password re-entered for /export, then ask for confirmation.
+    fn confirm_export_password(&mut self) -> Result<()> {
+        let npub = self.signer.public_key().to_bech32()?;
+        let Page::ExportKey {
+            stage,
+            input,
+            include_nsec,
+            error,
+        } = &mut self.current_page
+        else {
+            return Ok(());
+        };
+        let password = std::mem::take(input);
+        let unlocked = self
+            .key_storage
+            .for_account(&npub)
+            .load_encrypted(&password)
+            .is_ok_and(|keys| keys.public_key() == self.signer.public_key());
+        if !unlocked {
+            *error = Some("Invalid password. Please try again.".to_string());
+        } else {
+            *stage = ExportStage::Confirm;
+            let message = if *include_nsec {
+                "Show your encrypted key and your raw nsec? Anyone who sees the nsec controls your account."
+            } else {
+                "Show your encrypted key (ncryptsec)?"
+            };
+            self.modal = Some(Modal::Confirm {
+                message: message.to_string(),
+                on_confirm: ModalAction::RevealKey,
+            });
+        }
+        let _ = self.state_tx.send(self.current_page.clone());
+        Ok(())
+    }
+
+    fn copy_secret(&mut self, secret: String) -> Result<()> {
+        let mut ctx = clipboard::ClipboardContext::new()
+            .map_err(|e| anyhow::anyhow!("Clipboard not available: {e}"))?;
+        ctx.set_contents(secret.clone())
+            .map_err(|e| anyhow::anyhow!("Failed to copy to clipboard: {e}"))?;
+        self.clipboard_secret = Some(secret);
+        Ok(())
+    }
+
+    /// Take an exported key off the screen and, if still there, off the clipboard.
+    async fn clear_exported_key(&mut self) -> Result<()> {
+        self.wipe_clipboard_secret();
+        if matches!(self.previous_page, Some(Page::ExportKey { .. })) {
+            self.previous_page = None;
+        }
+        if matches!(self.current_page, Page::ExportKey { .. }) {
+            self.navigate_back().await?;
+            self.flash = Some((
+                "Exported key cleared from screen".to_string(),
+                Instant::now() + std::time::Duration::from_secs(5),
+            ));
+            let _ = self.state_tx.send(self.current_page.clone());
+        }
+        Ok(())
+    }
+
+    /// Clear a key we copied from `/export` off the clipboard, unless something replaced it
+    pub fn wipe_clipboard_secret(&mut self) {
+        if let Some(secret) = self.clipboard_secret.take() {
+            if let Ok(mut ctx) = clipboard::ClipboardContext::new() {
+                if ctx.get_contents().ok().as_deref() == Some(secret.as_str()) {
+                    let _ = ctx.set_contents(String::new());
+                }
+            }
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.is_some()
+    }
+
+    /// Whether nobody pressed a key for longer than the configured idle timeout
+    pub fn idle_lock_due(&self) -> bool {
+        self.locked.is_none()
+            && !matches!(
+                self.current_page,
+                Page::Onboarding { .. } | Page::Initializing { .. }
+            )
+            && self
+                .config
+                .idle_lock_timeout()
+                .is_some_and(|timeout| self.last_activity.elapsed() >= timeout)
+    }
+
+    /// Wipe the unlocked keys and show the password prompt until they're unlocked again.
+    async fn lock(&mut self) -> Result<()> {
+        if self.locked.is_some()
+            || matches!(
+                self.current_page,
+                Page::Onboarding { .. } | Page::Initializing { .. }
+            )
+        {
+            return Ok(());
+        }
+        // Unlocking must decrypt this account's key, whatever the registry's default is
+        let npub = self.signer.public_key().to_bech32()?;
+        self.key_storage = self.key_storage.for_account(&npub);
+
+        // Stop signing before our copies of the keys go away
+        let _ = self.ops_cmd_tx.send(OpsCommand::Lock);
+        self.client.unset_signer().await;
+        // Dropping our keys (or bunker client key) erases them; the placeholder can't sign for us
+        self.signer = Keys::generate().into();
+
+        // An exported key goes too, from the screen and the clipboard
+        self.export_generation += 1;
+        self.wipe_clipboard_secret();
+        self.modal = None;
+        self.error = None;
+
+        let lock_page = Page::Onboarding {
+            input: String::new(),
+            mode: OnboardingMode::EnterPassword,
+            error: None,
+        };
+        let mut page = std::mem::replace(&mut self.current_page, lock_page);
+        let mut previous_page = self.previous_page.take();
+        // Don't bring back a half-typed password or a revealed key
+        if matches!(page, Page::ChangePassword { .. } | Page::ExportKey { .. }) {
+            if let Some(prev) = previous_page.take() {
+                page = prev;
+            }
+        }
+        self.locked = Some(LockedSession {
+            page,
+            previous_page,
+            backlog: Vec::new(),
+        });
+        self.flash = None;
+        let _ = self.state_tx.send(self.current_page.clone());
+        Ok(())
+    }
+
+    /// Back from the lock screen: restore the page and catch up on what arrived meanwhile.
+    async fn unlock(&mut self, signer: AccountSigner) -> Result<()> {
+        let Some(locked) = self.locked.take() else {
+            return Ok(());
+        };
+        self.signer = signer;
+        self.resume_signing().await;
+        self.last_activity = Instant::now();
+
+        self.current_page = locked.page;
+        self.previous_page = locked.previous_page;
+        let _ = self.state_tx.send(self.current_page.clone());
+        self.answer_pending_auth_challenges().await;
+
+        let held = locked.backlog.len();
+        for event in locked.backlog {
+            self.send_event(event)?;
+        }
+        log::info!("Unlocked; replaying {held} event(s) received while locked");
+        self.send_event(AppEvent::RefreshCurrentPage)?;
+        Ok(())
+    }
+
+    /// Hand the unlocked keys to the orchestrator and the client
+    async fn resume_signing(&self) {
+        let _ = self
+            .ops_cmd_tx
+            .send(OpsCommand::Unlock(Box::new(self.signer.clone())));
+        self.signer.install(&self.client).await;
+    }
+
+    /// Advance the /password flow; the last step re-encrypts in the background.
+    fn advance_password_change(&mut self) -> Result<()> {
+        let Page::ChangePassword {
+            step,
+            input,
+            old_password,
+            new_password,
+            error,
+        } = &mut self.current_page
+        else {
+            return Ok(());
+        };
+        let entered = std::mem::take(input);
+        *error = None;
+        match step {
+            PasswordStep::Old => {
+                if !entered.is_empty() {
+                    *old_password = entered;
+                    *step = PasswordStep::New;
+                }
+            }
+            PasswordStep::New => {
+                if entered.len() < MIN_PASSWORD_LEN {
+                    *error = Some(format!(
+                        "Password must be at least {MIN_PASSWORD_LEN} characters."
+                    ));
+                } else {
+                    *new_password = entered;
+                    *step = PasswordStep::Confirm;
+                }
+            }
+            PasswordStep::Confirm => {
+                if entered != *new_password {
+                    *error = Some("Passwords don't match. Enter the new one again.".to_string());
+                    new_password.clear();
+                    *step = PasswordStep::New;
+                } else {
+                    *step = PasswordStep::Saving;
+                    // scrypt at a high log_n takes seconds; keep the UI responsive
+                    let key_storage = self
+                        .key_storage
+                        .for_account(&self.signer.public_key().to_bech32()?);
+                    let old = old_password.clone();
+                    let new = new_password.clone();
+                    let log_n = self.config.keys.log_n;
+                    let event_tx = self.event_tx.clone();
+                    tokio::spawn(async move {
+                        let result = tokio::task::spawn_blocking(move || {
+                            key_storage.change_password(&old, &new, log_n)
+                        })
+                        .await;
+                        let error = match result {
+                            Ok(Ok(_)) => None,
+                            Ok(Err(e)) => Some(format!("{e:#}")),
+                            Err(e) => Some(e.to_string()),
+                        };
+                        let _ = event_tx.send(AppEvent::PasswordChangeFinished { error });
+                    });
+                }
+            }
+            PasswordStep::Saving => {}
+        }
+        let _ = self.state_tx.send(self.current_page.clone());
+        Ok(())
+    }
+
+    /// Fetch our kind 3 in the background; it arrives as `FollowListReceived`.
+    fn sync_follow_list(&self) {
+        let client = self.client.clone();
+        let pubkey = self.signer.public_key();
+        let event_tx = self.event_tx.clone();
+        tokio::spawn(async move {
+            match fetch_follow_list(&client, pubkey).await {
+                Ok(Some(event)) => {
+                    let _ = event_tx.send(AppEvent::FollowListReceived { event });
+                }
+                Ok(None) => log::debug!("No contact list found for {pubkey}"),
+                Err(e) => log::warn!("Failed to fetch our contact list: {e}"),
+            }
+        });
+    }
+
+    /// Set or clear a local petname. Stored in nrc.db only, never published.
+    async fn set_petname(&mut self, pubkey: PublicKey, petname: Option<&str>) -> Result<()> {
+        self.contacts.set_petname(&pubkey, petname)?;
+        self.petnames = self.contacts.petnames()?;
+        self.refresh_current_page().await
+    }
+
+    /// An npub right away. A NIP-05 address is looked up on its domain in the
+    /// background, and `action` runs when `Nip05Resolved` reports back.
+    fn pubkey_or_lookup(&self, input: &str, action: Nip05Action) -> Result<Option<PublicKey>> {
+        if let Ok(pubkey) = PublicKey::from_bech32(input) {
+            return Ok(Some(pubkey));
+        }
+        let address = Nip05Address::parse(input)
+            .map_err(|_| anyhow::anyhow!("'{input}' is not a valid npub or NIP-05 address"))?;
+        let resolver = self.nip05.clone();
+        let event_tx = self.event_tx.clone();
+        let input = input.to_string();
+        tokio::spawn(async move {
+            let result = match resolver.lookup(&address).await {
+                Ok(Some(pubkey)) => Ok(pubkey),
+                Ok(None) => Err(format!("{address} doesn't list a pubkey")),
+                Err(e) => Err(format!("Failed to look up {address}: {e:#}")),
+            };
+            let _ = event_tx.send(AppEvent::Nip05Resolved {
+                input,
+                action,
+                result,
+            });
+        });
+        Ok(None)
+    }
+
+    /// `/dm`: fetch their key package and create the group
+    async fn dm_command(&mut self, pubkey: PublicKey, who: &str) -> Result<CommandOutcome> {
+        self.create_dm_with(pubkey)
+            .await
+            .with_context(|| format!("Failed to create DM with {who}"))?;
+        Ok(CommandOutcome::Noop)
+    }
+
+    /// `/nick`: set, or with no name clear, a petname
+    async fn nick_command(
+        &mut self,
+        pubkey: PublicKey,
+        who: &str,
+        petname: Option<String>,
+    ) -> Result<CommandOutcome> {
+        self.set_petname(pubkey, petname.as_deref()).await?;
+        Ok(CommandOutcome::Flash(match petname {
+            Some(petname) => format!("{who} is now {petname}"),
+            None => format!("Cleared nickname for {who}"),
+        }))
+    }
+
+    /// Show what a command produced: a flash, an error, or nothing.
+    fn show_command_outcome(&mut self, outcome: Result<CommandOutcome>) {
+        match outcome {
+            Ok(CommandOutcome::Noop) => {
+                self.error = None;
+            }
+            Ok(CommandOutcome::Flash(msg)) => {
+                self.error = None;
+                self.flash = Some((
+                    msg,
+                    std::time::Instant::now() + std::time::Duration::from_secs(5),
+                ));
+            }
+            Err(e) => {
+                self.error = Some(format!("{e:#}"));
+            }
+        }
+    }
+
+    /// Show a message's latest delivery status if it's in the open chat.
+    fn update_message_delivery(&mut self, event_id: &EventId) -> Result<()> {
+        let delivery = self.deliveries.get(event_id)?;
+        if let Page::Chat { messages, .. } = &mut self.current_page {
+            if let Some(msg) = messages
+                .iter_mut()
+                .find(|m| m.delivery.as_ref().is_some_and(|d| d.event_id == *event_id))
+            {
+                msg.delivery = delivery;
+                let _ = self.state_tx.send(self.current_page.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Resend this group's failed messages; returns how many were requeued.
+    fn retry_failed_messages(&mut self, group_id: &GroupId) -> Result<usize> {
+        let failed = self.deliveries.failed_in_group(group_id)?;
+        for delivery in &failed {
+            self.ops_store.requeue(&delivery.op_id)?;
+            self.deliveries.mark_queued(&delivery.event_id)?;
+            self.update_message_delivery(&delivery.event_id)?;
+        }
+        if !failed.is_empty() {
+            self.error = None;
+            let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+        }
+        Ok(failed.len())
+    }
+
+    /// Find the local group a kind 445 event belongs to via its `h` tag.
+    fn group_for_event(&self, event: &Event) -> Option<GroupId> {
+        let h = event
+            .tags
+            .find(TagKind::SingleLetter(SingleLetterTag::lowercase(
+                Alphabet::H,
+            )))
+            .and_then(|t| t.content())?
+            .to_string();
+        self.storage
+            .get_groups()
+            .ok()?
+            .into_iter()
+            .find(|g| hex::encode(g.nostr_group_id) == h)
+            .map(|g| g.mls_group_id)
+    }
+
+    fn group_epoch(&self, group_id: &GroupId) -> Option<u64> {
+        self.storage
+            .get_group(group_id)
+            .ok()
+            .flatten()
+            .map(|g| g.epoch)
+    }
+
+    /// Process one kind 445 event, remembering it (and any commit it applied) for rollback.
+    ///
+    /// `snapshot` is the group's MLS state from before this batch's first commit for
+    /// it, with the replay index of that moment.
+    fn apply_group_event(
+        &mut self,
+        event: &Event,
+        snapshot: Option<(PathBuf, usize)>,
+    ) -> Result<MessageProcessingResult> {
+        let group_id = self.group_for_event(event);
+        let epoch_before = group_id.as_ref().and_then(|g| self.group_epoch(g));
+        let result = self.storage.process_message(event);
+        let Some(group_id) = group_id else {
+            return Ok(result?);
+        };
+        self.commit_ledger
+            .record_processed(group_id.clone(), event.clone());
+
+        let advanced = epoch_before.filter(|epoch| {
+            self.group_epoch(&group_id)
+                .is_some_and(|after| after > *epoch)
+        });
+        match (advanced, snapshot) {
+            (Some(epoch), Some((snapshot, replay_from))) => {
+                self.commit_ledger.record_commit(AppliedCommit {
+                    group_id,
+                    epoch,
+                    event_id: event.id,
+                    created_at: event.created_at,
+                    snapshot,
+                    replay_from,
+                    op_id: None,
+                    applied_at: Instant::now(),
+                });
+            }
+            (Some(_), None) => log::debug!("Applied commit {} can't be rolled back", event.id),
+            (None, _) => {}
+        }
+        Ok(result?)
+    }
+
+    /// An event we couldn't apply may be a commit that should have beaten one we applied.
+    ///
+    /// If so, restore the MLS state from before the losing commit and reprocess
+    /// everything since then with the winner in its place.
+    async fn resolve_commit_race(&mut self, event: &Event) -> Result<()> {
+        let Some(group_id) = self.group_for_event(event) else {
+            return Ok(());
+        };
+        let Some(index) = self.commit_ledger.challenged_by(&group_id, event) else {
+            return Ok(());
+        };
+        let Some(loser) = self.commit_ledger.get(index).cloned() else {
+            return Ok(());
+        };
+        log::warn!(
+            "Commit race: {} sorts before applied commit {}; rolling back",
+            event.id,
+            loser.event_id
+        );
+
+        // Keep the current state in case the challenger turns out not to be a commit
+        let current = self.mls_snapshots.take(&group_id)?;
+        let saved_ledger = self.commit_ledger.clone();
+        self.mls_snapshots.restore(&group_id, &loser.snapshot)?;
+
+        let (mut replay, undone) = self.commit_ledger.rollback(index);
+        replay.push(event.clone());
+        replay.sort_by(commit_order);
+        let replay_from = self.commit_ledger.begin_batch();
+        for ev in &replay {
+            let snapshot = Some((loser.snapshot.clone(), replay_from));
+            if let Err(e) = self.apply_group_event(ev, snapshot) {
+                log::debug!("Replay of {} failed: {e}", ev.id);
+            }
+        }
+        self.commit_ledger.end_batch();
+
+        if !self.commit_ledger.is_known_commit(&event.id) {
+            log::info!(
+                "{} did not apply as a commit; keeping current state",
+                event.id
+            );
+            self.mls_snapshots.restore(&group_id, &current)?;
+            self.mls_snapshots.discard(&current);
+            self.commit_ledger = saved_ledger;
+            return Ok(());
+        }
+        self.mls_snapshots.discard(&current);
+
+        for commit in undone {
+            if !self.commit_ledger.uses_snapshot(&commit.snapshot) {
+                self.mls_snapshots.discard(&commit.snapshot);
+            }
+            // Our own commit lost; whatever it was doing has to be redone
+            if let Some(op_id) = commit.op_id {
+                self.ops_store
+                    .mark_failed(&op_id, "Commit lost a race with a competing commit")?;
+            }
+        }
+
+        self.record_system_message(
+            &group_id,
+            "Conflicting group changes were resolved in favour of the earlier commit",
+        )
+        .await?;
+        Ok(())
+    }
+
+    fn snapshot_group(&self, group_id: &GroupId) -> Option<GroupSnapshot> {
+        let group = self.storage.get_group(group_id).ok()??;
+        let members = self.storage.get_members(group_id).ok()?;
+        Some(GroupSnapshot {
+            name: group.name,
+            admins: group.admin_pubkeys,
+            members,
+            leaf_keys: self.leaf_keys(group_id),
+        })
+    }
+
+    /// Members' current leaf encryption keys, from the MLS ratchet tree
+    fn leaf_keys(&self, group_id: &GroupId) -> BTreeMap<PublicKey, Vec<u8>> {
+        let Ok(Some(group)) = MlsGroup::load(self.storage.provider.storage(), group_id) else {
+            return BTreeMap::new();
+        };
+        group
+            .members()
+            .filter_map(|member| {
+                let pubkey = group_messages::credential_pubkey(member.credential)?;
+                Some((pubkey, member.encryption_key))
+            })
+            .collect()
+    }
+
+    /// Turn the effect of an applied commit into events and system lines.
+    async fn announce_group_changes(
+        &mut self,
+        group_id: &GroupId,
+        before: &GroupSnapshot,
+    ) -> Result<()> {
+        // If we were removed the group may no longer be readable
+        let after = self.snapshot_group(group_id).unwrap_or_else(|| {
+            let mut after = before.clone();
+            after.members.remove(&self.signer.public_key());
+            after
+        });
+
+        // Follow the group if the commit changed its relays
+        if after.members.contains(&self.signer.public_key()) {
+            if let Err(e) = self.subscribe_group(group_id).await {
+                log::warn!("Failed to resubscribe to group messages: {e}");
+            }
+        }
+
+        let by = before.committer(&after, &self.signer.public_key());
+        let actor = by.map(|pk| self.display_label(&pk));
+        for change in before.diff(&after) {
+            match change {
+                GroupChange::MemberAdded(pk) => {
+                    let member = crate::ui_state::Member {
+                        public_key: pk,
+                        display_name: self.known_name(&pk),
+                        metadata: None,
+                    };
+                    self.send_event(AppEvent::MemberJoined {
+                        group_id: group_id.clone(),
+                        member,
+                        by,
+                    })?;
+                }
+                GroupChange::MemberRemoved(pk) => {
+                    self.send_event(AppEvent::MemberLeft {
+                        group_id: group_id.clone(),
+                        member: pk,
+                        by,
+                    })?;
+                }
+                GroupChange::Renamed(name) => {
+                    let line = match &actor {
+                        Some(actor) => format!("{actor} renamed the group to {name}"),
+                        None => format!("Group renamed to {name}"),
+                    };
+                    self.group_meta.add_system_message(group_id, &line)?;
+                }
+                GroupChange::AdminAdded(pk) => {
+                    let name = self.display_label(&pk);
+                    let line = match &actor {
+                        Some(actor) => format!("{actor} made {name} an admin"),
+                        None => format!("{name} is now an admin"),
+                    };
+                    self.group_meta.add_system_message(group_id, &line)?;
+                }
+                GroupChange::AdminRemoved(pk) => {
+                    let name = self.display_label(&pk);
+                    let line = match &actor {
+                        Some(actor) => format!("{actor} removed {name} as an admin"),
+                        None => format!("{name} is no longer an admin"),
+                    };
+                    self.group_meta.add_system_message(group_id, &line)?;
+                }
+            }
+        }
+
+        if self.current_group_id().as_ref() == Some(group_id) {
+            self.refresh_current_page().await?;
+        }
+        Ok(())
+    }
+
+    /// nrc-mls answers an admin's proposal by staging a commit for it. Admins publish
+    /// that commit right away; anyone else drops it, as only admins may commit.
+    fn commit_admin_proposal(&mut self, group_id: GroupId, commit_event: Event) -> Result<()> {
+        let is_admin = self
+            .storage
+            .get_group(&group_id)?
+            .is_some_and(|g| g.admin_pubkeys.contains(&self.signer.public_key()));
+        if !is_admin {
+            return group_messages::discard_pending_commit(&self.storage, &group_id);
+        }
+
+        let kind = OperationKind::GroupEvolution {
+            mls_group_id_hex: hex::encode(group_id.as_slice()),
+            description: "commit proposal".to_string(),
+            step: GroupEvolutionStep::Publish {
+                evolution_event: commit_event,
+                is_commit: true,
+                relays: self.group_relay_urls(&group_id),
+            },
+        };
+        let op_id = self
+            .ops_store
+            .enqueue(kind)
+            .context("Failed to enqueue commit operation")?;
+        log::info!("Enqueued proposal commit op {op_id}");
+        let _ = self.ops_cmd_tx.send(OpsCommand::Wake);
+        Ok(())
+    }
+
+    /// Commit every held removal proposal in one Remove commit; returns how many.
+    fn commit_pending_proposals(&mut self, group_id: GroupId) -> Result<usize> {
+        let held = self.group_meta.held_proposals(&group_id)?;
+        if held.is_empty() {
+            anyhow::bail!("No pending proposals in this group");
+        }
+
+        let members = self.storage.get_members(&group_id)?;
+        let mut removes: Vec<PublicKey> = Vec::new();
+        for proposal in &held {
+            if members.contains(&proposal.removes) && !removes.contains(&proposal.removes) {
+                removes.push(proposal.removes);
+            }
+        }
+        if !removes.is_empty() {
+            self.enqueue_removal(&group_id, &removes)?;
+        }
+        self.group_meta.mark_proposals_committed(&group_id)?;
+        Ok(held.len())
+    }
+
+    /// Persist a system line for the group and show it if the chat is open.
+    async fn record_system_message(&mut self, group_id: &GroupId, content: &str) -> Result<()> {
+        self.group_meta.add_system_message(group_id, content)?;
+        if self.current_group_id().as_ref() == Some(group_id) {
+            self.refresh_current_page().await?;
+        }
+        Ok(())
+    }
+
+    fn system_messages_for(&self, group_id: &GroupId) -> Result<Vec<Message>> {
+        let me = self.signer.public_key();
+        Ok(self
+            .group_meta
+            .system_messages(group_id)?
+            .into_iter()
+            .map(|m| Message {
+                id: None,
+                content: m.content,
+                sender: me,
+                timestamp: Timestamp::from(m.created_at as u64),
+                system: true,
+                delivery: None,
+                reply_to: None,
+            })
+            .collect())
+    }
+
+    /// Step the reply selection through the chat's messages (not local notices);
+    /// stepping past the newest one drops it.
+    fn move_message_selection(&mut self, up: bool) {
         if let Page::Chat {
-            group_id,
             messages,
             scroll_offset,
+            selected_message,
             ..
         } = &mut self.current_page
         {
-            let older_messages = self.storage.get_messages(group_id)?;
-            let skip = messages.len();
-            let additional: Vec<Message> = older_messages
-                .into_iter()
-                .rev()
-                .skip(skip)
-                .take(limit)
-                .rev()
-                .map(|m| Message {
-                    content: m.content,
-                    sender: m.pubkey,
-                    timestamp: Timestamp::now(),
-                })
+            let selectable: Vec<(usize, EventId)> = messages
+                .iter()
+                .enumerate()
+                .filter_map(|(i, m)| m.id.map(|id| (i, id)))
                 .collect();
+            let current =
+                selected_message.and_then(|id| selectable.iter().position(|(_, sel)| *sel == id));
+            let next = match (current, up) {
+                (None, true) => selectable.len().checked_sub(1),
+                (None, false) => None,
+                (Some(pos), true) => Some(pos.saturating_sub(1)),
+                (Some(pos), false) => Some(pos + 1).filter(|p| *p < selectable.len()),
+            };
+            *selected_message = next.map(|pos| selectable[pos].1);
+
+            // Keep the selected message on screen
+            if let Some(index) = next.map(|pos| selectable[pos].0) {
+                if index < *scroll_offset {
+                    *scroll_offset = index;
+                } else if index >= *scroll_offset + 20 {
+                    *scroll_offset = index - 19;
+                }
+            }
+            let _ = self.state_tx.send(self.current_page.clone());
+        }
+    }
+
+    /// A stored chat message for the chat view. Replies get their quote from
+    /// `quote_parents`, or `quoted_message` when they arrive one at a time.
+    fn chat_message(
+        m: nrc_mls_storage::messages::types::Message,
+        delivery: Option<Delivery>,
+    ) -> Message {
+        let reply_to = reply_target(&m.tags).map(|id| ReplyTo { id, parent: None });
+        Message {
+            id: Some(m.id),
+            content: m.content,
+            sender: m.pubkey,
+            timestamp: m.created_at,
+            system: false,
+            delivery,
+            reply_to,
+        }
+    }
+
+    /// The message a reply points at, if we have it locally.
+    fn quoted_message(&self, id: &EventId) -> Option<QuotedMessage> {
+        match self.storage.get_message(id) {
+            Ok(parent) => parent.map(|p| QuotedMessage {
+                sender: p.pubkey,
+                content: p.content,
+            }),
+            Err(e) => {
+                log::warn!("Failed to look up replied-to message {id}: {e}");
+                None
+            }
+        }
+    }
+
+    /// Our petname for someone, else their cached profile name.
+    fn known_name(&self, pk: &PublicKey) -> Option<String> {
+        self.petnames
+            .get(pk)
+            .cloned()
+            .or_else(|| self.profiles.display_name(pk))
+    }
+
+    /// Petname or cached profile name, or a short npub.
+    fn display_label(&self, pk: &PublicKey) -> String {
+        self.known_name(pk).unwrap_or_else(|| {
+            let npub = crate::utils::pubkey_to_bech32_safe(pk);
+            if npub.len() > 12 {
+                format!("{}…", &npub[..12])
+            } else {
+                npub
+            }
+        })
+    }
 
+    /// The group shown in the chat view, if it's a real group (not the empty placeholder).
+    fn current_group_id(&self) -> Option<GroupId> {
+        match &self.current_page {
+            Page::Chat {
+                groups, group_id, ..
+            } if groups.iter().any(|g| g.id == *group_id) => Some(group_id.clone()),
+            _ => None,
+        }
+    }
+
+    pub async fn load_older_messages(&mut self, limit: usize) -> Result<()> {
+        let Page::Chat {
+            group_id, messages, ..
+        } = &self.current_page
+        else {
+            return Ok(());
+        };
+        let older_messages = self.storage.get_messages(group_id)?;
+        let deliveries = self.deliveries.for_group(group_id)?;
+        let skip = messages.iter().filter(|m| !m.system).count();
+        let mut stored: Vec<Message> = older_messages
+            .into_iter()
+            .map(|m| {
+                let delivery = deliveries.get(&m.wrapper_event_id).cloned();
+                Self::chat_message(m, delivery)
+            })
+            .collect();
+        quote_parents(&mut stored);
+        let additional: Vec<Message> = stored
+            .into_iter()
+            .rev()
+            .skip(skip)
+            .take(limit)
+            .rev()
+            .collect();
+
+        if let Page::Chat {
+            messages,
+            scroll_offset,
+            ..
+        } = &mut self.current_page
+        {
             if !additional.is_empty() {
                 let mut new_messages = additional;
                 new_messages.append(messages);
@@ -1244,13 +4037,21 @@ impl App {
     }
 
     pub async fn refresh_current_page(&mut self) -> Result<()> {
+        // Forms hold what was typed (and /export the key); nothing to reload
+        if matches!(
+            self.current_page,
+            Page::ChangePassword { .. } | Page::ExportKey { .. }
+        ) {
+            return Ok(());
+        }
         let page_type = self.current_page.page_type();
-        let refreshed = self.load_page_data(page_type).await?;
+        let mut refreshed = self.load_page_data(page_type).await?;
 
         if let (
             Page::Chat {
                 input: old_input,
                 scroll_offset: old_scroll,
+                selected_message: old_selected,
                 ..
             },
             Page::Chat {
@@ -1264,14 +4065,55 @@ impl App {
             if let Page::Chat {
                 input,
                 scroll_offset,
+                messages,
+                selected_message,
                 ..
             } = &mut refreshed
             {
                 *input = old_input.clone();
                 *scroll_offset = *old_scroll;
+                // Keep the reply target if it's still in the chat
+                *selected_message =
+                    old_selected.filter(|id| messages.iter().any(|m| m.id == Some(*id)));
             }
             self.current_page = refreshed.clone();
             let _ = self.state_tx.send(refreshed);
+        } else if let (
+            Page::OpsDashboard {
+                items: old_items,
+                selected: old_selected,
+                show_detail: old_show_detail,
+            },
+            Page::OpsDashboard {
+                items,
+                selected,
+                show_detail,
+            },
+        ) = (&self.current_page, &mut refreshed)
+        {
+            // Keep the cursor on the same op even if the list reordered
+            let selected_id = old_items.get(*old_selected).map(|it| it.id.clone());
+            *selected = selected_id
+                .and_then(|id| items.iter().position(|it| it.id == id))
+                .unwrap_or((*old_selected).min(items.len().saturating_sub(1)));
+            *show_detail = *old_show_detail;
+            self.current_page = refreshed.clone();
+            let _ = self.state_tx.send(refreshed);
+        } else if let (
+            Page::Contacts {
+                contacts: old_contacts,
+                selected: old_selected,
+            },
+            Page::Contacts { contacts, selected },
+        ) = (&self.current_page, &mut refreshed)
+        {
+            // Keep the cursor on the same person when names change the order
+            let selected_pk = old_contacts.get(*old_selected).map(|c| c.public_key);
+            *selected = selected_pk
+                .and_then(|pk| contacts.iter().position(|c| c.public_key == pk))
+                .unwrap_or((*old_selected).min(contacts.len().saturating_sub(1)));
+            self.current_page = refreshed.clone();
+            let _ = self.state_tx.send(refreshed);
         } else {
             self.current_page = refreshed.clone();
             let _ = self.state_tx.send(refreshed);
@@ -0,0 +1,291 @@
+use anyhow::Result;
+use nostr_sdk::prelude::*;
+use openmls::group::GroupId;
+use rusqlite::{params, Connection};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// How long we keep the state needed to undo an applied commit (NIP-EE: "a short period")
+pub const COMMIT_RETENTION: Duration = Duration::from_secs(600);
+
+/// NIP-EE commit ordering: lowest `created_at` wins, ties go to the lowest event id.
+pub fn commit_order(a: &Event, b: &Event) -> Ordering {
+    (a.created_at, a.id).cmp(&(b.created_at, b.id))
+}
+
+/// A commit we applied, with what's needed to undo it.
+#[derive(Clone, Debug)]
+pub struct AppliedCommit {
+    pub group_id: GroupId,
+    // Epoch the commit moved the group away from
+    pub epoch: u64,
+    pub event_id: EventId,
+    pub created_at: Timestamp,
+    // MLS state from before the batch that applied this commit
+    pub snapshot: PathBuf,
+    // Index into the replay log where that batch starts
+    pub replay_from: usize,
+    // Our own op that published the commit, if any
+    pub op_id: Option<String>,
+    pub applied_at: Instant,
+}
+
+impl AppliedCommit {
+    fn loses_to(&self, event: &Event) -> bool {
+        (event.created_at, event.id) < (self.created_at, self.event_id)
+    }
+}
+
+/// Recently applied commits plus every group event processed since the oldest of them.
+#[derive(Clone, Debug, Default)]
+pub struct CommitLedger {
+    commits: Vec<AppliedCommit>,
+    replay: Vec<(GroupId, Event)>,
+}
+
+impl CommitLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a processing batch; returns the replay index for commits applied in it.
+    pub fn begin_batch(&self) -> usize {
+        self.replay.len()
+    }
+
+    pub fn record_processed(&mut self, group_id: GroupId, event: Event) {
+        self.replay.push((group_id, event));
+    }
+
+    /// Drop the batch's events again if no commit needs them for replay.
+    pub fn end_batch(&mut self) {
+        if self.commits.is_empty() {
+            self.replay.clear();
+        }
+    }
+
+    pub fn record_commit(&mut self, commit: AppliedCommit) {
+        self.commits.push(commit);
+    }
+
+    pub fn is_known_commit(&self, id: &EventId) -> bool {
+        self.commits.iter().any(|c| c.event_id == *id)
+    }
+
+    pub fn uses_snapshot(&self, snapshot: &Path) -> bool {
+        self.commits.iter().any(|c| c.snapshot == snapshot)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&AppliedCommit> {
+        self.commits.get(index)
+    }
+
+    /// Earliest applied commit in `group_id` that `event` would have beaten.
+    pub fn challenged_by(&self, group_id: &GroupId, event: &Event) -> Option<usize> {
+        self.commits
+            .iter()
+            .position(|c| c.group_id == *group_id && c.loses_to(event))
+    }
+
+    /// Forget the commit at `index` and everything applied after it in the same group.
+    ///
+    /// Returns the group's events to reprocess from that commit's snapshot (losing
+    /// commit excluded, NIP-EE order) and the commits that were undone. Other groups'
+    /// commits and events stay as they are.
+    pub fn rollback(&mut self, index: usize) -> (Vec<Event>, Vec<AppliedCommit>) {
+        if index >= self.commits.len() {
+            return (Vec::new(), Vec::new());
+        }
+        let group_id = self.commits[index].group_id.clone();
+        let (undone, kept): (Vec<AppliedCommit>, Vec<AppliedCommit>) = self
+            .commits
+            .drain(index..)
+            .partition(|c| c.group_id == group_id);
+        self.commits.extend(kept);
+        let loser = undone[0].event_id;
+        let start = undone[0].replay_from.min(self.replay.len());
+
+        let mut replay = Vec::new();
+        let mut removed = Vec::new();
+        let mut log = Vec::with_capacity(self.replay.len());
+        for (i, (g, event)) in self.replay.drain(..).enumerate() {
+            if i >= start && g == group_id {
+                removed.push(i);
+                if event.id != loser {
+                    replay.push(event);
+                }
+            } else {
+                log.push((g, event));
+            }
+        }
+        self.replay = log;
+        for c in &mut self.commits {
+            c.replay_from -= removed.iter().filter(|i| **i < c.replay_from).count();
+        }
+        replay.sort_by(commit_order);
+        replay.dedup_by_key(|e| e.id);
+        (replay, undone)
+    }
+
+    /// Drop commits older than `retain`; returns snapshots nobody needs anymore.
+    pub fn expire(&mut self, now: Instant, retain: Duration) -> Vec<PathBuf> {
+        let keep_from = self
+            .commits
+            .iter()
+            .position(|c| now.duration_since(c.applied_at) < retain)
+            .unwrap_or(self.commits.len());
+        let expired: Vec<AppliedCommit> = self.commits.drain(..keep_from).collect();
+
+        // Rebase the replay log on the oldest commit we still keep
+        let start = self
+            .commits
+            .first()
+            .map(|c| c.replay_from)
+            .unwrap_or(self.replay.len())
+            .min(self.replay.len());
+        self.replay.drain(..start);
+        for c in &mut self.commits {
+            c.replay_from -= start;
+        }
+
+        let mut stale: Vec<PathBuf> = Vec::new();
+        for c in expired {
+            if !self.uses_snapshot(&c.snapshot) && !stale.contains(&c.snapshot) {
+                stale.push(c.snapshot);
+            }
+        }
+        stale
+    }
+}
+
+// MLS storage tables holding a group's state, and how their rows name the group:
+// nrc-mls stores the raw group id, openmls its JSON encoding
+const GROUP_TABLES: &[(&str, &str)] = &[
+    ("groups", "mls_group_id"),
+    ("group_relays", "mls_group_id"),
+    ("group_exporter_secrets", "mls_group_id"),
+    ("openmls_group_data", "group_id"),
+    ("openmls_epoch_keys_pairs", "group_id"),
+    ("openmls_own_leaf_nodes", "group_id"),
+    ("openmls_proposals", "group_id"),
+];
+
+// Our own key pairs aren't tied to a group; a restore only ever adds them back
+const KEY_TABLES: &[&str] = &["openmls_encryption_keys", "openmls_signature_keys"];
+
+/// Copies of one group's MLS state in nrc.db, used to roll back a losing commit.
+///
+/// Messages, welcomes and every other group are left alone by a restore.
+#[derive(Clone)]
+pub struct MlsSnapshots {
+    db_path: PathBuf,
+    dir: PathBuf,
+}
+
+impl MlsSnapshots {
+    pub fn new(datadir: &Path) -> Self {
+        let dir = datadir.join("mls_snapshots");
+        // Snapshots are only meaningful within one session
+        let _ = fs::remove_dir_all(&dir);
+        Self {
+            db_path: datadir.join("nrc.db"),
+            dir,
+        }
+    }
+
+    fn open(&self) -> Result<Connection> {
+        let conn = Connection::open(&self.db_path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        Ok(conn)
+    }
+
+    fn has_table(conn: &Connection, schema: &str, table: &str) -> Result<bool> {
+        let found: i64 = conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM {schema}.sqlite_master WHERE type = 'table' AND name = ?1"
+            ),
+            params![table],
+            |row| row.get(0),
+        )?;
+        Ok(found > 0)
+    }
+
+    // Each way a table can name `group_id`, as bound for `column = ?1`
+    fn group_key(column: &str, group_id: &GroupId) -> Result<Vec<u8>> {
+        Ok(match column {
+            "mls_group_id" => group_id.as_slice().to_vec(),
+            _ => serde_json::to_vec(group_id)?,
+        })
+    }
+
+    /// Copy `group_id`'s MLS state aside.
+    pub fn take(&self, group_id: &GroupId) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("{}.db", Uuid::new_v4()));
+        let conn = self.open()?;
+        conn.execute(
+            "ATTACH DATABASE ?1 AS snap",
+            params![path.to_string_lossy()],
+        )?;
+        for (table, column) in GROUP_TABLES {
+            if !Self::has_table(&conn, "main", table)? {
+                continue;
+            }
+            conn.execute(
+                &format!(
+                    "CREATE TABLE snap.\"{table}\" AS
+                     SELECT * FROM main.\"{table}\" WHERE \"{column}\" = ?1"
+                ),
+                params![Self::group_key(column, group_id)?],
+            )?;
+        }
+        for table in KEY_TABLES {
+            if Self::has_table(&conn, "main", table)? {
+                conn.execute_batch(&format!(
+                    "CREATE TABLE snap.\"{table}\" AS SELECT * FROM main.\"{table}\""
+                ))?;
+            }
+        }
+        conn.execute("DETACH DATABASE snap", [])?;
+        Ok(path)
+    }
+
+    /// Put `group_id`'s MLS state back the way `snapshot` saw it.
+    pub fn restore(&self, group_id: &GroupId, snapshot: &Path) -> Result<()> {
+        let mut conn = self.open()?;
+        conn.execute(
+            "ATTACH DATABASE ?1 AS snap",
+            params![snapshot.to_string_lossy()],
+        )?;
+        let tx = conn.transaction()?;
+        for (table, column) in GROUP_TABLES {
+            if !Self::has_table(&tx, "snap", table)? {
+                continue;
+            }
+            tx.execute(
+                &format!("DELETE FROM main.\"{table}\" WHERE \"{column}\" = ?1"),
+                params![Self::group_key(column, group_id)?],
+            )?;
+            tx.execute_batch(&format!(
+                "INSERT INTO main.\"{table}\" SELECT * FROM snap.\"{table}\""
+            ))?;
+        }
+        for table in KEY_TABLES {
+            if Self::has_table(&tx, "snap", table)? {
+                tx.execute_batch(&format!(
+                    "INSERT OR IGNORE INTO main.\"{table}\" SELECT * FROM snap.\"{table}\""
+                ))?;
+            }
+        }
+        tx.commit()?;
+        conn.execute("DETACH DATABASE snap", [])?;
+        Ok(())
+    }
+
+    pub fn discard(&self, snapshot: &Path) {
+        let _ = fs::remove_file(snapshot);
+    }
+}
@@ -1,3 +1,15 @@
+use anyhow::{anyhow, Context, Result};
+use nostr_sdk::RelayUrl;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::key_packages::KeyPackagePolicy;
+use crate::key_storage::{DEFAULT_LOG_N, MAX_LOG_N, MIN_LOG_N};
+
+pub const CONFIG_FILE: &str = "config.toml";
+
 /// Get default relay URLs - uses local relay for tests when TEST_USE_LOCAL_RELAY is set
 pub fn get_default_relays() -> &'static [&'static str] {
     #[cfg(test)]
@@ -7,13 +19,7 @@ pub fn get_default_relays() -> &'static [&'static str] {
         }
     }
 
-    &[
-        "wss://relay.damus.io",
-        "wss://nos.lol",
-        "wss://relay.nostr.band",
-        "wss://relay.snort.social",
-        "wss://nostr.wine",
-    ]
+    DEFAULT_RELAYS
 }
 
 /// Default relay URLs used throughout the application
@@ -24,3 +30,213 @@ pub const DEFAULT_RELAYS: &[&str] = &[
     "wss://relay.snort.social",
     "wss://nostr.wine",
 ];
+
+/// When we issue a self-update commit to rotate our leaf key in a group (NIP-EE forward secrecy)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyRotationPolicy {
+    pub interval: Duration,
+    pub max_messages: u64,
+}
+
+impl Default for KeyRotationPolicy {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(7 * 24 * 60 * 60),
+            max_messages: 1000,
+        }
+    }
+}
+
+impl KeyRotationPolicy {
+    /// Due once `interval` has passed since the last rotation (or since we started
+    /// tracking the group) or `max_messages` went by, whichever comes first.
+    pub fn is_due(
+        &self,
+        last_rotated_at: Option<i64>,
+        tracked_since: i64,
+        messages_since: u64,
+        now: i64,
+    ) -> bool {
+        let since = last_rotated_at.unwrap_or(tracked_since);
+        now.saturating_sub(since) >= self.interval.as_secs() as i64
+            || messages_since >= self.max_messages
+    }
+}
+
+/// User settings from `<datadir>/config.toml`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // Relays we connect to, stamp into new groups and publish KeyPackages to
+    pub relays: Vec<String>,
+    pub key_rotation: KeyRotationSettings,
+    pub key_packages: KeyPackageSettings,
+    pub auth: AuthSettings,
+    pub keys: KeySettings,
+    pub lock: LockSettings,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyRotationSettings {
+    pub interval_secs: u64,
+    pub max_messages: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyPackageSettings {
+    pub count: usize,
+    pub rotate_after_secs: u64,
+}
+
+/// NIP-42: relays we answer AUTH challenges for (opt-in, since AUTH reveals our pubkey)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthSettings {
+    pub relays: Vec<String>,
+}
+
+/// How stored keys are encrypted (NIP-49)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeySettings {
+    // scrypt cost used when a password is changed; raise it on fast machines
+    pub log_n: u8,
+}
+
+/// Locking the TUI when nobody is using it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LockSettings {
+    // Lock after this many seconds without a key press; 0 never locks
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for LockSettings {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: 15 * 60,
+        }
+    }
+}
+
+impl Default for KeySettings {
+    fn default() -> Self {
+        Self {
+            log_n: DEFAULT_LOG_N,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            relays: get_default_relays().iter().map(|r| r.to_string()).collect(),
+            key_rotation: KeyRotationSettings::default(),
+            key_packages: KeyPackageSettings::default(),
+            auth: AuthSettings::default(),
+            keys: KeySettings::default(),
+            lock: LockSettings::default(),
+        }
+    }
+}
+
+impl Default for KeyRotationSettings {
+    fn default() -> Self {
+        let policy = KeyRotationPolicy::default();
+        Self {
+            interval_secs: policy.interval.as_secs(),
+            max_messages: policy.max_messages,
+        }
+    }
+}
+
+impl Default for KeyPackageSettings {
+    fn default() -> Self {
+        let policy = KeyPackagePolicy::default();
+        Self {
+            count: policy.target_count,
+            rotate_after_secs: policy.rotate_after.as_secs(),
+        }
+    }
+}
+
+impl Config {
+    /// Load `config.toml` from the datadir, writing the defaults there on first run
+    pub fn load(datadir: &Path) -> Result<Self> {
+        let path = datadir.join(CONFIG_FILE);
+        if !path.exists() {
+            let config = Self::default();
+            fs::create_dir_all(datadir)?;
+            fs::write(&path, toml::to_string_pretty(&config)?)?;
+            return Ok(config);
+        }
+
+        let raw = fs::read_to_string(&path)?;
+        let mut config: Self =
+            toml::from_str(&raw).with_context(|| format!("Invalid {}", path.display()))?;
+        if config.relays.is_empty() {
+            config.relays = Self::default().relays;
+        }
+        config.relay_urls()?;
+        config.auth_relay_urls()?;
+        if !(MIN_LOG_N..=MAX_LOG_N).contains(&config.keys.log_n) {
+            return Err(anyhow!(
+                "keys.log_n in {} must be between {MIN_LOG_N} and {MAX_LOG_N}",
+                path.display()
+            ));
+        }
+        Ok(config)
+    }
+
+    /// `--relay` flags replace the configured relay set entirely
+    pub fn with_relay_overrides(mut self, relays: Vec<String>) -> Result<Self> {
+        if !relays.is_empty() {
+            self.relays = relays;
+            self.relay_urls()?;
+        }
+        Ok(self)
+    }
+
+    pub fn relay_urls(&self) -> Result<Vec<RelayUrl>> {
+        self.relays
+            .iter()
+            .map(|r| RelayUrl::parse(r).with_context(|| format!("Invalid relay URL '{r}'")))
+            .collect()
+    }
+
+    pub fn auth_relay_urls(&self) -> Result<Vec<RelayUrl>> {
+        self.auth
+            .relays
+            .iter()
+            .map(|r| RelayUrl::parse(r).with_context(|| format!("Invalid auth relay URL '{r}'")))
+            .collect()
+    }
+
+    /// Whether we may answer this relay's NIP-42 AUTH challenge
+    pub fn auth_enabled_for(&self, relay: &RelayUrl) -> bool {
+        self.auth_relay_urls()
+            .map(|urls| urls.contains(relay))
+            .unwrap_or(false)
+    }
+
+    pub fn key_rotation_policy(&self) -> KeyRotationPolicy {
+        KeyRotationPolicy {
+            interval: Duration::from_secs(self.key_rotation.interval_secs),
+            max_messages: self.key_rotation.max_messages,
+        }
+    }
+
+    /// How long the TUI may sit idle before it locks, if it locks at all
+    pub fn idle_lock_timeout(&self) -> Option<Duration> {
+        (self.lock.idle_timeout_secs > 0).then(|| Duration::from_secs(self.lock.idle_timeout_secs))
+    }
+
+    pub fn key_package_policy(&self) -> KeyPackagePolicy {
+        KeyPackagePolicy {
+            target_count: self.key_packages.count,
+            rotate_after: Duration::from_secs(self.key_packages.rotate_after_secs),
+        }
+    }
+}
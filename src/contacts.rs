@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use nostr_sdk::prelude::*;
+use rusqlite::{params, Connection};
+
+use crate::migrations;
+
+/// Someone in our contacts: followed in our kind 3 list and/or given a petname
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContactRecord {
+    pub pubkey: PublicKey,
+    pub petname: Option<String>,
+    pub followed: bool,
+}
+
+/// Contacts and local petnames (lives in nrc.db).
+///
+/// Petnames are ours alone: they are never written into a kind 3 or published.
+#[derive(Clone)]
+pub struct ContactStore {
+    db_path: PathBuf,
+}
+
+impl ContactStore {
+    pub fn new(datadir: &Path) -> Result<Self> {
+        let store = Self {
+            db_path: datadir.join("nrc.db"),
+        };
+        store.init()?;
+        Ok(store)
+    }
+
+    fn init(&self) -> Result<()> {
+        let mut conn = Connection::open(&self.db_path)?;
+        migrations::migrate(&mut conn, migrations::NRC_DB)?;
+        Ok(())
+    }
+
+    /// Replace who we follow with the `p` tags of our latest kind 3; petnames are kept.
+    /// Returns how many contacts are followed now.
+    pub fn import_follows(&self, follows: &[PublicKey]) -> Result<usize> {
+        let now = Utc::now().timestamp();
+        let mut conn = Connection::open(&self.db_path)?;
+        let tx = conn.transaction()?;
+        tx.execute("UPDATE contacts SET followed = 0", [])?;
+        for pk in follows {
+            tx.execute(
+                "INSERT INTO contacts (pubkey, followed, updated_at) VALUES (?1, 1, ?2)
+                 ON CONFLICT(pubkey) DO UPDATE SET followed = 1, updated_at = excluded.updated_at",
+                params![pk.to_hex(), now],
+            )?;
+        }
+        // Unfollowed and never named: nothing left to keep
+        tx.execute(
+            "DELETE FROM contacts WHERE followed = 0 AND petname IS NULL",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(follows.len())
+    }
+
+    /// Set (or with `None`, clear) our local name for someone
+    pub fn set_petname(&self, pubkey: &PublicKey, petname: Option<&str>) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let petname = petname.map(str::trim).filter(|s| !s.is_empty());
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT INTO contacts (pubkey, petname, followed, updated_at) VALUES (?1, ?2, 0, ?3)
+             ON CONFLICT(pubkey) DO UPDATE SET petname = excluded.petname, updated_at = excluded.updated_at",
+            params![pubkey.to_hex(), petname, now],
+        )?;
+        conn.execute(
+            "DELETE FROM contacts WHERE followed = 0 AND petname IS NULL",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn petnames(&self) -> Result<HashMap<PublicKey, String>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter_map(|c| c.petname.map(|name| (c.pubkey, name)))
+            .collect())
+    }
+
+    pub fn list(&self) -> Result<Vec<ContactRecord>> {
+        let conn = Connection::open(&self.db_path)?;
+        let mut stmt =
+            conn.prepare("SELECT pubkey, petname, followed FROM contacts ORDER BY updated_at")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, bool>(2)?,
+            ))
+        })?;
+        let mut out = Vec::new();
+        for r in rows {
+            let (pk, petname, followed) = r?;
+            out.push(ContactRecord {
+                pubkey: PublicKey::from_hex(&pk)?,
+                petname,
+                followed,
+            });
+        }
+        Ok(out)
+    }
+}
+
+/// Pubkeys followed by a kind 3 contact list
+pub fn follow_list(event: &Event) -> Vec<PublicKey> {
+    event.tags.public_keys().copied().collect()
+}
+
+/// Our newest kind 3, if any relay has one
+pub async fn fetch_follow_list(client: &Client, pubkey: PublicKey) -> Result<Option<Event>> {
+    let filter = Filter::new()
+        .kind(Kind::ContactList)
+        .author(pubkey)
+        .limit(1);
+    let events = client.fetch_events(filter, Duration::from_secs(5)).await?;
+    Ok(events.into_iter().max_by_key(|e| e.created_at))
+}
@@ -0,0 +1,207 @@
+use anyhow::Result;
+use chrono::Utc;
+use nostr_sdk::prelude::*;
+use openmls::group::GroupId;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::migrations;
+
+/// Where one of our chat messages is on its way to the relays
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    // Waiting in the ops queue (offline, or backing off after an error)
+    Queued,
+    // Handed to the relays, no OK yet
+    Sent,
+    // At least one relay answered OK
+    Acked,
+    // The send op gave up or was cancelled; /retry resends it
+    Failed,
+}
+
+impl DeliveryStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Queued => "Queued",
+            DeliveryStatus::Sent => "Sent",
+            DeliveryStatus::Acked => "Acked",
+            DeliveryStatus::Failed => "Failed",
+        }
+    }
+
+    fn from_str_lossy(s: &str) -> Self {
+        match s {
+            "Sent" => DeliveryStatus::Sent,
+            "Acked" => DeliveryStatus::Acked,
+            "Failed" => DeliveryStatus::Failed,
+            _ => DeliveryStatus::Queued,
+        }
+    }
+}
+
+/// Delivery state of a message we sent, keyed by its kind 445 wrapper event
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delivery {
+    pub event_id: EventId,
+    // The SendMessage op that publishes it
+    pub op_id: String,
+    pub status: DeliveryStatus,
+    // Relays that acknowledged the event with an OK
+    pub acked_by: Vec<String>,
+}
+
+/// Links our outgoing messages to their send ops (lives in nrc.db)
+#[derive(Clone)]
+pub struct DeliveryStore {
+    db_path: PathBuf,
+}
+
+impl DeliveryStore {
+    pub fn new(datadir: &Path) -> Result<Self> {
+        let store = Self {
+            db_path: datadir.join("nrc.db"),
+        };
+        store.init()?;
+        Ok(store)
+    }
+
+    fn init(&self) -> Result<()> {
+        let mut conn = Connection::open(&self.db_path)?;
+        migrations::migrate(&mut conn, migrations::NRC_DB)?;
+        Ok(())
+    }
+
+    pub fn track(&self, event_id: &EventId, group_id: &GroupId, op_id: &str) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO message_delivery (event_id, mls_group_id, op_id, status, acked_relays, updated_at)
+             VALUES (?1, ?2, ?3, 'Queued', '[]', ?4)",
+            params![
+                event_id.to_hex(),
+                hex::encode(group_id.as_slice()),
+                op_id,
+                now
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, event_id: &EventId) -> Result<Option<Delivery>> {
+        let conn = Connection::open(&self.db_path)?;
+        let row = conn
+            .query_row(
+                "SELECT event_id, op_id, status, acked_relays FROM message_delivery WHERE event_id = ?1",
+                params![event_id.to_hex()],
+                Self::row,
+            )
+            .optional()?;
+        row.map(Self::parse).transpose()
+    }
+
+    /// Deliveries of our messages in a group, for decorating the chat view
+    pub fn for_group(&self, group_id: &GroupId) -> Result<HashMap<EventId, Delivery>> {
+        let conn = Connection::open(&self.db_path)?;
+        let mut stmt = conn.prepare(
+            "SELECT event_id, op_id, status, acked_relays FROM message_delivery
+             WHERE mls_group_id = ?1",
+        )?;
+        let rows = stmt.query_map(params![hex::encode(group_id.as_slice())], Self::row)?;
+        let mut out = HashMap::new();
+        for r in rows {
+            let delivery = Self::parse(r?)?;
+            out.insert(delivery.event_id, delivery);
+        }
+        Ok(out)
+    }
+
+    pub fn failed_in_group(&self, group_id: &GroupId) -> Result<Vec<Delivery>> {
+        Ok(self
+            .for_group(group_id)?
+            .into_values()
+            .filter(|d| d.status == DeliveryStatus::Failed)
+            .collect())
+    }
+
+    /// Back in the queue (retrying, or resent by the user). Acked messages stay acked.
+    pub fn mark_queued(&self, event_id: &EventId) -> Result<bool> {
+        self.set_status(event_id, DeliveryStatus::Queued, &["Sent", "Failed"])
+    }
+
+    pub fn mark_sent(&self, event_id: &EventId) -> Result<bool> {
+        self.set_status(event_id, DeliveryStatus::Sent, &["Queued", "Failed"])
+    }
+
+    pub fn mark_failed(&self, event_id: &EventId) -> Result<bool> {
+        self.set_status(event_id, DeliveryStatus::Failed, &["Queued", "Sent"])
+    }
+
+    /// Record OKs from relays; returns false if the event isn't one of our messages
+    pub fn record_acks(&self, event_id: &EventId, relays: &[RelayUrl]) -> Result<bool> {
+        let Some(mut delivery) = self.get(event_id)? else {
+            return Ok(false);
+        };
+        let before = delivery.clone();
+        for relay in relays {
+            let url = relay.to_string();
+            if !delivery.acked_by.contains(&url) {
+                delivery.acked_by.push(url);
+            }
+        }
+        if !delivery.acked_by.is_empty() {
+            delivery.status = DeliveryStatus::Acked;
+        }
+        if delivery == before {
+            return Ok(false);
+        }
+
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "UPDATE message_delivery SET status = ?2, acked_relays = ?3, updated_at = ?4
+             WHERE event_id = ?1",
+            params![
+                event_id.to_hex(),
+                delivery.status.as_str(),
+                serde_json::to_string(&delivery.acked_by)?,
+                now
+            ],
+        )?;
+        Ok(true)
+    }
+
+    fn set_status(&self, event_id: &EventId, to: DeliveryStatus, from: &[&str]) -> Result<bool> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        let from = from
+            .iter()
+            .map(|s| format!("'{s}'"))
+            .collect::<Vec<_>>()
+            .join(",");
+        let n = conn.execute(
+            &format!(
+                "UPDATE message_delivery SET status = ?2, updated_at = ?3
+                 WHERE event_id = ?1 AND status IN ({from})"
+            ),
+            params![event_id.to_hex(), to.as_str(), now],
+        )?;
+        Ok(n > 0)
+    }
+
+    fn row(row: &rusqlite::Row) -> rusqlite::Result<(String, String, String, String)> {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    }
+
+    fn parse(
+        (event_id, op_id, status, acked_relays): (String, String, String, String),
+    ) -> Result<Delivery> {
+        Ok(Delivery {
+            event_id: EventId::from_hex(&event_id)?,
+            op_id,
+            status: DeliveryStatus::from_str_lossy(&status),
+            acked_by: serde_json::from_str(&acked_relays)?,
+        })
+    }
+}
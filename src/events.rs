@@ -3,7 +3,18 @@ use nostr_sdk::prelude::*;
 use openmls::group::GroupId;
 use std::time::Duration;
 
-use crate::ui_state::{Member, Message, Page};
+use crate::delivery::DeliveryStatus;
+use crate::nip05::Nip05Address;
+use crate::signer::AccountSigner;
+use crate::ui_state::{Member, Message, Page, RelayInfo};
+
+/// What a command does with the pubkey once its NIP-05 address is looked up
+#[derive(Debug, Clone)]
+pub enum Nip05Action {
+    Dm,
+    // The nickname to set; None clears it
+    Nick(Option<String>),
+}
 
 #[derive(Debug, Clone)]
 pub enum AppEvent {
@@ -20,6 +31,8 @@ pub enum AppEvent {
     },
     JoinGroup(GroupId),
     LeaveGroup(GroupId),
+    // Stop an operation from the ops dashboard and undo its local side effects
+    CancelOperation(String),
 
     MessageReceived {
         group_id: GroupId,
@@ -31,12 +44,31 @@ pub enum AppEvent {
     MemberJoined {
         group_id: GroupId,
         member: Member,
+        // Who added them, when the commit's sender is known
+        by: Option<PublicKey>,
     },
     MemberLeft {
         group_id: GroupId,
         member: PublicKey,
+        by: Option<PublicKey>,
     },
     ConnectionStatusChanged(ConnectionStatus),
+    // A relay sent a NIP-42 AUTH challenge
+    RelayAuthChallenge {
+        relay_url: RelayUrl,
+        challenge: String,
+    },
+    // Relay monitor -> UI: per-relay state changed
+    RelayStatusUpdated {
+        relays: Vec<RelayInfo>,
+    },
+    // One of our messages moved along: picked up, acked by relays, or given up on
+    MessageDelivery {
+        event_id: EventId,
+        status: DeliveryStatus,
+        // Relays that acked (Acked only)
+        relays: Vec<RelayUrl>,
+    },
 
     RefreshCurrentPage,
     FlashMessage(String, Duration),
@@ -48,7 +80,10 @@ pub enum AppEvent {
     GroupCreated {
         group_id: GroupId,
     },
-    KeyPackagePublished,
+    // A relay accepted one of our KeyPackages
+    KeyPackagePublished {
+        event_id: EventId,
+    },
     ProfilePublished,
     NetworkError {
         error: String,
@@ -69,6 +104,37 @@ pub enum AppEvent {
     ProfileMetadataReceived {
         pubkey: PublicKey,
         metadata: Metadata,
+        created_at: Timestamp,
+    },
+    // Our kind 3 follow list, fetched after login
+    FollowListReceived {
+        event: Event,
+    },
+    // Background re-encryption for /password finished
+    PasswordChangeFinished {
+        error: Option<String>,
+    },
+    // /export: confirmed, show the key
+    RevealKey,
+    // /export: the reveal with this generation timed out; clear screen and clipboard
+    ClearExportedKey(u64),
+    // Idle timeout or /lock: wipe the keys and ask for the password again
+    Lock,
+    // Onboarding: the bunker accepted us (and said whose key it holds), or didn't
+    BunkerConnectFinished {
+        result: Result<Box<AccountSigner>, String>,
+    },
+    // Background NIP-05 check finished
+    Nip05Checked {
+        pubkey: PublicKey,
+        address: Nip05Address,
+        verified: bool,
+    },
+    // A NIP-05 address typed into /dm or /nick was looked up
+    Nip05Resolved {
+        input: String,
+        action: Nip05Action,
+        result: Result<PublicKey, String>,
     },
     // Orchestrator -> UI: requests a storage operation for an in-flight op
     OpNeedsStorageCreateGroup {
@@ -78,6 +144,30 @@ pub enum AppEvent {
         // Suggested display name for the group
         group_name: String,
     },
+    // Orchestrator -> UI: requests storage-side creation of a multi-member group
+    OpNeedsStorageCreateMultiGroup {
+        op_id: String,
+        name: String,
+        members: Vec<PublicKey>,
+        key_packages: Vec<Event>,
+    },
+    // Orchestrator -> UI: requests an MLS Add commit for an invitee
+    OpNeedsStorageAddMember {
+        op_id: String,
+        group_id: GroupId,
+        key_package: Event,
+    },
+    // Orchestrator -> UI: requests a self-update commit to rotate our leaf key
+    OpNeedsStorageSelfUpdate {
+        op_id: String,
+        group_id: GroupId,
+    },
+    // Orchestrator -> UI: a published commit was acked and can be merged locally
+    OpNeedsStorageMergeCommit {
+        op_id: String,
+        group_id: GroupId,
+        commit_event: Event,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -87,7 +177,7 @@ pub struct GroupInvite {
     pub group_name: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ConnectionStatus {
     Connected,
     Connecting,
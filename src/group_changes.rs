@@ -0,0 +1,66 @@
+use nostr_sdk::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The parts of a group that commits can change and that we announce in the chat
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupSnapshot {
+    pub name: String,
+    pub admins: BTreeSet<PublicKey>,
+    pub members: BTreeSet<PublicKey>,
+    /// Each member's MLS leaf encryption key
+    pub leaf_keys: BTreeMap<PublicKey, Vec<u8>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GroupChange {
+    MemberAdded(PublicKey),
+    MemberRemoved(PublicKey),
+    Renamed(String),
+    AdminAdded(PublicKey),
+    AdminRemoved(PublicKey),
+}
+
+impl GroupSnapshot {
+    /// Changes needed to get from `self` to `after`, membership first.
+    pub fn diff(&self, after: &GroupSnapshot) -> Vec<GroupChange> {
+        let mut changes = Vec::new();
+        for pk in after.members.difference(&self.members) {
+            changes.push(GroupChange::MemberAdded(*pk));
+        }
+        for pk in self.members.difference(&after.members) {
+            changes.push(GroupChange::MemberRemoved(*pk));
+        }
+        if after.name != self.name {
+            changes.push(GroupChange::Renamed(after.name.clone()));
+        }
+        for pk in after.admins.difference(&self.admins) {
+            changes.push(GroupChange::AdminAdded(*pk));
+        }
+        for pk in self.admins.difference(&after.admins) {
+            changes.push(GroupChange::AdminRemoved(*pk));
+        }
+        changes
+    }
+
+    /// Who made the commit that turned `self` into `after`, if we can tell.
+    ///
+    /// A commit with an update path replaces its sender's leaf key. One without
+    /// (adding members only) must come from an admin, which settles it when there's
+    /// just one besides `me`; our own commits never get here.
+    pub fn committer(&self, after: &GroupSnapshot, me: &PublicKey) -> Option<PublicKey> {
+        let rotated: Vec<PublicKey> = self
+            .leaf_keys
+            .iter()
+            .filter(|(pk, key)| after.leaf_keys.get(*pk).is_some_and(|k| k != *key))
+            .map(|(pk, _)| *pk)
+            .collect();
+        if let [sender] = rotated.as_slice() {
+            return Some(*sender);
+        }
+        let mut admins = self.admins.iter().filter(|pk| *pk != me);
+        match (admins.next(), admins.next()) {
+            (Some(admin), None) => Some(*admin),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{anyhow, Result};
+use nostr_sdk::prelude::*;
+use nrc_mls::NostrMls;
+use nrc_mls_sqlite_storage::NostrMlsSqliteStorage;
+use openmls::group::GroupId;
+use openmls::prelude::tls_codec::Deserialize;
+use openmls::prelude::{
+    BasicCredential, ContentType, Credential, MlsGroup, MlsMessageIn, OpenMlsProvider,
+    ProcessedMessageContent, Proposal, ProtocolMessage, Sender,
+};
+
+/// Open a kind 445 event sent in the group's current epoch, without processing it.
+///
+/// Only in-memory state is touched, so nrc-mls can still process the event afterwards.
+pub fn open(
+    mls: &NostrMls<NostrMlsSqliteStorage>,
+    group_id: &GroupId,
+    event: &Event,
+) -> Result<(MlsGroup, ProtocolMessage)> {
+    let group = MlsGroup::load(mls.provider.storage(), group_id)
+        .map_err(|e| anyhow!("Failed to load MLS group: {e:?}"))?
+        .ok_or_else(|| anyhow!("No MLS group for this event"))?;
+    let secret = group.export_secret(mls.provider.crypto(), "nostr", b"nostr", 32)?;
+    let keys = Keys::new(SecretKey::from_slice(&secret)?);
+    let bytes = nip44::decrypt_to_bytes(keys.secret_key(), &keys.public_key, &event.content)?;
+    let message = MlsMessageIn::tls_deserialize_exact(bytes)?.try_into_protocol_message()?;
+    Ok((group, message))
+}
+
+/// Whether `event` is a commit that would move the group on from its current epoch.
+pub fn is_commit(mls: &NostrMls<NostrMlsSqliteStorage>, group_id: &GroupId, event: &Event) -> bool {
+    open(mls, group_id, event)
+        .is_ok_and(|(_, message)| message.content_type() == ContentType::Commit)
+}
+
+/// What a member's proposal asks for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProposedChange {
+    /// The proposer wants out of the group
+    Leave,
+    Remove(PublicKey),
+    Add(PublicKey),
+    /// Anything else (key updates, extensions, ...)
+    Other,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemberProposal {
+    pub proposer: PublicKey,
+    pub change: ProposedChange,
+}
+
+/// Read the proposal in `event`, if it is one from a current member.
+///
+/// nrc-mls drops proposals from members who aren't admins; this lets an admin act on them.
+pub fn read_proposal(
+    mls: &NostrMls<NostrMlsSqliteStorage>,
+    group_id: &GroupId,
+    event: &Event,
+) -> Result<Option<MemberProposal>> {
+    let (mut group, message) = open(mls, group_id, event)?;
+    if message.content_type() != ContentType::Proposal {
+        return Ok(None);
+    }
+    let processed = group.process_message(&mls.provider, message)?;
+    let ProcessedMessageContent::ProposalMessage(queued) = processed.into_content() else {
+        return Ok(None);
+    };
+    let Sender::Member(sender) = queued.sender() else {
+        return Ok(None);
+    };
+    let pubkey_at = |leaf| {
+        group
+            .member_at(leaf)
+            .and_then(|member| credential_pubkey(member.credential))
+    };
+    let Some(proposer) = pubkey_at(*sender) else {
+        return Ok(None);
+    };
+    let change = match queued.proposal() {
+        Proposal::SelfRemove => ProposedChange::Leave,
+        Proposal::Remove(remove) if remove.removed() == *sender => ProposedChange::Leave,
+        Proposal::Remove(remove) => match pubkey_at(remove.removed()) {
+            Some(pubkey) => ProposedChange::Remove(pubkey),
+            None => ProposedChange::Other,
+        },
+        Proposal::Add(add) => {
+            match credential_pubkey(add.key_package().leaf_node().credential().clone()) {
+                Some(pubkey) => ProposedChange::Add(pubkey),
+                None => ProposedChange::Other,
+            }
+        }
+        _ => ProposedChange::Other,
+    };
+    Ok(Some(MemberProposal { proposer, change }))
+}
+
+/// The Nostr key a member's MLS credential carries (hex in the identity).
+pub fn credential_pubkey(credential: Credential) -> Option<PublicKey> {
+    let credential = BasicCredential::try_from(credential).ok()?;
+    let identity = std::str::from_utf8(credential.identity()).ok()?;
+    PublicKey::from_hex(identity).ok()
+}
+
+/// Drop the commit nrc-mls stages for an admin's proposal when we can't commit it.
+///
+/// The proposal stays queued, since the admin's commit will refer to it.
+pub fn discard_pending_commit(
+    mls: &NostrMls<NostrMlsSqliteStorage>,
+    group_id: &GroupId,
+) -> Result<()> {
+    let Some(mut group) = MlsGroup::load(mls.provider.storage(), group_id)
+        .map_err(|e| anyhow!("Failed to load MLS group: {e:?}"))?
+    else {
+        return Ok(());
+    };
+    group
+        .clear_pending_commit(mls.provider.storage())
+        .map_err(|e| anyhow!("Failed to clear pending commit: {e:?}"))
+}
@@ -0,0 +1,245 @@
+use anyhow::Result;
+use chrono::Utc;
+use nostr_sdk::prelude::*;
+use openmls::group::GroupId;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+
+use crate::migrations;
+
+/// Local per-group bookkeeping that MLS storage doesn't track (lives in nrc.db)
+#[derive(Clone)]
+pub struct GroupMetaStore {
+    db_path: PathBuf,
+}
+
+impl GroupMetaStore {
+    pub fn new(datadir: &Path) -> Result<Self> {
+        let store = Self {
+            db_path: datadir.join("nrc.db"),
+        };
+        store.init()?;
+        Ok(store)
+    }
+
+    fn init(&self) -> Result<()> {
+        let mut conn = Connection::open(&self.db_path)?;
+        migrations::migrate(&mut conn, migrations::NRC_DB)?;
+        Ok(())
+    }
+
+    /// Mark a group as left; it stays in storage but is greyed out in the sidebar
+    pub fn archive(&self, group_id: &GroupId) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT INTO group_meta (mls_group_id, archived_at) VALUES (?1, ?2)
+             ON CONFLICT(mls_group_id) DO UPDATE SET archived_at = excluded.archived_at",
+            params![hex::encode(group_id.as_slice()), now],
+        )?;
+        Ok(())
+    }
+
+    /// Hide a group we created but never got going (e.g. a cancelled DM); it's also archived
+    pub fn discard(&self, group_id: &GroupId) -> Result<()> {
+        self.archive(group_id)?;
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO discarded_groups (mls_group_id, discarded_at) VALUES (?1, ?2)",
+            params![hex::encode(group_id.as_slice()), now],
+        )?;
+        Ok(())
+    }
+
+    pub fn is_discarded(&self, group_id: &GroupId) -> Result<bool> {
+        let conn = Connection::open(&self.db_path)?;
+        let found: Option<i64> = conn
+            .query_row(
+                "SELECT discarded_at FROM discarded_groups WHERE mls_group_id = ?1",
+                params![hex::encode(group_id.as_slice())],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(found.is_some())
+    }
+
+    pub fn is_archived(&self, group_id: &GroupId) -> Result<bool> {
+        let conn = Connection::open(&self.db_path)?;
+        let archived_at: Option<Option<i64>> = conn
+            .query_row(
+                "SELECT archived_at FROM group_meta WHERE mls_group_id = ?1",
+                params![hex::encode(group_id.as_slice())],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(archived_at.flatten().is_some())
+    }
+
+    /// Record a membership/name change line to show in the chat history
+    pub fn add_system_message(&self, group_id: &GroupId, content: &str) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT INTO group_system_messages (mls_group_id, content, created_at)
+             VALUES (?1, ?2, ?3)",
+            params![hex::encode(group_id.as_slice()), content, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn system_messages(&self, group_id: &GroupId) -> Result<Vec<SystemMessage>> {
+        let conn = Connection::open(&self.db_path)?;
+        let mut stmt = conn.prepare(
+            "SELECT content, created_at FROM group_system_messages
+             WHERE mls_group_id = ?1 ORDER BY created_at ASC, id ASC",
+        )?;
+        let rows = stmt.query_map(params![hex::encode(group_id.as_slice())], |row| {
+            Ok(SystemMessage {
+                content: row.get(0)?,
+                created_at: row.get(1)?,
+            })
+        })?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    }
+
+    /// Keep the commit for a member's proposal until an admin applies it with /commit
+    /// Keep a member's proposal until an admin commits it; false if we've seen it before
+    pub fn hold_proposal(&self, group_id: &GroupId, proposal: &HeldProposal) -> Result<bool> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO group_held_proposals
+                 (event_id, mls_group_id, proposer, removes, event, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                proposal.event.id.to_hex(),
+                hex::encode(group_id.as_slice()),
+                proposal.proposer.to_hex(),
+                proposal.removes.to_hex(),
+                proposal.event.as_json(),
+                now
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Proposals still waiting for /commit, oldest first
+    pub fn held_proposals(&self, group_id: &GroupId) -> Result<Vec<HeldProposal>> {
+        let conn = Connection::open(&self.db_path)?;
+        let mut stmt = conn.prepare(
+            "SELECT event, proposer, removes FROM group_held_proposals
+             WHERE mls_group_id = ?1 AND committed_at IS NULL
+             ORDER BY created_at ASC, rowid ASC",
+        )?;
+        let rows = stmt.query_map(params![hex::encode(group_id.as_slice())], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        let mut out = Vec::new();
+        for r in rows {
+            let (event, proposer, removes) = r?;
+            out.push(HeldProposal {
+                event: Event::from_json(event)?,
+                proposer: PublicKey::from_hex(&proposer)?,
+                removes: PublicKey::from_hex(&removes)?,
+            });
+        }
+        Ok(out)
+    }
+
+    /// Mark the group's held proposals as committed. The rows stay, so a
+    /// redelivered proposal isn't held again.
+    pub fn mark_proposals_committed(&self, group_id: &GroupId) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "UPDATE group_held_proposals SET committed_at = ?2
+             WHERE mls_group_id = ?1 AND committed_at IS NULL",
+            params![hex::encode(group_id.as_slice()), now],
+        )?;
+        Ok(())
+    }
+
+    /// Rotation bookkeeping for a group; starts tracking the first time it's asked about
+    pub fn key_rotation(&self, group_id: &GroupId) -> Result<KeyRotation> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        let id = hex::encode(group_id.as_slice());
+        conn.execute(
+            "INSERT OR IGNORE INTO group_key_rotation (mls_group_id, tracked_since)
+             VALUES (?1, ?2)",
+            params![id, now],
+        )?;
+        let rotation = conn.query_row(
+            "SELECT last_rotated_at, tracked_since, messages_since
+             FROM group_key_rotation WHERE mls_group_id = ?1",
+            params![id],
+            |row| {
+                Ok(KeyRotation {
+                    last_rotated_at: row.get(0)?,
+                    tracked_since: row.get(1)?,
+                    messages_since: row.get::<_, i64>(2)? as u64,
+                })
+            },
+        )?;
+        Ok(rotation)
+    }
+
+    /// Count a message sent or received in the group towards the next rotation
+    pub fn count_message(&self, group_id: &GroupId) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT INTO group_key_rotation (mls_group_id, tracked_since, messages_since)
+             VALUES (?1, ?2, 1)
+             ON CONFLICT(mls_group_id) DO UPDATE SET messages_since = messages_since + 1",
+            params![hex::encode(group_id.as_slice()), now],
+        )?;
+        Ok(())
+    }
+
+    /// Our self-update commit for the group was merged
+    pub fn record_key_rotation(&self, group_id: &GroupId) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT INTO group_key_rotation
+                (mls_group_id, last_rotated_at, tracked_since, messages_since)
+             VALUES (?1, ?2, ?2, 0)
+             ON CONFLICT(mls_group_id) DO UPDATE SET
+                last_rotated_at = excluded.last_rotated_at,
+                messages_since = 0",
+            params![hex::encode(group_id.as_slice()), now],
+        )?;
+        Ok(())
+    }
+}
+
+/// A member's proposal to remove someone, which only an admin can commit
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeldProposal {
+    pub event: Event,
+    pub proposer: PublicKey,
+    pub removes: PublicKey,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyRotation {
+    pub last_rotated_at: Option<i64>,
+    pub tracked_since: i64,
+    pub messages_since: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemMessage {
+    pub content: String,
+    pub created_at: i64,
+}
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use chrono::Utc;
+use nostr_sdk::prelude::*;
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::migrations;
+
+/// KeyPackage events (kind 443) we created, and so hold the private keys for (lives in nrc.db)
+#[derive(Clone)]
+pub struct KeyPackageStore {
+    db_path: PathBuf,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyPackageRecord {
+    pub event_id: EventId,
+    pub created_at: i64,
+    pub published_at: Option<i64>,
+}
+
+impl KeyPackageStore {
+    pub fn new(datadir: &Path) -> Result<Self> {
+        let store = Self {
+            db_path: datadir.join("nrc.db"),
+        };
+        store.init()?;
+        Ok(store)
+    }
+
+    fn init(&self) -> Result<()> {
+        let mut conn = Connection::open(&self.db_path)?;
+        migrations::migrate(&mut conn, migrations::NRC_DB)?;
+        Ok(())
+    }
+
+    pub fn add(&self, event: &Event) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO key_packages (event_id, event, created_at) VALUES (?1, ?2, ?3)",
+            params![event.id.to_hex(), event.as_json(), now],
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_published(&self, event_id: &EventId) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "UPDATE key_packages SET published_at = ?2 WHERE event_id = ?1",
+            params![event_id.to_hex(), now],
+        )?;
+        Ok(())
+    }
+
+    /// A welcome used this package; returns false if it isn't one of ours
+    pub fn mark_consumed(&self, event_id: &EventId) -> Result<bool> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        let n = conn.execute(
+            "UPDATE key_packages SET consumed_at = ?2
+             WHERE event_id = ?1 AND consumed_at IS NULL",
+            params![event_id.to_hex(), now],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// We asked relays to delete these (NIP-09); they no longer count as available
+    pub fn mark_deleted(&self, event_ids: &[EventId]) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        for id in event_ids {
+            conn.execute(
+                "UPDATE key_packages SET deleted_at = ?2 WHERE event_id = ?1",
+                params![id.to_hex(), now],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Packages still on offer: neither consumed by a welcome nor deleted
+    pub fn active(&self) -> Result<Vec<KeyPackageRecord>> {
+        let conn = Connection::open(&self.db_path)?;
+        let mut stmt = conn.prepare(
+            "SELECT event_id, created_at, published_at FROM key_packages
+             WHERE consumed_at IS NULL AND deleted_at IS NULL
+             ORDER BY created_at ASC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let id: String = row.get(0)?;
+            Ok((id, row.get::<_, i64>(1)?, row.get::<_, Option<i64>>(2)?))
+        })?;
+        let mut out = Vec::new();
+        for r in rows {
+            let (id, created_at, published_at) = r?;
+            out.push(KeyPackageRecord {
+                event_id: EventId::from_hex(&id)?,
+                created_at,
+                published_at,
+            });
+        }
+        Ok(out)
+    }
+
+    /// Active packages that relays acked at least once, for reconciliation
+    pub fn published_events(&self) -> Result<Vec<Event>> {
+        let conn = Connection::open(&self.db_path)?;
+        let mut stmt = conn.prepare(
+            "SELECT event FROM key_packages
+             WHERE published_at IS NOT NULL AND consumed_at IS NULL AND deleted_at IS NULL",
+        )?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(Event::from_json(r?)?);
+        }
+        Ok(out)
+    }
+}
+
+/// NIP-EE KeyPackage Relays List (kind 10051): where a user publishes their KeyPackages
+pub fn key_package_relays_kind() -> Kind {
+    Kind::from(10051)
+}
+
+pub fn key_package_relays_event(relays: &[RelayUrl]) -> EventBuilder {
+    let tags = relays
+        .iter()
+        .map(|url| Tag::custom(TagKind::Relay, [url.to_string()]));
+    EventBuilder::new(key_package_relays_kind(), "").tags(tags)
+}
+
+/// Relay URLs listed in a kind 10051 event; malformed entries are skipped
+pub fn key_package_relays(event: &Event) -> Vec<RelayUrl> {
+    event
+        .tags
+        .iter()
+        .filter(|t| t.kind() == TagKind::Relay)
+        .filter_map(|t| t.content())
+        .filter_map(|url| RelayUrl::parse(url).ok())
+        .collect()
+}
+
+/// How many KeyPackages we keep on relays and how long each may stay there
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyPackagePolicy {
+    pub target_count: usize,
+    pub rotate_after: Duration,
+}
+
+impl Default for KeyPackagePolicy {
+    fn default() -> Self {
+        Self {
+            target_count: 3,
+            rotate_after: Duration::from_secs(30 * 24 * 60 * 60),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyPackagePlan {
+    // Packages past their lifetime to delete from relays
+    pub retire: Vec<EventId>,
+    // Fresh packages to publish so we're back at the target count
+    pub publish: usize,
+}
+
+impl KeyPackagePolicy {
+    pub fn plan(&self, active: &[KeyPackageRecord], now: i64) -> KeyPackagePlan {
+        let max_age = self.rotate_after.as_secs() as i64;
+        let retire: Vec<EventId> = active
+            .iter()
+            .filter(|p| now.saturating_sub(p.created_at) >= max_age)
+            .map(|p| p.event_id)
+            .collect();
+        let remaining = active.len() - retire.len();
+        KeyPackagePlan {
+            retire,
+            publish: self.target_count.saturating_sub(remaining),
+        }
+    }
+}
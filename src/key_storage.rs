@@ -1,17 +1,52 @@
 use anyhow::{anyhow, Result};
+use nostr_sdk::nips::nip44;
 use nostr_sdk::prelude::*;
-use rusqlite::{params, Connection};
-use std::path::Path;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::path::{Path, PathBuf};
 
-/// Manages encrypted key storage using NIP-49 in SQLite
+use crate::migrations;
+use crate::signer::AccountSigner;
+
+/// NIP-49 scrypt cost (2^log_n). Each step up doubles the time and memory to unlock.
+pub const DEFAULT_LOG_N: u8 = 16;
+pub const MIN_LOG_N: u8 = 16;
+// 2^22 needs 4 GiB of memory to unlock
+pub const MAX_LOG_N: u8 = 22;
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+/// A stored account and the directory holding its MLS storage and ops queue
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountInfo {
+    pub npub: String,
+    pub datadir: PathBuf,
+}
+
+/// Manages encrypted key storage using NIP-49 in SQLite.
+///
+/// The `keys` table in `{datadir}/nrc.db` is the account registry. The first account
+/// keeps its data in the datadir itself; each one added later gets `accounts/<npub>/`.
+/// Bunker accounts store our NIP-46 client key in place of the nsec, and their `bunker://`
+/// URI (which may carry a connect secret) NIP-44 encrypted to that client key.
 pub struct KeyStorage {
     db_path: std::path::PathBuf,
+    // Account this session is for; None means the first one
+    account: Option<String>,
 }
 
 impl KeyStorage {
     pub fn new(datadir: &Path) -> Self {
         Self {
             db_path: datadir.join("nrc.db"),
+            account: None,
+        }
+    }
+
+    /// The same registry, for a session on another account
+    pub fn for_account(&self, npub: &str) -> Self {
+        Self {
+            db_path: self.db_path.clone(),
+            account: Some(npub.to_string()),
         }
     }
 
@@ -19,16 +54,69 @@ impl KeyStorage {
         self.db_path.parent().unwrap_or_else(|| Path::new("."))
     }
 
-    /// Initialize the keys table if it doesn't exist
-    fn init_table(&self, conn: &Connection) -> Result<()> {
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS keys (
-                id INTEGER PRIMARY KEY,
-                npub TEXT NOT NULL UNIQUE,
-                encrypted_nsec TEXT NOT NULL
-            )",
-            [],
-        )?;
+    /// The account this session unlocks: the chosen one, else the first stored
+    pub fn account(&self) -> Option<String> {
+        self.account.clone().or_else(|| self.get_first_npub().ok())
+    }
+
+    /// Where this session's account keeps its data
+    pub fn account_datadir(&self) -> Result<PathBuf> {
+        match self.account() {
+            Some(npub) => self.account_dir(&npub),
+            None => Ok(self.datadir().to_path_buf()),
+        }
+    }
+
+    /// Where an account's data lives, or will live once it is saved
+    pub fn account_dir(&self, npub: &str) -> Result<PathBuf> {
+        let mut conn = Connection::open(&self.db_path)?;
+        self.init_table(&mut conn)?;
+        let stored: Option<Option<String>> = conn
+            .query_row(
+                "SELECT data_dir FROM keys WHERE npub = ?1",
+                params![npub],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let relative = match stored {
+            Some(relative) => relative,
+            None => Self::new_account_dir(&conn, npub)?,
+        };
+        Ok(match relative {
+            Some(relative) => self.datadir().join(relative),
+            None => self.datadir().to_path_buf(),
+        })
+    }
+
+    // The first account uses the datadir itself (NULL), later ones a subdirectory
+    fn new_account_dir(conn: &Connection, npub: &str) -> Result<Option<String>> {
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM keys", [], |row| row.get(0))?;
+        Ok((count > 0).then(|| format!("accounts/{npub}")))
+    }
+
+    /// All stored accounts, oldest first
+    pub fn list_accounts(&self) -> Result<Vec<AccountInfo>> {
+        let mut conn = Connection::open(&self.db_path)?;
+        self.init_table(&mut conn)?;
+        let mut stmt = conn.prepare("SELECT npub, data_dir FROM keys ORDER BY id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })?;
+        let mut accounts = Vec::new();
+        for row in rows {
+            let (npub, relative) = row?;
+            let datadir = match relative {
+                Some(relative) => self.datadir().join(relative),
+                None => self.datadir().to_path_buf(),
+            };
+            accounts.push(AccountInfo { npub, datadir });
+        }
+        Ok(accounts)
+    }
+
+    /// Bring nrc.db's app tables (including keys) up to the current schema
+    fn init_table(&self, conn: &mut Connection) -> Result<()> {
+        migrations::migrate(conn, migrations::NRC_DB)?;
         Ok(())
     }
 
@@ -38,8 +126,8 @@ impl KeyStorage {
     }
 
     fn keys_exist_internal(&self) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
-        self.init_table(&conn)?;
+        let mut conn = Connection::open(&self.db_path)?;
+        self.init_table(&mut conn)?;
         let count: i64 = conn.query_row("SELECT COUNT(*) FROM keys", [], |row| row.get(0))?;
         Ok(count > 0)
     }
@@ -56,37 +144,129 @@ impl KeyStorage {
 
     /// Save keys encrypted with password using NIP-49
     pub fn save_encrypted(&self, keys: &Keys, password: &str) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
-        self.init_table(&conn)?;
+        self.save(&keys.public_key(), keys, None, password)
+    }
+
+    /// Save a bunker account: its URI, and our NIP-46 client key encrypted with password
+    pub fn save_bunker(
+        &self,
+        public_key: &PublicKey,
+        app_keys: &Keys,
+        uri: &NostrConnectURI,
+        password: &str,
+    ) -> Result<()> {
+        self.save(public_key, app_keys, Some(uri), password)
+    }
+
+    /// Save whichever kind of account `signer` is for
+    pub fn save_account(&self, signer: &AccountSigner, password: &str) -> Result<()> {
+        match signer {
+            AccountSigner::Local(keys) => self.save_encrypted(keys, password),
+            AccountSigner::Bunker {
+                public_key,
+                app_keys,
+                uri,
+                ..
+            } => self.save_bunker(public_key, app_keys, uri, password),
+        }
+    }
 
-        // Get the npub from the keys
-        let npub = keys.public_key().to_bech32()?;
+    fn save(
+        &self,
+        public_key: &PublicKey,
+        keys: &Keys,
+        bunker_uri: Option<&NostrConnectURI>,
+        password: &str,
+    ) -> Result<()> {
+        let mut conn = Connection::open(&self.db_path)?;
+        self.init_table(&mut conn)?;
+
+        let npub = public_key.to_bech32()?;
 
         // Use the encrypt method to create an EncryptedSecretKey
         let encrypted = keys.secret_key().encrypt(password)?;
         // Convert to bech32 string for storage
         let encrypted_str = encrypted.to_bech32()?;
+        // Only the client key (so only the password) opens the URI
+        let bunker_uri = bunker_uri
+            .map(|uri| {
+                nip44::encrypt(
+                    keys.secret_key(),
+                    &keys.public_key(),
+                    uri.to_string(),
+                    nip44::Version::V2,
+                )
+            })
+            .transpose()?;
 
-        // Insert or replace the account with the npub
+        // New accounts get their data directory; existing ones keep theirs
+        let data_dir = Self::new_account_dir(&conn, &npub)?;
         conn.execute(
-            "INSERT OR REPLACE INTO keys (npub, encrypted_nsec) VALUES (?1, ?2)",
-            params![npub, encrypted_str],
+            "INSERT INTO keys (npub, encrypted_nsec, data_dir, bunker_uri) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(npub) DO UPDATE SET
+                encrypted_nsec = excluded.encrypted_nsec, bunker_uri = excluded.bunker_uri",
+            params![npub, encrypted_str, data_dir, bunker_uri],
         )?;
 
         log::info!("Keys saved to database for npub: {npub}");
         Ok(())
     }
 
-    /// Load and decrypt keys with password
+    /// Whether a bunker holds this session's account key
+    pub fn is_bunker(&self) -> Result<bool> {
+        Ok(self.stored_bunker_uri()?.is_some())
+    }
+
+    fn stored_bunker_uri(&self) -> Result<Option<String>> {
+        let npub = self.account().ok_or_else(|| anyhow!("No stored keys"))?;
+        let mut conn = Connection::open(&self.db_path)?;
+        self.init_table(&mut conn)?;
+        Ok(conn
+            .query_row(
+                "SELECT bunker_uri FROM keys WHERE npub = ?1",
+                params![npub],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten())
+    }
+
+    /// Unlock this session's account: its keys, or a connection to its bunker
+    pub fn load_signer(&self, password: &str) -> Result<AccountSigner> {
+        let keys = self.load_encrypted(password)?;
+        let Some(stored) = self.stored_bunker_uri()? else {
+            return Ok(AccountSigner::Local(keys));
+        };
+        // Saved before the URI was encrypted
+        let uri = if stored.starts_with("bunker://") {
+            stored
+        } else {
+            nip44::decrypt(keys.secret_key(), &keys.public_key(), &stored)?
+        };
+        let uri = NostrConnectURI::parse(&uri).map_err(|e| anyhow!("Invalid bunker URI: {e}"))?;
+        let npub = self.account().ok_or_else(|| anyhow!("No stored keys"))?;
+        AccountSigner::bunker(PublicKey::parse(&npub)?, uri, keys)
+    }
+
+    /// Load and decrypt this session's account (the first one if none was chosen)
     pub fn load_encrypted(&self, password: &str) -> Result<Keys> {
         let conn = Connection::open(&self.db_path)?;
 
-        // Get the first account (for now, until we have account selection)
-        let encrypted_str: String = conn.query_row(
-            "SELECT encrypted_nsec FROM keys ORDER BY id LIMIT 1",
-            [],
-            |row| row.get(0),
-        )?;
+        let encrypted_str: String = match &self.account {
+            Some(npub) => conn
+                .query_row(
+                    "SELECT encrypted_nsec FROM keys WHERE npub = ?1",
+                    params![npub],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .ok_or_else(|| anyhow!("No stored keys for {npub}"))?,
+            None => conn.query_row(
+                "SELECT encrypted_nsec FROM keys ORDER BY id LIMIT 1",
+                [],
+                |row| row.get(0),
+            )?,
+        };
 
         // Parse the encrypted key from bech32
         let encrypted = EncryptedSecretKey::from_bech32(&encrypted_str)?;
@@ -97,6 +277,70 @@ impl KeyStorage {
         Ok(Keys::new(secret_key))
     }
 
+    /// The stored NIP-49 `ncryptsec` of this session's account, for backups
+    pub fn ncryptsec(&self) -> Result<String> {
+        let npub = self.account().ok_or_else(|| anyhow!("No stored keys"))?;
+        let conn = Connection::open(&self.db_path)?;
+        conn.query_row(
+            "SELECT encrypted_nsec FROM keys WHERE npub = ?1",
+            params![npub],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| anyhow!("No stored keys for {npub}"))
+    }
+
+    /// Re-encrypt this session's account under a new password in one transaction.
+    /// The old ciphertext stays in place unless the new one decrypts back to the same key.
+    pub fn change_password(
+        &self,
+        old_password: &str,
+        new_password: &str,
+        log_n: u8,
+    ) -> Result<Keys> {
+        if new_password.len() < MIN_PASSWORD_LEN {
+            return Err(anyhow!(
+                "Password must be at least {MIN_PASSWORD_LEN} characters"
+            ));
+        }
+        if !(MIN_LOG_N..=MAX_LOG_N).contains(&log_n) {
+            return Err(anyhow!("log_n must be between {MIN_LOG_N} and {MAX_LOG_N}"));
+        }
+        let npub = self.account().ok_or_else(|| anyhow!("No stored keys"))?;
+
+        let mut conn = Connection::open(&self.db_path)?;
+        self.init_table(&mut conn)?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let encrypted_str: String = tx
+            .query_row(
+                "SELECT encrypted_nsec FROM keys WHERE npub = ?1",
+                params![npub],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow!("No stored keys for {npub}"))?;
+
+        let secret_key = EncryptedSecretKey::from_bech32(&encrypted_str)?
+            .decrypt(old_password)
+            .map_err(|_| anyhow!("Wrong password"))?;
+        let encrypted =
+            EncryptedSecretKey::new(&secret_key, new_password, log_n, KeySecurity::Unknown)?;
+        if encrypted.decrypt(new_password).ok().as_ref() != Some(&secret_key) {
+            return Err(anyhow!(
+                "Re-encrypted key failed to verify; password unchanged"
+            ));
+        }
+
+        tx.execute(
+            "UPDATE keys SET encrypted_nsec = ?2 WHERE npub = ?1",
+            params![npub, encrypted.to_bech32()?],
+        )?;
+        tx.commit()?;
+
+        log::info!("Password changed for npub: {npub} (log_n {log_n})");
+        Ok(Keys::new(secret_key))
+    }
+
     /// Delete stored keys for a specific npub
     pub fn delete_by_npub(&self, npub: &str) -> Result<()> {
         let conn = Connection::open(&self.db_path)?;
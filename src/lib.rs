@@ -1,11 +1,24 @@
 // Module declarations
 pub mod app;
+pub mod commit_race;
 pub mod config;
+pub mod contacts;
+pub mod delivery;
 pub mod events;
+pub mod group_changes;
+pub mod group_messages;
+pub mod group_meta;
+pub mod key_packages;
 pub mod key_storage;
+pub mod migrations;
+pub mod nip05;
 pub mod notification_handler;
 pub mod ops;
 pub mod profiles;
+pub mod relay_auth;
+pub mod relay_status;
+pub mod replies;
+pub mod signer;
 pub mod ui_state;
 pub mod utils;
 
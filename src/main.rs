@@ -1,14 +1,19 @@
 mod keyboard;
 mod render;
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
 use crossterm::{
     event::{DisableBracketedPaste, EnableBracketedPaste, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use nostr_sdk::prelude::*;
 use nrc::{
+    app::AccountSwitch,
+    config::Config,
+    key_storage::KeyStorage,
+    signer::AccountSigner,
     ui_state::{OnboardingMode, Page},
     App, AppEvent,
 };
@@ -17,6 +22,7 @@ use std::fs::{self, OpenOptions};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::sync::mpsc;
 use tokio::time::{timeout, Duration};
 
 fn default_data_dir() -> PathBuf {
@@ -50,6 +56,9 @@ struct Args {
     /// Watch operations dashboard mode
     #[arg(long, default_value_t = false)]
     watch_ops: bool,
+    /// Relay to use instead of those in config.toml (repeatable)
+    #[arg(long = "relay", value_name = "URL")]
+    relays: Vec<String>,
     /// Skip onboarding and use default credentials (debug builds only)
     #[cfg(debug_assertions)]
     #[arg(long)]
@@ -58,6 +67,40 @@ struct Args {
     #[cfg(debug_assertions)]
     #[arg(long)]
     wipe: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage stored accounts without starting the chat UI
+    Account {
+        #[command(subcommand)]
+        action: AccountCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AccountCommand {
+    /// Re-encrypt an account's keys under a new password
+    ChangePassword {
+        /// Account to change (defaults to the first one)
+        #[arg(long)]
+        npub: Option<String>,
+        /// NIP-49 scrypt cost for the new encryption (defaults to keys.log_n in config.toml)
+        #[arg(long)]
+        log_n: Option<u8>,
+    },
+    /// Print an account's key to stdout, for backups
+    Export {
+        /// Account to export (defaults to the first one)
+        #[arg(long)]
+        npub: Option<String>,
+        /// Print the password-encrypted NIP-49 key (the default, and the only format:
+        /// the raw nsec is only shown in the app)
+        #[arg(long)]
+        ncryptsec: bool,
+    },
 }
 
 fn setup_logging(datadir: &PathBuf) -> Result<()> {
@@ -102,6 +145,11 @@ fn setup_logging(datadir: &PathBuf) -> Result<()> {
 async fn main() -> Result<()> {
     let args = Args::parse();
 
+    // Before logging is set up: its banner would end up in scripted stdout
+    if let Some(Command::Account { action }) = args.command {
+        return run_account_command(&args.datadir, action);
+    }
+
     setup_logging(&args.datadir)?;
     log::info!("Starting NRC with datadir: {:?}", args.datadir);
 
@@ -124,11 +172,12 @@ async fn main() -> Result<()> {
         &mut terminal,
         &args.datadir,
         args.watch_ops,
+        args.relays,
         args.skip_onboarding,
     )
     .await;
     #[cfg(not(debug_assertions))]
-    let res = run_app(&mut terminal, &args.datadir, args.watch_ops).await;
+    let res = run_app(&mut terminal, &args.datadir, args.watch_ops, args.relays).await;
 
     disable_raw_mode()?;
     execute!(
@@ -145,18 +194,64 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+fn run_account_command(datadir: &Path, action: AccountCommand) -> Result<()> {
+    match action {
+        AccountCommand::ChangePassword { npub, log_n } => {
+            let key_storage = account_storage(datadir, npub)?;
+            let account = key_storage
+                .account()
+                .ok_or_else(|| anyhow!("No accounts in {}", datadir.display()))?;
+            let log_n = match log_n {
+                Some(log_n) => log_n,
+                None => Config::load(datadir)?.keys.log_n,
+            };
+
+            println!("Changing password for {account}");
+            let old_password = rpassword::prompt_password("Current password: ")?;
+            let new_password = rpassword::prompt_password("New password: ")?;
+            let confirm = rpassword::prompt_password("Confirm new password: ")?;
+            if new_password != confirm {
+                return Err(anyhow!("Passwords don't match; nothing changed"));
+            }
+            key_storage.change_password(&old_password, &new_password, log_n)?;
+            println!("Password changed (log_n {log_n})");
+            Ok(())
+        }
+        // ncryptsec is the only format, so the flag just spells out the default
+        AccountCommand::Export { npub, ncryptsec: _ } => {
+            let key_storage = account_storage(datadir, npub)?;
+            if key_storage.is_bunker()? {
+                return Err(anyhow!(
+                    "This account's key is held by its bunker; back it up there"
+                ));
+            }
+            println!("{}", key_storage.ncryptsec()?);
+            Ok(())
+        }
+    }
+}
+
+/// The registry, pointed at `--npub` if given
+fn account_storage(datadir: &Path, npub: Option<String>) -> Result<KeyStorage> {
+    let registry = KeyStorage::new(datadir);
+    if !registry.keys_exist() {
+        return Err(anyhow!("No accounts in {}", datadir.display()));
+    }
+    Ok(match npub {
+        Some(npub) => registry.for_account(&npub),
+        None => registry,
+    })
+}
+
 async fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     datadir: &Path,
     watch_ops: bool,
+    relay_overrides: Vec<String>,
     #[cfg(debug_assertions)] skip_onboarding: bool,
 ) -> Result<()> {
-    use nostr_sdk::prelude::*;
-    use nrc::config::get_default_relays;
-    use nrc_mls::NostrMls;
-    use nrc_mls_sqlite_storage::NostrMlsSqliteStorage;
-
-    let key_storage = nrc::key_storage::KeyStorage::new(datadir);
+    let key_storage = KeyStorage::new(datadir);
+    let config = Config::load(datadir)?.with_relay_overrides(relay_overrides)?;
 
     let (keys, initial_page) = if watch_ops {
         let keys = Keys::generate();
@@ -165,6 +260,7 @@ async fn run_app<B: ratatui::backend::Backend>(
             Page::OpsDashboard {
                 items: vec![],
                 selected: 0,
+                show_detail: false,
             },
         )
     } else {
@@ -215,14 +311,7 @@ async fn run_app<B: ratatui::backend::Backend>(
             (keys, page)
         } else if key_storage.keys_exist() {
             let keys = Keys::generate();
-            (
-                keys,
-                Page::Onboarding {
-                    input: String::new(),
-                    mode: OnboardingMode::EnterPassword,
-                    error: None,
-                },
-            )
+            (keys, unlock_page(&key_storage))
         } else {
             let keys = Keys::generate();
             (
@@ -237,14 +326,7 @@ async fn run_app<B: ratatui::backend::Backend>(
         #[cfg(not(debug_assertions))]
         if key_storage.keys_exist() {
             let keys = Keys::generate();
-            (
-                keys,
-                Page::Onboarding {
-                    input: String::new(),
-                    mode: OnboardingMode::EnterPassword,
-                    error: None,
-                },
-            )
+            (keys, unlock_page(&key_storage))
         } else {
             let keys = Keys::generate();
             (
@@ -258,39 +340,137 @@ async fn run_app<B: ratatui::backend::Backend>(
         }
     };
 
-    let client = Client::builder().signer(keys.clone()).build();
+    // Key presses outlive sessions, so they get their own channel
+    let (input_tx, mut input_rx) = mpsc::unbounded_channel();
+    keyboard::spawn_keyboard_listener(input_tx);
+
+    // Switching accounts ends the session and starts one on the other account's storage
+    let mut session = (key_storage, AccountSigner::from(keys), initial_page);
+    loop {
+        let (key_storage, signer, initial_page) = session;
+        let app = start_session(key_storage, signer, initial_page, config.clone()).await?;
+        let client = app.client.clone();
+        let switch = run_session(terminal, app, watch_ops, &mut input_rx).await;
+        // Ends the notification handler; the orchestrator and tickers stop with the app
+        client.shutdown().await;
+
+        let registry = KeyStorage::new(datadir);
+        session = match switch? {
+            None => return Ok(()),
+            Some(AccountSwitch::Unlock(npub)) => (
+                registry.for_account(&npub),
+                Keys::generate().into(),
+                onboarding_page(OnboardingMode::EnterPassword),
+            ),
+            Some(AccountSwitch::Create) => {
+                let keys = Keys::generate();
+                let npub = keys.public_key().to_bech32()?;
+                (
+                    registry.for_account(&npub),
+                    keys.into(),
+                    onboarding_page(OnboardingMode::Choose),
+                )
+            }
+            Some(AccountSwitch::Import(keys)) => {
+                let npub = keys.public_key().to_bech32()?;
+                (
+                    registry.for_account(&npub),
+                    keys.into(),
+                    onboarding_page(OnboardingMode::CreatePassword),
+                )
+            }
+            Some(AccountSwitch::Bunker(signer)) => {
+                let npub = signer.public_key().to_bech32()?;
+                (
+                    registry.for_account(&npub),
+                    *signer,
+                    onboarding_page(OnboardingMode::CreatePassword),
+                )
+            }
+        };
+        log::info!("Switching account session");
+    }
+}
+
+fn onboarding_page(mode: OnboardingMode) -> Page {
+    Page::Onboarding {
+        input: String::new(),
+        mode,
+        error: None,
+    }
+}
+
+/// Password entry, preceded by an account picker when there are several
+fn unlock_page(key_storage: &KeyStorage) -> Page {
+    let accounts = key_storage.list_accounts().unwrap_or_default();
+    if accounts.len() > 1 {
+        onboarding_page(OnboardingMode::SelectAccount)
+    } else {
+        onboarding_page(OnboardingMode::EnterPassword)
+    }
+}
+
+/// Client, MLS storage and app for one account
+async fn start_session(
+    key_storage: KeyStorage,
+    signer: AccountSigner,
+    initial_page: Page,
+    config: Config,
+) -> Result<App> {
+    use nrc_mls::NostrMls;
+    use nrc_mls_sqlite_storage::NostrMlsSqliteStorage;
+
+    // NIP-42 AUTH is answered by the app, with the unlocked keys, for opted-in relays only
+    let client = Client::builder()
+        .opts(ClientOptions::new().automatic_authentication(false))
+        .build();
+    signer.install(&client).await;
 
     // Add relays and connect in background for faster startup
     let client_clone = client.clone();
+    let relays = config.relays.clone();
     tokio::spawn(async move {
-        for &relay in get_default_relays() {
-            if let Err(e) = client_clone.add_relay(relay).await {
+        for relay in relays {
+            if let Err(e) = client_clone.add_relay(relay.as_str()).await {
                 log::warn!("Failed to add relay {relay}: {e}");
             }
         }
         client_clone.connect().await;
     });
 
-    let db_path = datadir.join("nrc.db");
+    // Each account has its own MLS storage
+    let account_dir = key_storage.account_datadir()?;
+    fs::create_dir_all(&account_dir)?;
+    let db_path = account_dir.join("nrc.db");
     #[allow(clippy::arc_with_non_send_sync)]
     let storage = Arc::new(NostrMls::new(NostrMlsSqliteStorage::new(db_path)?));
 
-    let mut app = App::new(
-        storage.clone(),
-        client.clone(),
-        keys,
-        key_storage,
-        initial_page,
-    )
-    .await?;
+    App::with_config(storage, client, signer, key_storage, initial_page, config).await
+}
 
+/// Runs one account's session; returns the account to switch to, or None on quit
+async fn run_session<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    mut app: App,
+    watch_ops: bool,
+    input_rx: &mut mpsc::UnboundedReceiver<AppEvent>,
+) -> Result<Option<AccountSwitch>> {
+    let result = session_loop(terminal, &mut app, watch_ops, input_rx).await;
+    // Quitting or switching accounts must not leave an exported key on the clipboard
+    app.wipe_clipboard_secret();
+    result
+}
+
+async fn session_loop<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    watch_ops: bool,
+    input_rx: &mut mpsc::UnboundedReceiver<AppEvent>,
+) -> Result<Option<AccountSwitch>> {
     let mut state_rx = app.get_state_receiver();
     let event_rx = app.event_rx.take().unwrap();
 
-    let event_tx = app.event_tx.clone();
-    keyboard::spawn_keyboard_listener(event_tx.clone());
-
-    let ops_event_tx = event_tx.clone();
+    let ops_event_tx = app.event_tx.clone();
     tokio::spawn(async move {
         use tokio::time::{interval, Duration};
         let mut pending_ops_interval = if watch_ops {
@@ -301,7 +481,13 @@ async fn run_app<B: ratatui::backend::Backend>(
 
         loop {
             pending_ops_interval.tick().await;
-            let _ = ops_event_tx.send(AppEvent::ProcessPendingOperationsTick);
+            // The session ended
+            if ops_event_tx
+                .send(AppEvent::ProcessPendingOperationsTick)
+                .is_err()
+            {
+                break;
+            }
             if watch_ops {
                 let _ = ops_event_tx.send(AppEvent::RefreshCurrentPage);
             }
@@ -325,37 +511,53 @@ async fn run_app<B: ratatui::backend::Backend>(
                 last_rendered_state = Some(state);
             }
             changed
-        } else if force_render {
-            force_render = false;
-            true
         } else {
-            false
+            std::mem::take(&mut force_render)
         };
 
         if should_render {
-            terminal.draw(|f| render::render(f, &app))?;
+            terminal.draw(|f| render::render(f, app))?;
         }
 
-        match timeout(Duration::from_millis(16), event_rx.recv()).await {
+        let next = async {
+            tokio::select! {
+                event = event_rx.recv() => event,
+                input = input_rx.recv() => input,
+            }
+        };
+        match timeout(Duration::from_millis(16), next).await {
             Ok(Some(event)) => match event {
                 AppEvent::KeyPress(key) => {
                     if key.modifiers.contains(KeyModifiers::CONTROL)
                         && key.code == KeyCode::Char('c')
                     {
-                        return Ok(());
+                        return Ok(None);
                     }
                     // Check if we have a flash message before handling the key
                     let had_flash = app.flash.is_some();
+                    let modal_before = app.modal.clone();
                     app.handle_event(event).await?;
                     // If we had a flash and it's now gone, force immediate re-render
                     if had_flash && app.flash.is_none() {
                         force_render = true;
                     }
+                    // Modals don't change the page, so redraw when one opens or closes
+                    if app.modal != modal_before {
+                        force_render = true;
+                    }
                 }
                 AppEvent::Resize => {
                     force_render = true;
                     app.handle_event(event).await?;
                 }
+                // The relay status segment lives outside the page state
+                // So are NIP-05 verified markers
+                AppEvent::RelayStatusUpdated { .. }
+                | AppEvent::ConnectionStatusChanged(_)
+                | AppEvent::Nip05Checked { .. } => {
+                    force_render = true;
+                    app.handle_event(event).await?;
+                }
                 _ => {
                     app.handle_event(event).await?;
                 }
@@ -371,7 +573,16 @@ async fn run_app<B: ratatui::backend::Backend>(
         {
             app.send_event(AppEvent::ClearFlash)?;
         }
+
+        // The ops watcher has no stored keys to unlock with
+        if !watch_ops && app.idle_lock_due() {
+            app.send_event(AppEvent::Lock)?;
+        }
+
+        if let Some(switch) = app.account_switch.take() {
+            return Ok(Some(switch));
+        }
     }
 
-    Ok(())
+    Ok(None)
 }
@@ -0,0 +1,308 @@
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+
+/// One step of a database's schema history.
+///
+/// Steps must tolerate databases created before versioning existed, which
+/// already have some of the tables or columns they add.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub up: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+/// App tables in nrc.db; the MLS storage tables there manage their own schema
+pub const NRC_DB: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "accounts, group bookkeeping and key packages",
+        up: nrc_db_v1,
+    },
+    Migration {
+        version: 2,
+        description: "message delivery status",
+        up: nrc_db_v2,
+    },
+    Migration {
+        version: 3,
+        description: "profile cache",
+        up: nrc_db_v3,
+    },
+    Migration {
+        version: 4,
+        description: "NIP-05 verification results",
+        up: nrc_db_v4,
+    },
+    Migration {
+        version: 5,
+        description: "contacts and petnames",
+        up: nrc_db_v5,
+    },
+    Migration {
+        version: 6,
+        description: "per-account data directories",
+        up: nrc_db_v6,
+    },
+    Migration {
+        version: 7,
+        description: "NIP-46 bunker accounts",
+        up: nrc_db_v7,
+    },
+    Migration {
+        version: 8,
+        description: "held member proposals",
+        up: nrc_db_v8,
+    },
+];
+
+/// nrc_ops.db
+pub const OPS_DB: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "operations",
+        up: ops_db_v1,
+    },
+    Migration {
+        version: 2,
+        description: "retry attempts and backoff",
+        up: ops_db_v2,
+    },
+];
+
+/// Version the newest migration in `migrations` brings a database to
+pub fn latest_version(migrations: &[Migration]) -> u32 {
+    migrations.iter().map(|m| m.version).max().unwrap_or(0)
+}
+
+/// Schema version recorded in the database; 0 if it was never migrated
+pub fn current_version(conn: &Connection) -> Result<u32> {
+    let exists: Option<String> = conn
+        .query_row(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if exists.is_none() {
+        return Ok(0);
+    }
+    let version: Option<u32> = conn
+        .query_row("SELECT MAX(version) FROM schema_version", [], |row| {
+            row.get(0)
+        })
+        .optional()?
+        .flatten();
+    Ok(version.unwrap_or(0))
+}
+
+/// Bring the database up to the newest version in `migrations`.
+///
+/// Pending migrations run in a single transaction, so a failure leaves the database
+/// as it was. A database written by a newer nrc is refused rather than guessed at.
+/// Returns the resulting version.
+pub fn migrate(conn: &mut Connection, migrations: &[Migration]) -> Result<u32> {
+    let latest = latest_version(migrations);
+    let current = current_version(conn)?;
+    if current > latest {
+        bail!(
+            "Database schema version {current} is newer than this version of nrc supports ({latest}); please upgrade nrc"
+        );
+    }
+    if current == latest {
+        return Ok(current);
+    }
+
+    // IMMEDIATE so two processes opening the same datadir don't both migrate
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    // Re-read under the write lock in case someone else just migrated
+    let current = current_version(&tx)?;
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    let mut pending: Vec<&Migration> = migrations.iter().filter(|m| m.version > current).collect();
+    pending.sort_by_key(|m| m.version);
+    for m in pending {
+        log::info!(
+            "Migrating database to version {}: {}",
+            m.version,
+            m.description
+        );
+        (m.up)(&tx)?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+            params![m.version, m.description, chrono::Utc::now().timestamp()],
+        )?;
+    }
+    tx.commit()?;
+    Ok(latest)
+}
+
+/// Whether `table` already has `column` (for databases that predate versioning)
+pub fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+    let columns = stmt
+        .query_map([table], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(columns.iter().any(|c| c == column))
+}
+
+fn nrc_db_v1(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS keys (
+            id INTEGER PRIMARY KEY,
+            npub TEXT NOT NULL UNIQUE,
+            encrypted_nsec TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS group_meta (
+            mls_group_id TEXT PRIMARY KEY,
+            archived_at INTEGER
+        );
+        CREATE TABLE IF NOT EXISTS discarded_groups (
+            mls_group_id TEXT PRIMARY KEY,
+            discarded_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS group_system_messages (
+            id INTEGER PRIMARY KEY,
+            mls_group_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS group_key_rotation (
+            mls_group_id TEXT PRIMARY KEY,
+            last_rotated_at INTEGER,
+            tracked_since INTEGER NOT NULL,
+            messages_since INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE IF NOT EXISTS key_packages (
+            event_id TEXT PRIMARY KEY,
+            event TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            published_at INTEGER,
+            consumed_at INTEGER,
+            deleted_at INTEGER
+        );",
+    )
+}
+
+fn nrc_db_v2(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS message_delivery (
+            event_id TEXT PRIMARY KEY,
+            mls_group_id TEXT NOT NULL,
+            op_id TEXT NOT NULL,
+            status TEXT NOT NULL,
+            acked_relays TEXT NOT NULL DEFAULT '[]',
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn nrc_db_v3(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS profiles (
+            pubkey TEXT PRIMARY KEY,
+            metadata TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            fetched_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn nrc_db_v4(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS nip05_checks (
+            pubkey TEXT PRIMARY KEY,
+            address TEXT NOT NULL,
+            verified INTEGER NOT NULL,
+            checked_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn nrc_db_v5(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS contacts (
+            pubkey TEXT PRIMARY KEY,
+            petname TEXT,
+            followed INTEGER NOT NULL DEFAULT 0,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn nrc_db_v6(conn: &Connection) -> rusqlite::Result<()> {
+    // NULL: the account predates multi-account support and lives in the datadir itself
+    if !has_column(conn, "keys", "data_dir")? {
+        conn.execute("ALTER TABLE keys ADD COLUMN data_dir TEXT", [])?;
+    }
+    Ok(())
+}
+
+fn nrc_db_v7(conn: &Connection) -> rusqlite::Result<()> {
+    // Set for bunker accounts, whose encrypted_nsec is then our NIP-46 client key
+    if !has_column(conn, "keys", "bunker_uri")? {
+        conn.execute("ALTER TABLE keys ADD COLUMN bunker_uri TEXT", [])?;
+    }
+    Ok(())
+}
+
+fn nrc_db_v8(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS group_held_proposals (
+            event_id TEXT PRIMARY KEY,
+            mls_group_id TEXT NOT NULL,
+            proposer TEXT NOT NULL,
+            removes TEXT NOT NULL,
+            event TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            committed_at INTEGER
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn ops_db_v1(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS operations (
+            id TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            status TEXT NOT NULL,
+            last_error TEXT,
+            payload TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn ops_db_v2(conn: &Connection) -> rusqlite::Result<()> {
+    if !has_column(conn, "operations", "attempts")? {
+        conn.execute(
+            "ALTER TABLE operations ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    if !has_column(conn, "operations", "next_attempt_at")? {
+        conn.execute(
+            "ALTER TABLE operations ADD COLUMN next_attempt_at INTEGER",
+            [],
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use nostr_sdk::prelude::*;
+use serde::Deserialize;
+
+/// How long a successful verification is trusted before we check again
+pub const NIP05_VERIFIED_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Failed checks are retried sooner, in case the server was just down
+pub const NIP05_FAILED_TTL: Duration = Duration::from_secs(60 * 60);
+
+const NIP05_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A NIP-05 identifier, `name@domain` (a bare domain means `_@domain`)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Nip05Address {
+    pub name: String,
+    pub domain: String,
+}
+
+impl Nip05Address {
+    pub fn parse(address: &str) -> Result<Self> {
+        let address = address.trim().to_lowercase();
+        let (name, domain) = match address.split_once('@') {
+            Some((name, domain)) => (name.to_string(), domain.to_string()),
+            None => ("_".to_string(), address.clone()),
+        };
+        let name_ok = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        let domain_ok = domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+            && domain
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | ':'));
+        if !name_ok || !domain_ok {
+            return Err(anyhow!("'{address}' is not a NIP-05 address"));
+        }
+        Ok(Self { name, domain })
+    }
+}
+
+impl fmt::Display for Nip05Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name == "_" {
+            write!(f, "{}", self.domain)
+        } else {
+            write!(f, "{}@{}", self.name, self.domain)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NostrJson {
+    #[serde(default)]
+    names: HashMap<String, String>,
+}
+
+/// Looks NIP-05 addresses up on `https://<domain>/.well-known/nostr.json`
+#[derive(Clone)]
+pub struct Nip05Resolver {
+    http: reqwest::Client,
+    // Replaces `https://<domain>` (tests point this at a local server)
+    base_url: Option<String>,
+}
+
+impl Nip05Resolver {
+    pub fn new() -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(NIP05_TIMEOUT)
+                // NIP-05: fetchers must ignore redirects
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap_or_default(),
+            base_url: None,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    fn well_known_url(&self, address: &Nip05Address) -> String {
+        let base = self
+            .base_url
+            .clone()
+            .unwrap_or_else(|| format!("https://{}", address.domain));
+        format!("{base}/.well-known/nostr.json?name={}", address.name)
+    }
+
+    /// The pubkey the domain lists for this name, if any
+    pub async fn lookup(&self, address: &Nip05Address) -> Result<Option<PublicKey>> {
+        let url = self.well_known_url(address);
+        let response = self.http.get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("{url} answered {}", response.status()));
+        }
+        let json: NostrJson = response.json().await?;
+        match json.names.get(&address.name) {
+            Some(hex) => Ok(Some(PublicKey::from_hex(hex)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether the domain vouches for `pubkey` under this name
+    pub async fn verify(&self, pubkey: &PublicKey, address: &Nip05Address) -> Result<bool> {
+        Ok(self.lookup(address).await? == Some(*pubkey))
+    }
+}
+
+impl Default for Nip05Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,7 +1,13 @@
+use crate::delivery::DeliveryStatus;
+use crate::relay_status::{AuthOutcome, RelayActivity};
 use crate::AppEvent;
 use nostr_sdk::prelude::*;
+use std::time::Duration;
 use tokio::sync::mpsc;
 
+/// How long kind 445 events are gathered so competing commits are ordered together
+const GROUP_EVENT_BATCH_WINDOW: Duration = Duration::from_millis(300);
+
 // Test helpers module - only used in tests
 pub mod test_helpers {
     use super::*;
@@ -36,14 +42,34 @@ pub mod test_helpers {
     }
 }
 
+/// Forward group messages to the main loop in short batches (NIP-EE commit ordering)
+fn spawn_group_event_batcher(
+    event_tx: mpsc::UnboundedSender<AppEvent>,
+) -> mpsc::UnboundedSender<Event> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+    tokio::spawn(async move {
+        while let Some(first) = rx.recv().await {
+            let mut events = vec![first];
+            let deadline = tokio::time::Instant::now() + GROUP_EVENT_BATCH_WINDOW;
+            while let Ok(Some(event)) = tokio::time::timeout_at(deadline, rx.recv()).await {
+                events.push(event);
+            }
+            let _ = event_tx.send(AppEvent::RawMessagesReceived { events });
+        }
+    });
+    tx
+}
+
 /// Spawn a task to handle real-time subscription notifications
 pub fn spawn_notification_handler(
     client: Client,
     event_tx: mpsc::UnboundedSender<AppEvent>,
     pubkey: PublicKey,
+    activity: RelayActivity,
 ) {
     // Use npub as ID for test logging
     let client_id = pubkey.to_bech32().unwrap_or_else(|_| pubkey.to_hex());
+    let group_tx = spawn_group_event_batcher(event_tx.clone());
 
     tokio::spawn(async move {
         log::info!("Starting notification handler for real-time events");
@@ -51,7 +77,10 @@ pub fn spawn_notification_handler(
         let result = client
             .handle_notifications(|notification| async {
                 match notification {
-                    RelayPoolNotification::Event { event, .. } => {
+                    RelayPoolNotification::Event {
+                        relay_url, event, ..
+                    } => {
+                        activity.record_received(&relay_url);
                         // Log all events for debugging
                         test_helpers::log_test_event(&client_id, event.as_ref().clone()).await;
 
@@ -63,10 +92,8 @@ pub fn spawn_notification_handler(
                                 });
                             }
                             Kind::MlsGroupMessage => {
-                                // Send MLS messages to main loop
-                                let _ = event_tx.send(AppEvent::RawMessagesReceived {
-                                    events: vec![event.as_ref().clone()],
-                                });
+                                // Batched so racing commits reach the app together
+                                let _ = group_tx.send(event.as_ref().clone());
                             }
                             Kind::MlsKeyPackage => {
                                 // Key package events - forward to app for storage
@@ -87,6 +114,7 @@ pub fn spawn_notification_handler(
                                     let _ = event_tx.send(AppEvent::ProfileMetadataReceived {
                                         pubkey: event.pubkey,
                                         metadata,
+                                        created_at: event.created_at,
                                     });
                                 }
                             }
@@ -96,8 +124,48 @@ pub fn spawn_notification_handler(
                             }
                         }
                     }
-                    RelayPoolNotification::Message { message, .. } => {
+                    RelayPoolNotification::Message {
+                        relay_url, message, ..
+                    } => {
                         log::debug!("Received relay message: {message:?}");
+                        if let RelayMessage::Auth { challenge } = &message {
+                            // NIP-42: the app decides whether (and with which keys) to answer
+                            let _ = event_tx.send(AppEvent::RelayAuthChallenge {
+                                relay_url: relay_url.clone(),
+                                challenge: challenge.to_string(),
+                            });
+                        }
+                        if let RelayMessage::Ok {
+                            event_id,
+                            status: true,
+                            ..
+                        } = &message
+                        {
+                            // Per-relay acks; the app ignores events that aren't our messages
+                            let _ = event_tx.send(AppEvent::MessageDelivery {
+                                event_id: *event_id,
+                                status: DeliveryStatus::Acked,
+                                relays: vec![relay_url.clone()],
+                            });
+                        }
+                        match activity.record_message(&relay_url, &message) {
+                            Some(AuthOutcome::Accepted) => {
+                                log::info!("Authenticated to {relay_url}");
+                                // Subscriptions the relay closed as auth-required can go again
+                                if let Ok(relay) = client.relay(&relay_url).await {
+                                    if let Err(e) = relay.resubscribe().await {
+                                        log::warn!("Failed to resubscribe to {relay_url}: {e}");
+                                    }
+                                }
+                            }
+                            Some(AuthOutcome::Rejected(reason)) => {
+                                log::warn!("AUTH rejected by {relay_url}: {reason}");
+                                let _ = event_tx.send(AppEvent::NetworkError {
+                                    error: format!("AUTH failed on {relay_url}: {reason}"),
+                                });
+                            }
+                            None => {}
+                        }
                     }
                     RelayPoolNotification::Shutdown => {
                         log::info!("Relay pool shutdown notification");
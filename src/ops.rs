@@ -1,12 +1,20 @@
 use anyhow::{anyhow, Result};
 use chrono::Utc;
 use nostr_sdk::prelude::*;
+use openmls::group::GroupId;
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::sync::mpsc;
 
+use crate::delivery::DeliveryStatus;
+use crate::key_packages::{key_package_relays, key_package_relays_kind};
 use crate::key_storage::KeyStorage;
+use crate::migrations;
+use crate::relay_status::any_relay_connected;
+use crate::signer::AccountSigner;
 use uuid::Uuid;
 
 use crate::AppEvent;
@@ -16,15 +24,53 @@ use crate::AppEvent;
 pub enum OperationKind {
     SendMessage {
         event: Event,
+        // The group's own relays; empty means the client's pool
+        #[serde(default)]
+        relays: Vec<RelayUrl>,
     },
     PublishKeyPackage {
         event: Event,
     },
+    // Our kind 10051 KeyPackage Relays List
+    PublishKeyPackageRelays {
+        event: Event,
+    },
+    // NIP-09 deletion of KeyPackages that were consumed or rotated out
+    DeleteKeyPackages {
+        event_ids: Vec<EventId>,
+    },
+    // Republish our packages that relays lost; `published` is the local view
+    ReconcileKeyPackages {
+        published: Vec<Event>,
+    },
     CreateDm {
         other_pubkey: PublicKey,
         // State machine data
         step: CreateDmStep,
     },
+    CreateGroup {
+        name: String,
+        // Invitees (excluding ourselves)
+        members: Vec<PublicKey>,
+        step: CreateGroupStep,
+    },
+    InviteMember {
+        mls_group_id_hex: String,
+        invitee: PublicKey,
+        step: InviteMemberStep,
+    },
+    // Publish a commit or proposal the UI already built in storage
+    GroupEvolution {
+        mls_group_id_hex: String,
+        // Short label for the ops dashboard, e.g. "remove npub1…"
+        description: String,
+        step: GroupEvolutionStep,
+    },
+    // Scheduled self-update commit that rotates our leaf key (forward secrecy)
+    RotateGroupKey {
+        mls_group_id_hex: String,
+        step: RotateGroupKeyStep,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,12 +94,121 @@ pub enum CreateDmStep {
     Done,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CreateGroupStep {
+    // Fetch a KeyPackage for every invitee; packages found so far survive a failed attempt
+    FetchKeyPackages {
+        key_packages: Vec<Event>,
+    },
+    // After fetch, UI must create group in storage
+    RequestCreateGroup {
+        key_packages: Vec<Event>,
+    },
+    // Gift-wrap welcomes; each one is dropped from the list once sent
+    SendWelcomes {
+        nostr_group_id_hex: String,
+        welcomes: Vec<PendingWelcome>,
+    },
+    Done,
+}
+
+// NIP-EE: the Add commit must be acked by a relay and merged before the welcome goes out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum InviteMemberStep {
+    FetchKeyPackage,
+    // After fetch, UI must build the Add commit in storage
+    RequestAddMember {
+        key_package: Event,
+    },
+    PublishCommit {
+        commit_event: Event,
+        welcome_rumor: UnsignedEvent,
+        #[serde(default)]
+        relays: Vec<RelayUrl>,
+    },
+    // Commit was acked; UI must merge it locally
+    MergeCommit {
+        commit_event: Event,
+        welcome_rumor: UnsignedEvent,
+    },
+    SendWelcome {
+        welcome_rumor: UnsignedEvent,
+    },
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GroupEvolutionStep {
+    // Commits are merged locally once a relay acks them; proposals are just published
+    Publish {
+        evolution_event: Event,
+        is_commit: bool,
+        #[serde(default)]
+        relays: Vec<RelayUrl>,
+    },
+    MergeCommit {
+        commit_event: Event,
+    },
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RotateGroupKeyStep {
+    // UI must build the self-update commit in storage
+    RequestSelfUpdate,
+    Publish {
+        commit_event: Event,
+        #[serde(default)]
+        relays: Vec<RelayUrl>,
+    },
+    // Commit was acked; UI must merge it locally
+    MergeCommit {
+        commit_event: Event,
+    },
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingWelcome {
+    pub to: PublicKey,
+    pub welcome_rumor: UnsignedEvent,
+}
+
+/// Match welcome rumors to their recipients via the KeyPackage id in the `e` tag.
+pub fn pair_welcomes(
+    key_packages: &[Event],
+    welcome_rumors: &[UnsignedEvent],
+) -> Vec<PendingWelcome> {
+    welcome_rumors
+        .iter()
+        .enumerate()
+        .filter_map(|(i, rumor)| {
+            let by_tag = rumor
+                .tags
+                .event_ids()
+                .find_map(|id| key_packages.iter().find(|kp| kp.id == *id));
+            // Fall back to positional order if the tag is missing
+            by_tag
+                .or_else(|| key_packages.get(i))
+                .map(|kp| PendingWelcome {
+                    to: kp.pubkey,
+                    welcome_rumor: rumor.clone(),
+                })
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum OpStatus {
     Pending,
     InProgress,
     Success,
+    // Last attempt failed; retried at `next_attempt_at`
     Error,
+    // Gave up: out of attempts, or retrying can't help
+    Failed,
+    // Stopped by the user from the ops dashboard
+    Cancelled,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,35 +219,116 @@ pub struct Operation {
     pub last_error: Option<String>,
     pub updated_at: i64,
     pub created_at: i64,
+    // Failed attempts so far
+    pub attempts: u32,
+    pub next_attempt_at: Option<i64>,
+}
+
+impl Operation {
+    /// Still waiting for the UI to merge its acked commit. The orchestrator asks
+    /// again on every tick, so a repeat request for a merged op must be ignored.
+    pub fn awaits_merge(&self) -> bool {
+        if matches!(self.status, OpStatus::Success | OpStatus::Cancelled) {
+            return false;
+        }
+        matches!(
+            self.kind,
+            OperationKind::InviteMember {
+                step: InviteMemberStep::MergeCommit { .. },
+                ..
+            } | OperationKind::GroupEvolution {
+                step: GroupEvolutionStep::MergeCommit { .. },
+                ..
+            } | OperationKind::RotateGroupKey {
+                step: RotateGroupKeyStep::MergeCommit { .. },
+                ..
+            }
+        )
+    }
+
+    /// Still waiting for the UI to create its group. Also re-requested on every
+    /// tick, and creating the group twice would leave a stray copy behind.
+    pub fn awaits_group_creation(&self) -> bool {
+        self.status != OpStatus::Cancelled
+            && matches!(
+                self.kind,
+                OperationKind::CreateGroup {
+                    step: CreateGroupStep::RequestCreateGroup { .. },
+                    ..
+                }
+            )
+    }
+}
+
+/// Exponential backoff with jitter for failed operations
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    // Attempts before an op is moved to `Failed`
+    pub max_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(10 * 60),
+            max_attempts: 8,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait after the `attempt`th failure: `base * 2^(attempt-1)`, capped, then
+    /// scaled into [50%, 100%] by `jitter` (0.0..=1.0) so retries don't line up.
+    pub fn delay(&self, attempt: u32, jitter: f64) -> Duration {
+        let exp = attempt.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1u32 << exp)
+            .min(self.max_delay);
+        delay.mul_f64(0.5 + 0.5 * jitter.clamp(0.0, 1.0))
+    }
+
+    /// When to try again after `attempts` failures; `None` once they're used up
+    pub fn next_attempt_at(&self, attempts: u32, now: i64, jitter: f64) -> Option<i64> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+        Some(now + self.delay(attempts, jitter).as_secs().max(1) as i64)
+    }
+}
+
+fn jitter() -> f64 {
+    (Uuid::new_v4().as_u128() % 1000) as f64 / 999.0
 }
 
 #[derive(Clone)]
 pub struct OpsStore {
     db_path: PathBuf,
+    retry: RetryPolicy,
 }
 
 impl OpsStore {
     pub fn new(datadir: &Path) -> Result<Self> {
         let path = datadir.join("nrc_ops.db");
-        let store = Self { db_path: path };
+        let store = Self {
+            db_path: path,
+            retry: RetryPolicy::default(),
+        };
         store.init()?;
         Ok(store)
     }
 
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
     fn init(&self) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS operations (
-                id TEXT PRIMARY KEY,
-                kind TEXT NOT NULL,
-                status TEXT NOT NULL,
-                last_error TEXT,
-                payload TEXT NOT NULL,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
-            )",
-            [],
-        )?;
+        let mut conn = Connection::open(&self.db_path)?;
+        migrations::migrate(&mut conn, migrations::OPS_DB)?;
         Ok(())
     }
 
@@ -112,7 +348,8 @@ impl OpsStore {
     pub fn load(&self, id: &str) -> Result<Operation> {
         let conn = Connection::open(&self.db_path)?;
         let mut stmt = conn.prepare(
-            "SELECT id, kind, status, last_error, payload, created_at, updated_at
+            "SELECT id, kind, status, last_error, payload, created_at, updated_at,
+                    attempts, next_attempt_at
              FROM operations WHERE id = ?1",
         )?;
         let op = stmt.query_row(params![id], |row| {
@@ -123,6 +360,8 @@ impl OpsStore {
             let payload: String = row.get(4)?;
             let created_at: i64 = row.get(5)?;
             let updated_at: i64 = row.get(6)?;
+            let attempts: u32 = row.get(7)?;
+            let next_attempt_at: Option<i64> = row.get(8)?;
             let kind: OperationKind = serde_json::from_str(&payload).map_err(|e| {
                 rusqlite::Error::FromSqlConversionFailure(
                     payload.len(),
@@ -138,23 +377,39 @@ impl OpsStore {
                 last_error,
                 created_at,
                 updated_at,
+                attempts,
+                next_attempt_at,
             })
         })?;
         Ok(op)
     }
 
     pub fn take_next_pending(&self) -> Result<Option<Operation>> {
+        self.take_next_pending_except(&[])
+    }
+
+    /// Like `take_next_pending`, but leaves ops of the `held` kinds in the queue.
+    pub fn take_next_pending_except(&self, held: &[&str]) -> Result<Option<Operation>> {
         let conn = Connection::open(&self.db_path)?;
-        // Pick one pending or in_progress op to resume
+        // Pick one pending or in_progress op to resume, or a failed one that's due a retry
+        let now = Utc::now().timestamp();
         let mut stmt = conn.prepare(
-            "SELECT id FROM operations
+            "SELECT id, kind FROM operations
              WHERE status IN ('Pending','InProgress')
-             ORDER BY created_at ASC LIMIT 1",
+                OR (status = 'Error' AND next_attempt_at <= ?1)
+             ORDER BY created_at ASC",
         )?;
-        let next: Option<String> = stmt
-            .query_map([], |row| row.get::<_, String>(0))?
-            .next()
-            .transpose()?;
+        let mut next: Option<String> = None;
+        let rows = stmt.query_map(params![now], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for r in rows {
+            let (id, kind) = r?;
+            if !held.contains(&kind.as_str()) {
+                next = Some(id);
+                break;
+            }
+        }
         if let Some(id) = next {
             let mut op = self.load(&id)?;
             // Mark in progress
@@ -171,7 +426,8 @@ impl OpsStore {
         let payload = serde_json::to_string(&op.kind)?;
         let conn = Connection::open(&self.db_path)?;
         conn.execute(
-            "UPDATE operations SET kind = ?2, status = ?3, last_error = ?4, payload = ?5, updated_at = ?6 WHERE id = ?1",
+            "UPDATE operations SET kind = ?2, status = ?3, last_error = ?4, payload = ?5, updated_at = ?6
+             WHERE id = ?1 AND status != 'Cancelled'",
             params![
                 op.id,
                 Self::kind_str(&op.kind),
@@ -187,7 +443,8 @@ impl OpsStore {
     pub fn list_all(&self) -> Result<Vec<Operation>> {
         let conn = Connection::open(&self.db_path)?;
         let mut stmt = conn.prepare(
-            "SELECT id, kind, status, last_error, payload, created_at, updated_at
+            "SELECT id, kind, status, last_error, payload, created_at, updated_at,
+                    attempts, next_attempt_at
              FROM operations ORDER BY updated_at DESC LIMIT 500",
         )?;
         let rows = stmt.query_map([], |row| {
@@ -198,6 +455,8 @@ impl OpsStore {
             let payload: String = row.get(4)?;
             let created_at: i64 = row.get(5)?;
             let updated_at: i64 = row.get(6)?;
+            let attempts: u32 = row.get(7)?;
+            let next_attempt_at: Option<i64> = row.get(8)?;
             let kind: OperationKind = serde_json::from_str(&payload).map_err(|e| {
                 rusqlite::Error::FromSqlConversionFailure(
                     payload.len(),
@@ -213,6 +472,8 @@ impl OpsStore {
                 last_error,
                 created_at,
                 updated_at,
+                attempts,
+                next_attempt_at,
             })
         })?;
         let mut out = Vec::new();
@@ -222,11 +483,139 @@ impl OpsStore {
         Ok(out)
     }
 
+    /// Messages waiting to go out (e.g. while no relay is connected)
+    pub fn queued_sends(&self) -> Result<usize> {
+        let conn = Connection::open(&self.db_path)?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM operations
+             WHERE kind = 'SendMessage' AND status IN ('Pending','InProgress')",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
     pub fn mark_success(&self, id: &str) -> Result<()> {
         let now = Utc::now().timestamp();
         let conn = Connection::open(&self.db_path)?;
         conn.execute(
-            "UPDATE operations SET status = 'Success', updated_at = ?2 WHERE id = ?1",
+            "UPDATE operations SET status = 'Success', updated_at = ?2
+             WHERE id = ?1 AND status != 'Cancelled'",
+            params![id, now],
+        )?;
+        Ok(())
+    }
+
+    /// Record a failed attempt: retry later with backoff, or move to `Failed` once
+    /// the policy's attempts are used up. Returns the resulting status.
+    pub fn mark_error(&self, id: &str, error: &str) -> Result<OpStatus> {
+        self.record_error(id, None, error)
+    }
+
+    /// `mark_error` for an op that ran: also saves its step, in the same write,
+    /// so the retry resumes from the progress it made.
+    pub fn mark_op_error(&self, op: &Operation, error: &str) -> Result<OpStatus> {
+        let payload = serde_json::to_string(&op.kind)?;
+        self.record_error(&op.id, Some(payload), error)
+    }
+
+    fn record_error(&self, id: &str, payload: Option<String>, error: &str) -> Result<OpStatus> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        let attempts: u32 = conn.query_row(
+            "SELECT attempts FROM operations WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+        let attempts = attempts + 1;
+        let next_attempt_at = self.retry.next_attempt_at(attempts, now, jitter());
+        let status = if next_attempt_at.is_some() {
+            OpStatus::Error
+        } else {
+            OpStatus::Failed
+        };
+        conn.execute(
+            "UPDATE operations SET status = ?2, last_error = ?3, attempts = ?4,
+                next_attempt_at = ?5, updated_at = ?6, payload = COALESCE(?7, payload)
+             WHERE id = ?1 AND status != 'Cancelled'",
+            params![
+                id,
+                Self::status_str(&status),
+                error,
+                attempts,
+                next_attempt_at,
+                now,
+                payload
+            ],
+        )?;
+        Ok(status)
+    }
+
+    /// Give up on an operation without further retries (dead letter); finished ones are left alone.
+    pub fn mark_failed(&self, id: &str, error: &str) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "UPDATE operations SET status = 'Failed', last_error = ?2, next_attempt_at = NULL,
+                updated_at = ?3
+             WHERE id = ?1 AND status NOT IN ('Success', 'Cancelled')",
+            params![id, error, now],
+        )?;
+        Ok(())
+    }
+
+    /// Stop an operation for good; finished ones are left alone. Returns whether it was cancelled.
+    pub fn cancel(&self, id: &str) -> Result<bool> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        let n = conn.execute(
+            "UPDATE operations SET status = 'Cancelled', next_attempt_at = NULL, updated_at = ?2
+             WHERE id = ?1 AND status NOT IN ('Success', 'Cancelled')",
+            params![id, now],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// Ops of one kind (`kind_str`) that haven't finished: pending, running or failed
+    pub fn list_unfinished(&self, kind: &str) -> Result<Vec<Operation>> {
+        let conn = Connection::open(&self.db_path)?;
+        let mut stmt = conn.prepare(
+            "SELECT id FROM operations
+             WHERE kind = ?1 AND status IN ('Pending','InProgress','Error','Failed')
+             ORDER BY created_at ASC",
+        )?;
+        let ids = stmt
+            .query_map(params![kind], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        ids.iter().map(|id| self.load(id)).collect()
+    }
+
+    /// Resume an unfinished CreateGroup for the same name and members (in any order):
+    /// a failed one runs again now, keeping the KeyPackages it already fetched.
+    /// Returns its id, or None if there's nothing to resume.
+    pub fn resume_create_group(&self, name: &str, members: &[PublicKey]) -> Result<Option<String>> {
+        let wanted: HashSet<&PublicKey> = members.iter().collect();
+        let existing = self.list_unfinished("CreateGroup")?.into_iter().find(|op| {
+            matches!(&op.kind, OperationKind::CreateGroup { name: n, members: m, .. }
+                if n == name && m.iter().collect::<HashSet<_>>() == wanted)
+        });
+        let Some(op) = existing else {
+            return Ok(None);
+        };
+        if matches!(op.status, OpStatus::Error | OpStatus::Failed) {
+            self.requeue(&op.id)?;
+        }
+        Ok(Some(op.id))
+    }
+
+    /// Put a failed or stalled operation back in the queue, keeping its current step.
+    pub fn requeue(&self, id: &str) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let conn = Connection::open(&self.db_path)?;
+        conn.execute(
+            "UPDATE operations SET status = 'Pending', last_error = NULL, attempts = 0,
+                next_attempt_at = NULL, updated_at = ?2
+             WHERE id = ?1",
             params![id, now],
         )?;
         Ok(())
@@ -236,7 +625,14 @@ impl OpsStore {
         match kind {
             OperationKind::SendMessage { .. } => "SendMessage",
             OperationKind::PublishKeyPackage { .. } => "PublishKeyPackage",
+            OperationKind::PublishKeyPackageRelays { .. } => "PublishKeyPackageRelays",
+            OperationKind::DeleteKeyPackages { .. } => "DeleteKeyPackages",
+            OperationKind::ReconcileKeyPackages { .. } => "ReconcileKeyPackages",
             OperationKind::CreateDm { .. } => "CreateDm",
+            OperationKind::CreateGroup { .. } => "CreateGroup",
+            OperationKind::InviteMember { .. } => "InviteMember",
+            OperationKind::GroupEvolution { .. } => "GroupEvolution",
+            OperationKind::RotateGroupKey { .. } => "RotateGroupKey",
         }
     }
 
@@ -246,6 +642,8 @@ impl OpsStore {
             OpStatus::InProgress => "InProgress",
             OpStatus::Success => "Success",
             OpStatus::Error => "Error",
+            OpStatus::Failed => "Failed",
+            OpStatus::Cancelled => "Cancelled",
         }
     }
 
@@ -255,6 +653,8 @@ impl OpsStore {
             "InProgress" => OpStatus::InProgress,
             "Success" => OpStatus::Success,
             "Error" => OpStatus::Error,
+            "Failed" => OpStatus::Failed,
+            "Cancelled" => OpStatus::Cancelled,
             _ => OpStatus::Error,
         }
     }
@@ -266,41 +666,82 @@ pub enum OpsCommand {
     Updated(String),
     // Ephemeral (not persisted) background task to save encrypted keys
     SaveEncryptedKeys { password: String },
+    // The app locked: drop our copy of the keys and stop signing
+    Lock,
+    // Keys unlocked (again): resume signing with them
+    Unlock(Box<AccountSigner>),
 }
 
+/// Runs queued operations one at a time. Without a signer (`None`, or after
+/// `OpsCommand::Lock`) operations stay queued until `OpsCommand::Unlock`.
 pub fn spawn_orchestrator(
     ops: OpsStore,
     client: Client,
-    keys: Keys,
+    mut signer: Option<AccountSigner>,
     event_tx: mpsc::UnboundedSender<AppEvent>,
     mut cmd_rx: mpsc::UnboundedReceiver<OpsCommand>,
     datadir: PathBuf,
 ) {
     tokio::spawn(async move {
         loop {
+            // Messages stay queued while no relay is connected, instead of failing
+            let held: &[&str] = if any_relay_connected(&client).await {
+                &[]
+            } else {
+                &["SendMessage"]
+            };
+
             // Process at most one operation per tick to avoid busy loops
-            if let Ok(Some(mut op)) = ops.take_next_pending() {
-                if let Err(e) = process_operation(&ops, &client, &keys, &event_tx, &mut op).await {
+            let next = match &signer {
+                Some(signer) => ops
+                    .take_next_pending_except(held)
+                    .ok()
+                    .flatten()
+                    .map(|op| (signer, op)),
+                None => None,
+            };
+            if let Some((signer, mut op)) = next {
+                if let Err(e) = process_operation(&ops, &client, signer, &event_tx, &mut op).await {
                     log::error!("Operation {} failed: {}", op.id, e);
-                    op.status = OpStatus::Error;
-                    op.last_error = Some(e.to_string());
-                    let _ = ops.save(&op);
+                    let status = match ops.mark_op_error(&op, &e.to_string()) {
+                        Ok(OpStatus::Failed) => {
+                            log::error!("Operation {} out of retries; marked Failed", op.id);
+                            DeliveryStatus::Failed
+                        }
+                        Ok(_) => DeliveryStatus::Queued,
+                        Err(e) => {
+                            log::error!("Failed to record error for {}: {e}", op.id);
+                            DeliveryStatus::Queued
+                        }
+                    };
+                    if let OperationKind::SendMessage { event, .. } = &op.kind {
+                        let _ = event_tx.send(AppEvent::MessageDelivery {
+                            event_id: event.id,
+                            status,
+                            relays: vec![],
+                        });
+                    }
                 }
             }
 
             // Wait for a wake signal or small delay
             tokio::select! {
-                Some(cmd) = cmd_rx.recv() => {
+                cmd = cmd_rx.recv() => {
+                    // The app that owned this queue is gone (exit or account switch)
+                    let Some(cmd) = cmd else { break };
                     match cmd {
                         OpsCommand::Wake => { /* wake up */ }
                         OpsCommand::Updated(_) => { /* nothing extra to do here */ }
                         OpsCommand::SaveEncryptedKeys { password } => {
+                            let Some(signer_clone) = signer.clone() else {
+                                log::warn!("Not saving keys: locked");
+                                continue;
+                            };
                             let datadir_clone = datadir.clone();
-                            let keys_clone = keys.clone();
                             let event_tx_clone = event_tx.clone();
                             tokio::task::spawn_blocking(move || {
                                 let storage = KeyStorage::new(&datadir_clone);
-                                match storage.save_encrypted(&keys_clone, &password) {
+                                match storage.save_account(&signer_clone, &password) {
                                     Ok(_) => {}
                                     Err(e) => {
                                         log::error!("Failed to save encrypted keys: {e}");
@@ -312,6 +753,8 @@ pub fn spawn_orchestrator(
                                 }
                             });
                         }
+                        OpsCommand::Lock => signer = None,
+                        OpsCommand::Unlock(unlocked) => signer = Some(*unlocked),
                     }
                 }
                 _ = tokio::time::sleep(std::time::Duration::from_millis(250)) => {}
@@ -323,14 +766,27 @@ pub fn spawn_orchestrator(
 async fn process_operation(
     ops: &OpsStore,
     client: &Client,
-    keys: &Keys,
+    signer: &AccountSigner,
     event_tx: &mpsc::UnboundedSender<AppEvent>,
     op: &mut Operation,
 ) -> Result<()> {
     let current = op.kind.clone();
     match current {
-        OperationKind::SendMessage { event } => {
-            client.send_event(&event).await?;
+        OperationKind::SendMessage { event, relays } => {
+            let _ = event_tx.send(AppEvent::MessageDelivery {
+                event_id: event.id,
+                status: DeliveryStatus::Sent,
+                relays: vec![],
+            });
+            let output = send_group_event(client, &relays, &event).await?;
+            if output.success.is_empty() {
+                return Err(anyhow!("No relay accepted the message"));
+            }
+            let _ = event_tx.send(AppEvent::MessageDelivery {
+                event_id: event.id,
+                status: DeliveryStatus::Acked,
+                relays: output.success.into_iter().collect(),
+            });
             ops.mark_success(&op.id)?;
         }
         OperationKind::PublishKeyPackage { event } => {
@@ -341,23 +797,59 @@ async fn process_operation(
                 .exit_policy(ReqExitPolicy::ExitOnEOSE)
                 .timeout(Some(std::time::Duration::from_secs(5)));
             client.subscribe(filter, Some(opts)).await?;
+            let _ = event_tx.send(AppEvent::KeyPackagePublished { event_id: event.id });
+            ops.mark_success(&op.id)?;
+        }
+        OperationKind::PublishKeyPackageRelays { event } => {
+            let output = client.send_event(&event).await?;
+            if output.success.is_empty() {
+                return Err(anyhow!("No relay accepted the KeyPackage relay list"));
+            }
+            ops.mark_success(&op.id)?;
+        }
+        OperationKind::DeleteKeyPackages { event_ids } => {
+            let request = EventDeletionRequest::new()
+                .ids(event_ids)
+                .reason("KeyPackage consumed or rotated");
+            let deletion = signer
+                .sign(EventBuilder::delete(request).build(signer.public_key()))
+                .await?;
+            let output = client.send_event(&deletion).await?;
+            if output.success.is_empty() {
+                return Err(anyhow!("No relay accepted the KeyPackage deletion"));
+            }
+            ops.mark_success(&op.id)?;
+        }
+        OperationKind::ReconcileKeyPackages { published } => {
+            let filter = Filter::new()
+                .kind(Kind::MlsKeyPackage)
+                .author(signer.public_key());
+            let on_relays = client
+                .fetch_events(filter, std::time::Duration::from_secs(5))
+                .await?;
+            let on_relay_ids: Vec<EventId> = on_relays.iter().map(|e| e.id).collect();
+
+            for event in &published {
+                if !on_relay_ids.contains(&event.id) {
+                    log::info!("KeyPackage {} missing from relays; republishing", event.id);
+                    client.send_event(event).await?;
+                }
+            }
+            // Packages we don't know about were made by another client or install;
+            // we can't decrypt welcomes for them, but they aren't ours to delete either
+            let unknown = on_relays
+                .iter()
+                .filter(|e| !published.iter().any(|p| p.id == e.id))
+                .count();
+            if unknown > 0 {
+                log::info!("{unknown} KeyPackage(s) on relays without local private keys");
+            }
             ops.mark_success(&op.id)?;
         }
         OperationKind::CreateDm { other_pubkey, step } => {
             match step {
                 CreateDmStep::FetchKeyPackage => {
-                    // Fetch directly with a short timeout
-                    let filter = Filter::new()
-                        .kind(Kind::MlsKeyPackage)
-                        .author(other_pubkey)
-                        .limit(1);
-                    let events = client
-                        .fetch_events(filter.clone(), std::time::Duration::from_secs(2))
-                        .await?;
-                    let key_package = events
-                        .into_iter()
-                        .next()
-                        .ok_or_else(|| anyhow!("No key package found for {}", other_pubkey))?;
+                    let key_package = fetch_key_package(client, other_pubkey).await?;
 
                     // Update op to request storage-side group creation
                     op.kind = OperationKind::CreateDm {
@@ -392,8 +884,7 @@ async fn process_operation(
                     welcome_rumor, to, ..
                 } => {
                     // Send welcome immediately for faster UX
-                    let gift_wrapped =
-                        EventBuilder::gift_wrap(keys, &to, welcome_rumor.clone(), None).await?;
+                    let gift_wrapped = signer.gift_wrap(&to, welcome_rumor.clone()).await?;
                     client.send_event(&gift_wrapped).await?;
                     ops.mark_success(&op.id)?;
                     op.kind = OperationKind::CreateDm {
@@ -406,8 +897,7 @@ async fn process_operation(
                     welcome_rumor,
                     to,
                 } => {
-                    let gift_wrapped =
-                        EventBuilder::gift_wrap(keys, &to, welcome_rumor.clone(), None).await?;
+                    let gift_wrapped = signer.gift_wrap(&to, welcome_rumor.clone()).await?;
                     client.send_event(&gift_wrapped).await?;
                     ops.mark_success(&op.id)?;
                     op.kind = OperationKind::CreateDm {
@@ -420,6 +910,375 @@ async fn process_operation(
                 }
             }
         }
+        OperationKind::CreateGroup {
+            name,
+            members,
+            step,
+        } => match step {
+            CreateGroupStep::FetchKeyPackages { mut key_packages } => {
+                let missing: Vec<PublicKey> = members
+                    .iter()
+                    .filter(|pk| !key_packages.iter().any(|kp| kp.pubkey == **pk))
+                    .cloned()
+                    .collect();
+                if !missing.is_empty() {
+                    let filter = Filter::new()
+                        .kind(Kind::MlsKeyPackage)
+                        .authors(missing.clone());
+                    let events = client
+                        .fetch_events(filter, std::time::Duration::from_secs(2))
+                        .await?;
+                    for pk in &missing {
+                        // Prefer the newest package per invitee
+                        if let Some(kp) = events
+                            .iter()
+                            .filter(|e| e.pubkey == *pk)
+                            .max_by_key(|e| e.created_at)
+                        {
+                            key_packages.push(kp.clone());
+                        }
+                    }
+                }
+
+                let still_missing: Vec<String> = members
+                    .iter()
+                    .filter(|pk| !key_packages.iter().any(|kp| kp.pubkey == **pk))
+                    .map(crate::utils::pubkey_to_bech32_safe)
+                    .collect();
+                if !still_missing.is_empty() {
+                    // Keep what we found so a retry only fetches the rest
+                    op.kind = OperationKind::CreateGroup {
+                        name,
+                        members,
+                        step: CreateGroupStep::FetchKeyPackages { key_packages },
+                    };
+                    return Err(anyhow!(
+                        "No key package found for {}",
+                        still_missing.join(", ")
+                    ));
+                }
+
+                op.kind = OperationKind::CreateGroup {
+                    name: name.clone(),
+                    members: members.clone(),
+                    step: CreateGroupStep::RequestCreateGroup {
+                        key_packages: key_packages.clone(),
+                    },
+                };
+                ops.save(op)?;
+
+                let _ = event_tx.send(AppEvent::OpNeedsStorageCreateMultiGroup {
+                    op_id: op.id.clone(),
+                    name,
+                    members,
+                    key_packages,
+                });
+            }
+            CreateGroupStep::RequestCreateGroup { key_packages } => {
+                // Re-emit the storage request so UI can proceed (important on resume)
+                let _ = event_tx.send(AppEvent::OpNeedsStorageCreateMultiGroup {
+                    op_id: op.id.clone(),
+                    name,
+                    members,
+                    key_packages,
+                });
+            }
+            CreateGroupStep::SendWelcomes {
+                nostr_group_id_hex,
+                mut welcomes,
+            } => {
+                while let Some(pending) = welcomes.first().cloned() {
+                    let gift_wrapped = signer.gift_wrap(&pending.to, pending.welcome_rumor).await?;
+                    client.send_event(&gift_wrapped).await?;
+
+                    // Persist progress so a crash doesn't re-send delivered welcomes
+                    welcomes.remove(0);
+                    op.kind = OperationKind::CreateGroup {
+                        name: name.clone(),
+                        members: members.clone(),
+                        step: CreateGroupStep::SendWelcomes {
+                            nostr_group_id_hex: nostr_group_id_hex.clone(),
+                            welcomes: welcomes.clone(),
+                        },
+                    };
+                    ops.save(op)?;
+                }
+                op.kind = OperationKind::CreateGroup {
+                    name,
+                    members,
+                    step: CreateGroupStep::Done,
+                };
+                ops.save(op)?;
+                ops.mark_success(&op.id)?;
+            }
+            CreateGroupStep::Done => {
+                ops.mark_success(&op.id)?;
+            }
+        },
+        OperationKind::InviteMember {
+            mls_group_id_hex,
+            invitee,
+            step,
+        } => {
+            let group_id = GroupId::from_slice(&hex::decode(&mls_group_id_hex)?);
+            match step {
+                InviteMemberStep::FetchKeyPackage => {
+                    let key_package = fetch_key_package(client, invitee).await?;
+                    op.kind = OperationKind::InviteMember {
+                        mls_group_id_hex,
+                        invitee,
+                        step: InviteMemberStep::RequestAddMember {
+                            key_package: key_package.clone(),
+                        },
+                    };
+                    ops.save(op)?;
+                    let _ = event_tx.send(AppEvent::OpNeedsStorageAddMember {
+                        op_id: op.id.clone(),
+                        group_id,
+                        key_package,
+                    });
+                }
+                InviteMemberStep::RequestAddMember { key_package } => {
+                    // Re-emit the storage request so UI can proceed (important on resume)
+                    let _ = event_tx.send(AppEvent::OpNeedsStorageAddMember {
+                        op_id: op.id.clone(),
+                        group_id,
+                        key_package,
+                    });
+                }
+                InviteMemberStep::PublishCommit {
+                    commit_event,
+                    welcome_rumor,
+                    relays,
+                } => {
+                    let output = send_group_event(client, &relays, &commit_event).await?;
+                    if output.success.is_empty() {
+                        return Err(anyhow!("No relay accepted the commit"));
+                    }
+                    op.kind = OperationKind::InviteMember {
+                        mls_group_id_hex,
+                        invitee,
+                        step: InviteMemberStep::MergeCommit {
+                            commit_event: commit_event.clone(),
+                            welcome_rumor,
+                        },
+                    };
+                    ops.save(op)?;
+                    let _ = event_tx.send(AppEvent::OpNeedsStorageMergeCommit {
+                        op_id: op.id.clone(),
+                        group_id,
+                        commit_event,
+                    });
+                }
+                InviteMemberStep::MergeCommit { commit_event, .. } => {
+                    // Re-emit the merge request so UI can proceed (important on resume)
+                    let _ = event_tx.send(AppEvent::OpNeedsStorageMergeCommit {
+                        op_id: op.id.clone(),
+                        group_id,
+                        commit_event,
+                    });
+                }
+                InviteMemberStep::SendWelcome { welcome_rumor } => {
+                    let gift_wrapped = signer.gift_wrap(&invitee, welcome_rumor).await?;
+                    client.send_event(&gift_wrapped).await?;
+                    op.kind = OperationKind::InviteMember {
+                        mls_group_id_hex,
+                        invitee,
+                        step: InviteMemberStep::Done,
+                    };
+                    ops.save(op)?;
+                    ops.mark_success(&op.id)?;
+                }
+                InviteMemberStep::Done => {
+                    ops.mark_success(&op.id)?;
+                }
+            }
+        }
+        OperationKind::GroupEvolution {
+            mls_group_id_hex,
+            description,
+            step,
+        } => {
+            let group_id = GroupId::from_slice(&hex::decode(&mls_group_id_hex)?);
+            match step {
+                GroupEvolutionStep::Publish {
+                    evolution_event,
+                    is_commit,
+                    relays,
+                } => {
+                    let output = send_group_event(client, &relays, &evolution_event).await?;
+                    if output.success.is_empty() {
+                        return Err(anyhow!("No relay accepted the group event"));
+                    }
+                    if is_commit {
+                        op.kind = OperationKind::GroupEvolution {
+                            mls_group_id_hex,
+                            description,
+                            step: GroupEvolutionStep::MergeCommit {
+                                commit_event: evolution_event.clone(),
+                            },
+                        };
+                        ops.save(op)?;
+                        let _ = event_tx.send(AppEvent::OpNeedsStorageMergeCommit {
+                            op_id: op.id.clone(),
+                            group_id,
+                            commit_event: evolution_event,
+                        });
+                    } else {
+                        op.kind = OperationKind::GroupEvolution {
+                            mls_group_id_hex,
+                            description,
+                            step: GroupEvolutionStep::Done,
+                        };
+                        ops.save(op)?;
+                        ops.mark_success(&op.id)?;
+                    }
+                }
+                GroupEvolutionStep::MergeCommit { commit_event } => {
+                    // Re-emit the merge request so UI can proceed (important on resume)
+                    let _ = event_tx.send(AppEvent::OpNeedsStorageMergeCommit {
+                        op_id: op.id.clone(),
+                        group_id,
+                        commit_event,
+                    });
+                }
+                GroupEvolutionStep::Done => {
+                    ops.mark_success(&op.id)?;
+                }
+            }
+        }
+        OperationKind::RotateGroupKey {
+            mls_group_id_hex,
+            step,
+        } => {
+            let group_id = GroupId::from_slice(&hex::decode(&mls_group_id_hex)?);
+            match step {
+                RotateGroupKeyStep::RequestSelfUpdate => {
+                    let _ = event_tx.send(AppEvent::OpNeedsStorageSelfUpdate {
+                        op_id: op.id.clone(),
+                        group_id,
+                    });
+                }
+                RotateGroupKeyStep::Publish {
+                    commit_event,
+                    relays,
+                } => {
+                    let output = send_group_event(client, &relays, &commit_event).await?;
+                    if output.success.is_empty() {
+                        return Err(anyhow!("No relay accepted the self-update commit"));
+                    }
+                    op.kind = OperationKind::RotateGroupKey {
+                        mls_group_id_hex,
+                        step: RotateGroupKeyStep::MergeCommit {
+                            commit_event: commit_event.clone(),
+                        },
+                    };
+                    ops.save(op)?;
+                    let _ = event_tx.send(AppEvent::OpNeedsStorageMergeCommit {
+                        op_id: op.id.clone(),
+                        group_id,
+                        commit_event,
+                    });
+                }
+                RotateGroupKeyStep::MergeCommit { commit_event } => {
+                    // Re-emit the merge request so UI can proceed (important on resume)
+                    let _ = event_tx.send(AppEvent::OpNeedsStorageMergeCommit {
+                        op_id: op.id.clone(),
+                        group_id,
+                        commit_event,
+                    });
+                }
+                RotateGroupKeyStep::Done => {
+                    ops.mark_success(&op.id)?;
+                }
+            }
+        }
     }
     Ok(())
 }
+
+/// Add and connect relays the pool doesn't know yet (group or KeyPackage relays)
+pub async fn ensure_relays(client: &Client, relays: &[RelayUrl]) {
+    for url in relays {
+        if let Err(e) = client.add_relay(url.as_str()).await {
+            log::warn!("Failed to add relay {url}: {e}");
+            continue;
+        }
+        let _ = client.connect_relay(url.as_str()).await;
+    }
+}
+
+/// Publish a kind 445 event to exactly its group's relays
+async fn send_group_event(
+    client: &Client,
+    relays: &[RelayUrl],
+    event: &Event,
+) -> Result<Output<EventId>> {
+    if relays.is_empty() {
+        return Ok(client.send_event(event).await?);
+    }
+    ensure_relays(client, relays).await;
+    Ok(client
+        .send_event_to(relays.iter().map(|u| u.as_str()), event)
+        .await?)
+}
+
+async fn fetch_key_package(client: &Client, pubkey: PublicKey) -> Result<Event> {
+    let filter = Filter::new()
+        .kind(Kind::MlsKeyPackage)
+        .author(pubkey)
+        .limit(1);
+
+    // Prefer the relays listed in their kind 10051 event; people on private relays
+    // only publish KeyPackages there
+    let relays = fetch_key_package_relays(client, pubkey).await;
+    if !relays.is_empty() {
+        ensure_relays(client, &relays).await;
+        match client
+            .fetch_events_from(
+                relays.iter().map(|u| u.as_str()),
+                filter.clone(),
+                std::time::Duration::from_secs(3),
+            )
+            .await
+        {
+            Ok(events) => {
+                if let Some(event) = events.into_iter().next() {
+                    return Ok(event);
+                }
+                log::info!("No key package for {pubkey} on their listed relays; trying ours");
+            }
+            Err(e) => log::warn!("Failed to query KeyPackage relays for {pubkey}: {e}"),
+        }
+    }
+
+    // Fetch directly with a short timeout
+    let events = client
+        .fetch_events(filter, std::time::Duration::from_secs(2))
+        .await?;
+    events
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No key package found for {}", pubkey))
+}
+
+async fn fetch_key_package_relays(client: &Client, pubkey: PublicKey) -> Vec<RelayUrl> {
+    let filter = Filter::new()
+        .kind(key_package_relays_kind())
+        .author(pubkey)
+        .limit(1);
+    match client
+        .fetch_events(filter, std::time::Duration::from_secs(2))
+        .await
+    {
+        Ok(events) => events
+            .into_iter()
+            .max_by_key(|e| e.created_at)
+            .map(|e| key_package_relays(&e))
+            .unwrap_or_default(),
+        Err(e) => {
+            log::debug!("No KeyPackage relay list for {pubkey}: {e}");
+            Vec::new()
+        }
+    }
+}
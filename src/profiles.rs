@@ -1,44 +1,317 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use nostr_sdk::prelude::*;
+use rusqlite::{params, Connection};
 use tokio::sync::Mutex;
 
-/// Small helper that owns Kind 0 cache + subscription/fetch logic.
+use crate::migrations;
+use crate::nip05::{Nip05Address, NIP05_FAILED_TTL, NIP05_VERIFIED_TTL};
+
+/// Profiles older than this are re-requested from relays when shown
+pub const PROFILE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Kind 0 metadata we know for a pubkey
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedProfile {
+    pub metadata: Metadata,
+    // created_at of the Kind 0 event; an older event never replaces a newer one
+    pub created_at: Timestamp,
+    // When we last received it from a relay
+    pub fetched_at: Timestamp,
+}
+
+/// Outcome of checking a profile's `nip05` against its domain
+#[derive(Clone, Debug, PartialEq)]
+pub struct Nip05Check {
+    pub address: Nip05Address,
+    pub verified: bool,
+    pub checked_at: Timestamp,
+}
+
+impl Nip05Check {
+    fn expired(&self, now: Timestamp) -> bool {
+        let ttl = if self.verified {
+            NIP05_VERIFIED_TTL
+        } else {
+            NIP05_FAILED_TTL
+        };
+        now.as_u64().saturating_sub(self.checked_at.as_u64()) >= ttl.as_secs()
+    }
+}
+
+/// Small helper that owns Kind 0 cache + subscription logic.
+///
+/// The cache is persisted in nrc.db so names show immediately after a restart.
 #[derive(Clone)]
 pub struct Profiles {
-    store: Arc<Mutex<HashMap<PublicKey, Metadata>>>,
+    store: Arc<Mutex<HashMap<PublicKey, CachedProfile>>>,
+    // Pubkeys we've already subscribed to this session
+    subscribed: Arc<Mutex<HashSet<PublicKey>>>,
+    nip05_checks: Arc<Mutex<HashMap<PublicKey, Nip05Check>>>,
+    db_path: Option<PathBuf>,
 }
 impl Profiles {
+    /// In-memory cache only
     pub fn new() -> Self {
         Self {
             store: Arc::new(Mutex::new(HashMap::new())),
+            subscribed: Arc::new(Mutex::new(HashSet::new())),
+            nip05_checks: Arc::new(Mutex::new(HashMap::new())),
+            db_path: None,
         }
     }
 
-    pub async fn cache(&self, pk: PublicKey, metadata: Metadata) {
+    /// Cache backed by the profiles table in nrc.db, loaded up front
+    pub fn load(datadir: &Path) -> Result<Self> {
+        let db_path = datadir.join("nrc.db");
+        let mut conn = Connection::open(&db_path)?;
+        migrations::migrate(&mut conn, migrations::NRC_DB)?;
+
+        let mut map = HashMap::new();
+        let mut stmt =
+            conn.prepare("SELECT pubkey, metadata, created_at, fetched_at FROM profiles")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+        for r in rows {
+            let (pk, metadata, created_at, fetched_at) = r?;
+            // A bad row only costs us a refetch
+            let (Ok(pk), Ok(metadata)) = (PublicKey::from_hex(&pk), Metadata::from_json(&metadata))
+            else {
+                log::warn!("Skipping unreadable cached profile {pk}");
+                continue;
+            };
+            map.insert(
+                pk,
+                CachedProfile {
+                    metadata,
+                    created_at: Timestamp::from(created_at as u64),
+                    fetched_at: Timestamp::from(fetched_at as u64),
+                },
+            );
+        }
+        log::info!("Loaded {} cached profiles", map.len());
+
+        let mut checks = HashMap::new();
+        let mut stmt =
+            conn.prepare("SELECT pubkey, address, verified, checked_at FROM nip05_checks")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, bool>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+        for r in rows {
+            let (pk, address, verified, checked_at) = r?;
+            let (Ok(pk), Ok(address)) = (PublicKey::from_hex(&pk), Nip05Address::parse(&address))
+            else {
+                continue;
+            };
+            checks.insert(
+                pk,
+                Nip05Check {
+                    address,
+                    verified,
+                    checked_at: Timestamp::from(checked_at as u64),
+                },
+            );
+        }
+
+        Ok(Self {
+            store: Arc::new(Mutex::new(map)),
+            subscribed: Arc::new(Mutex::new(HashSet::new())),
+            nip05_checks: Arc::new(Mutex::new(checks)),
+            db_path: Some(db_path),
+        })
+    }
+
+    /// Remember metadata from a Kind 0 event created at `created_at`.
+    ///
+    /// Returns false (and keeps what we have) if we already hold a newer or equal event.
+    pub async fn cache(&self, pk: PublicKey, metadata: Metadata, created_at: Timestamp) -> bool {
+        let now = Timestamp::now();
         let mut map = self.store.lock().await;
-        map.insert(pk, metadata);
+        if let Some(existing) = map.get_mut(&pk) {
+            if existing.created_at >= created_at {
+                // Still tells us the cached copy is current
+                existing.fetched_at = now;
+                self.persist_fetched(&pk, now);
+                return false;
+            }
+        }
+        let profile = CachedProfile {
+            metadata,
+            created_at,
+            fetched_at: now,
+        };
+        self.persist(&pk, &profile);
+        map.insert(pk, profile);
+        true
+    }
+
+    fn persist(&self, pk: &PublicKey, profile: &CachedProfile) {
+        let Some(db_path) = &self.db_path else {
+            return;
+        };
+        let res = Connection::open(db_path).and_then(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO profiles (pubkey, metadata, created_at, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    pk.to_hex(),
+                    profile.metadata.as_json(),
+                    profile.created_at.as_u64() as i64,
+                    profile.fetched_at.as_u64() as i64
+                ],
+            )
+        });
+        if let Err(e) = res {
+            log::warn!("Failed to persist profile {pk}: {e}");
+        }
+    }
+
+    fn persist_fetched(&self, pk: &PublicKey, fetched_at: Timestamp) {
+        let Some(db_path) = &self.db_path else {
+            return;
+        };
+        let res = Connection::open(db_path).and_then(|conn| {
+            conn.execute(
+                "UPDATE profiles SET fetched_at = ?2 WHERE pubkey = ?1",
+                params![pk.to_hex(), fetched_at.as_u64() as i64],
+            )
+        });
+        if let Err(e) = res {
+            log::warn!("Failed to persist profile {pk}: {e}");
+        }
+    }
+
+    pub async fn get(&self, pk: &PublicKey) -> Option<CachedProfile> {
+        self.store.lock().await.get(pk).cloned()
+    }
+
+    pub async fn pubkeys(&self) -> Vec<PublicKey> {
+        self.store.lock().await.keys().copied().collect()
+    }
+
+    fn nip05_of(profile: Option<&CachedProfile>) -> Option<Nip05Address> {
+        profile
+            .and_then(|p| p.metadata.nip05.as_deref())
+            .and_then(|a| Nip05Address::parse(a).ok())
+    }
+
+    /// The `nip05` a profile claims, if it hasn't been checked (or the check expired)
+    pub async fn nip05_to_check(&self, pk: &PublicKey, now: Timestamp) -> Option<Nip05Address> {
+        let address = Self::nip05_of(self.store.lock().await.get(pk))?;
+        match self.nip05_checks.lock().await.get(pk) {
+            Some(check) if check.address == address && !check.expired(now) => None,
+            _ => Some(address),
+        }
+    }
+
+    /// Remember a NIP-05 check; returns whether the verified state changed
+    pub async fn record_nip05(
+        &self,
+        pk: PublicKey,
+        address: Nip05Address,
+        verified: bool,
+        checked_at: Timestamp,
+    ) -> bool {
+        let was = self.is_verified(&pk, checked_at).await;
+        if let Some(db_path) = &self.db_path {
+            let res = Connection::open(db_path).and_then(|conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO nip05_checks (pubkey, address, verified, checked_at)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        pk.to_hex(),
+                        address.to_string(),
+                        verified,
+                        checked_at.as_u64() as i64
+                    ],
+                )
+            });
+            if let Err(e) = res {
+                log::warn!("Failed to persist NIP-05 check for {pk}: {e}");
+            }
+        }
+        self.nip05_checks.lock().await.insert(
+            pk,
+            Nip05Check {
+                address,
+                verified,
+                checked_at,
+            },
+        );
+        self.is_verified(&pk, checked_at).await != was
+    }
+
+    /// Whether the profile's current `nip05` was verified and the result hasn't expired
+    pub async fn is_verified(&self, pk: &PublicKey, now: Timestamp) -> bool {
+        let address = Self::nip05_of(self.store.lock().await.get(pk));
+        let checks = self.nip05_checks.lock().await;
+        Self::verified_with(address, checks.get(pk), now)
+    }
+
+    fn verified_with(
+        address: Option<Nip05Address>,
+        check: Option<&Nip05Check>,
+        now: Timestamp,
+    ) -> bool {
+        match (address, check) {
+            (Some(address), Some(check)) => {
+                check.verified && check.address == address && !check.expired(now)
+            }
+            _ => false,
+        }
+    }
+
+    /// Verified pubkeys, without blocking (for rendering)
+    pub fn try_verified(&self) -> Option<HashSet<PublicKey>> {
+        let now = Timestamp::now();
+        let map = self.store.try_lock().ok()?;
+        let checks = self.nip05_checks.try_lock().ok()?;
+        Some(
+            checks
+                .iter()
+                .filter(|(pk, check)| {
+                    Self::verified_with(Self::nip05_of(map.get(pk)), Some(check), now)
+                })
+                .map(|(pk, _)| *pk)
+                .collect(),
+        )
     }
 
     /// Return best-effort display name for pk.
     pub async fn display_name_async(&self, pk: &PublicKey) -> Option<String> {
         let map = self.store.lock().await;
-        Self::pick_name(map.get(pk))
+        Self::pick_name(map.get(pk).map(|p| &p.metadata))
     }
 
     /// Non-async convenience using a fast path if lock is available.
     pub fn display_name(&self, pk: &PublicKey) -> Option<String> {
         if let Ok(map) = self.store.try_lock() {
-            return Self::pick_name(map.get(pk));
+            return Self::pick_name(map.get(pk).map(|p| &p.metadata));
         }
         None
     }
 
     /// Try to get a snapshot of profiles without blocking.
     pub fn try_snapshot(&self) -> Option<HashMap<PublicKey, Metadata>> {
-        self.store.try_lock().ok().map(|m| m.clone())
+        self.store
+            .try_lock()
+            .ok()
+            .map(|m| m.iter().map(|(pk, p)| (*pk, p.metadata.clone())).collect())
     }
 
     fn pick_name(meta: Option<&Metadata>) -> Option<String> {
@@ -46,43 +319,52 @@ impl Profiles {
             .or_else(|| meta.and_then(|m| m.name.clone().filter(|s| !s.is_empty())))
     }
 
-    /// Subscribe and opportunistically fetch missing Kind 0 for pubkeys.
+    /// Pubkeys among `pubkeys` that we have no profile for, or only one older than `ttl`
+    pub async fn stale(
+        &self,
+        pubkeys: &[PublicKey],
+        now: Timestamp,
+        ttl: Duration,
+    ) -> Vec<PublicKey> {
+        let map = self.store.lock().await;
+        pubkeys
+            .iter()
+            .filter(|pk| match map.get(pk) {
+                Some(p) => now.as_u64().saturating_sub(p.fetched_at.as_u64()) >= ttl.as_secs(),
+                None => true,
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Subscribe to Kind 0 for pubkeys whose cached profile is missing or stale.
+    ///
+    /// Doesn't wait for relays: answers arrive as `ProfileMetadataReceived`.
     pub async fn ensure(&self, client: &Client, pubkeys: Vec<PublicKey>) -> Result<()> {
         if pubkeys.is_empty() {
             return Ok(());
         }
 
-        // Subscribe first
-        let filter = Filter::new().kind(Kind::Metadata).authors(pubkeys.clone());
-        let _ = client.subscribe(filter, None).await;
-
-        // Determine what we still need
-        let missing: Vec<PublicKey> = {
-            let map = self.store.lock().await;
-            pubkeys
+        let stale = self.stale(&pubkeys, Timestamp::now(), PROFILE_TTL).await;
+        let wanted: Vec<PublicKey> = {
+            let mut subscribed = self.subscribed.lock().await;
+            stale
                 .into_iter()
-                .filter(|pk| !map.contains_key(pk))
+                .filter(|pk| subscribed.insert(*pk))
                 .collect()
         };
-
-        if missing.is_empty() {
+        if wanted.is_empty() {
             return Ok(());
         }
 
-        // One-shot fetch fallback
-        let filter = Filter::new().kind(Kind::Metadata).authors(missing);
-        if let Ok(events) = client
-            .fetch_events(filter, std::time::Duration::from_secs(3))
-            .await
-        {
-            if !events.is_empty() {
-                let mut map = self.store.lock().await;
-                for ev in events {
-                    if let Ok(meta) = Metadata::from_json(&ev.content) {
-                        map.insert(ev.pubkey, meta);
-                    }
-                }
+        let filter = Filter::new().kind(Kind::Metadata).authors(wanted.clone());
+        if let Err(e) = client.subscribe(filter, None).await {
+            // Let the next ensure try again
+            let mut subscribed = self.subscribed.lock().await;
+            for pk in &wanted {
+                subscribed.remove(pk);
             }
+            return Err(e.into());
         }
         Ok(())
     }
@@ -0,0 +1,28 @@
+use anyhow::{Context, Result};
+use nostr_sdk::prelude::*;
+
+use crate::relay_status::RelayActivity;
+use crate::signer::AccountSigner;
+
+/// Answer a relay's NIP-42 AUTH challenge with a kind 22242 event signed by `signer`.
+///
+/// The relay's OK for the returned event id arrives through the notification handler.
+pub async fn answer_auth_challenge(
+    client: &Client,
+    signer: &AccountSigner,
+    activity: &RelayActivity,
+    relay_url: &RelayUrl,
+    challenge: &str,
+) -> Result<EventId> {
+    let unsigned = EventBuilder::auth(challenge, relay_url.clone()).build(signer.public_key());
+    let event = signer.sign(unsigned).await?;
+    let relay = client
+        .relay(relay_url)
+        .await
+        .with_context(|| format!("{relay_url} is not in the relay pool"))?;
+
+    activity.expect_auth(relay_url, event.id);
+    relay.send_msg(ClientMessage::auth(event.clone()))?;
+    log::info!("Sent AUTH to {relay_url}");
+    Ok(event.id)
+}
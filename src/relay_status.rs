@@ -0,0 +1,224 @@
+use nostr_sdk::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::events::ConnectionStatus;
+use crate::ui_state::RelayInfo;
+use crate::AppEvent;
+
+/// How often the monitor samples the relay pool
+const RELAY_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What a relay told us, counted by the notification handler
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RelayCounters {
+    pub events_received: u64,
+    // Events the relay accepted with an OK
+    pub events_sent: u64,
+    pub last_error: Option<String>,
+}
+
+/// How a relay answered one of our NIP-42 AUTH events
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuthOutcome {
+    Accepted,
+    Rejected(String),
+}
+
+/// Per-relay counters shared between the notification handler and the monitor
+#[derive(Clone, Default)]
+pub struct RelayActivity {
+    inner: Arc<Mutex<HashMap<RelayUrl, RelayCounters>>>,
+    // AUTH events we sent and are waiting on an OK for
+    pending_auth: Arc<Mutex<HashMap<EventId, RelayUrl>>>,
+}
+
+impl RelayActivity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&self, url: &RelayUrl, f: impl FnOnce(&mut RelayCounters)) {
+        if let Ok(mut map) = self.inner.lock() {
+            f(map.entry(url.clone()).or_default());
+        }
+    }
+
+    pub fn record_received(&self, url: &RelayUrl) {
+        self.update(url, |c| c.events_received += 1);
+    }
+
+    pub fn record_sent(&self, url: &RelayUrl) {
+        self.update(url, |c| c.events_sent += 1);
+    }
+
+    pub fn record_error(&self, url: &RelayUrl, error: impl Into<String>) {
+        let error = error.into();
+        self.update(url, |c| c.last_error = Some(error));
+    }
+
+    /// Remember an AUTH event so its OK is reported as an auth result
+    pub fn expect_auth(&self, url: &RelayUrl, event_id: EventId) {
+        if let Ok(mut pending) = self.pending_auth.lock() {
+            pending.insert(event_id, url.clone());
+        }
+    }
+
+    fn take_auth(&self, url: &RelayUrl, event_id: &EventId) -> bool {
+        self.pending_auth
+            .lock()
+            .map(|mut pending| pending.remove(event_id).is_some_and(|u| u == *url))
+            .unwrap_or(false)
+    }
+
+    /// Count an OK/NOTICE/CLOSED message from a relay; OKs for our AUTH events
+    /// come back as an outcome instead.
+    pub fn record_message(&self, url: &RelayUrl, message: &RelayMessage) -> Option<AuthOutcome> {
+        if let RelayMessage::Ok {
+            event_id,
+            status,
+            message,
+        } = message
+        {
+            if self.take_auth(url, event_id) {
+                if *status {
+                    return Some(AuthOutcome::Accepted);
+                }
+                self.record_error(url, format!("auth failed: {message}"));
+                return Some(AuthOutcome::Rejected(message.to_string()));
+            }
+        }
+
+        match message {
+            RelayMessage::Ok { status: true, .. } => self.record_sent(url),
+            RelayMessage::Ok {
+                status: false,
+                message,
+                ..
+            } => self.record_error(url, format!("rejected: {message}")),
+            RelayMessage::Notice(notice) => self.record_error(url, notice.to_string()),
+            RelayMessage::Closed { message, .. } if !message.is_empty() => {
+                self.record_error(url, format!("closed: {message}"))
+            }
+            _ => {}
+        }
+        None
+    }
+
+    pub fn get(&self, url: &RelayUrl) -> RelayCounters {
+        self.inner
+            .lock()
+            .ok()
+            .and_then(|map| map.get(url).cloned())
+            .unwrap_or_default()
+    }
+}
+
+/// Collapse nostr-sdk's relay states into what the UI shows
+pub fn relay_state(status: RelayStatus) -> ConnectionStatus {
+    match status {
+        RelayStatus::Connected => ConnectionStatus::Connected,
+        RelayStatus::Initialized | RelayStatus::Pending | RelayStatus::Connecting => {
+            ConnectionStatus::Connecting
+        }
+        RelayStatus::Banned => ConnectionStatus::Error("banned".to_string()),
+        _ => ConnectionStatus::Disconnected,
+    }
+}
+
+/// Connected if any relay is; otherwise Connecting if any is trying
+pub fn overall_status(relays: &[RelayInfo]) -> ConnectionStatus {
+    if relays
+        .iter()
+        .any(|r| r.state == ConnectionStatus::Connected)
+    {
+        ConnectionStatus::Connected
+    } else if relays
+        .iter()
+        .any(|r| r.state == ConnectionStatus::Connecting)
+    {
+        ConnectionStatus::Connecting
+    } else {
+        ConnectionStatus::Disconnected
+    }
+}
+
+pub fn connected_count(relays: &[RelayInfo]) -> usize {
+    relays
+        .iter()
+        .filter(|r| r.state == ConnectionStatus::Connected)
+        .count()
+}
+
+/// Whether any relay in the client's pool is connected right now
+pub async fn any_relay_connected(client: &Client) -> bool {
+    client
+        .relays()
+        .await
+        .values()
+        .any(|r| r.status() == RelayStatus::Connected)
+}
+
+pub async fn snapshot(client: &Client, activity: &RelayActivity) -> Vec<RelayInfo> {
+    let mut relays: Vec<RelayInfo> = client
+        .relays()
+        .await
+        .iter()
+        .map(|(url, relay)| {
+            let counters = activity.get(url);
+            RelayInfo {
+                url: url.to_string(),
+                state: relay_state(relay.status()),
+                latency: relay.stats().latency(),
+                last_error: counters.last_error,
+                events_received: counters.events_received,
+                events_sent: counters.events_sent,
+            }
+        })
+        .collect();
+    relays.sort_by(|a, b| a.url.cmp(&b.url));
+    relays
+}
+
+/// Poll the relay pool and tell the app when relays or overall connectivity change
+pub fn spawn_relay_monitor(
+    client: Client,
+    activity: RelayActivity,
+    event_tx: mpsc::UnboundedSender<AppEvent>,
+) {
+    tokio::spawn(async move {
+        let mut last_relays: Vec<RelayInfo> = Vec::new();
+        let mut last_status: Option<ConnectionStatus> = None;
+        let mut interval = tokio::time::interval(RELAY_POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            let relays = snapshot(&client, &activity).await;
+            let status = overall_status(&relays);
+
+            if relays != last_relays {
+                if event_tx
+                    .send(AppEvent::RelayStatusUpdated {
+                        relays: relays.clone(),
+                    })
+                    .is_err()
+                {
+                    break;
+                }
+                last_relays = relays;
+            }
+            if last_status.as_ref() != Some(&status) {
+                log::info!("Relay connectivity: {status:?}");
+                if event_tx
+                    .send(AppEvent::ConnectionStatusChanged(status.clone()))
+                    .is_err()
+                {
+                    break;
+                }
+                last_status = Some(status);
+            }
+        }
+    });
+}
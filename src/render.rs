@@ -1,18 +1,29 @@
 use nostr_sdk::prelude::*;
 use nrc::app::App;
-use nrc::ui_state::{GroupSummary, Message, Modal, OnboardingMode, OpsItem, Page};
+use nrc::app::EXPORT_CLEAR_AFTER;
+use nrc::delivery::DeliveryStatus;
+use nrc::events::ConnectionStatus;
+use nrc::key_storage::AccountInfo;
+use nrc::relay_status::connected_count;
+use nrc::replies::quote_text;
+use nrc::ui_state::{
+    Contact, ExportStage, GroupSummary, Message, Modal, OnboardingMode, OpsItem, Page,
+    PasswordStep, RelayInfo, ReplyTo,
+};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
     Frame,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub fn render(f: &mut Frame, app: &App) {
     match &app.current_page {
-        Page::Onboarding { input, mode, error } => render_onboarding(f, input, mode, error),
+        Page::Onboarding { input, mode, error } => {
+            render_onboarding(f, input, mode, error, &app.accounts, app.is_locked())
+        }
         Page::Initializing { message, progress } => render_initializing(f, message, *progress),
         Page::Chat {
             groups,
@@ -21,11 +32,13 @@ pub fn render(f: &mut Frame, app: &App) {
             messages,
             input,
             scroll_offset,
+            selected_message,
             ..
         } => {
             // Snapshot my pubkey and known profiles (non-blocking best-effort)
             let profiles_snapshot: Option<HashMap<PublicKey, Metadata>> =
                 app.profiles.try_snapshot();
+            let verified = app.profiles.try_verified().unwrap_or_default();
             render_chat(
                 f,
                 groups,
@@ -34,13 +47,59 @@ pub fn render(f: &mut Frame, app: &App) {
                 messages,
                 input,
                 *scroll_offset,
+                selected_message.as_ref(),
                 &app.flash,
                 &app.error,
                 profiles_snapshot,
+                &app.petnames,
+                &verified,
+                &app.relays,
+                app.queued_sends,
             )
         }
         Page::Help { selected_section } => render_help(f, *selected_section),
-        Page::OpsDashboard { items, selected } => render_ops_dashboard(f, items, *selected),
+        Page::OpsDashboard {
+            items,
+            selected,
+            show_detail,
+        } => render_ops_dashboard(f, items, *selected, *show_detail, &app.flash, &app.error),
+        Page::GroupInfo {
+            name,
+            description,
+            epoch,
+            admins,
+            members,
+            last_key_rotation,
+            messages_since_rotation,
+            ..
+        } => render_group_info(
+            f,
+            name,
+            description,
+            *epoch,
+            admins,
+            members,
+            *last_key_rotation,
+            *messages_since_rotation,
+        ),
+        Page::Relays { relays } => render_relays(f, relays),
+        Page::Contacts { contacts, selected } => {
+            render_contacts(f, contacts, *selected, &app.flash, &app.error)
+        }
+        Page::Accounts {
+            accounts,
+            current,
+            selected,
+        } => render_accounts(f, accounts, current, *selected),
+        Page::ChangePassword {
+            step, input, error, ..
+        } => render_change_password(f, *step, input, error),
+        Page::ExportKey {
+            stage,
+            input,
+            include_nsec,
+            error,
+        } => render_export_key(f, stage, input, *include_nsec, error, &app.flash),
     }
 
     if let Some(modal) = &app.modal {
@@ -48,7 +107,14 @@ pub fn render(f: &mut Frame, app: &App) {
     }
 }
 
-fn render_onboarding(f: &mut Frame, input: &str, mode: &OnboardingMode, error: &Option<String>) {
+fn render_onboarding(
+    f: &mut Frame,
+    input: &str,
+    mode: &OnboardingMode,
+    error: &Option<String>,
+    accounts: &[AccountInfo],
+    locked: bool,
+) {
     let size = f.area();
 
     let chunks = Layout::default()
@@ -73,6 +139,7 @@ fn render_onboarding(f: &mut Frame, input: &str, mode: &OnboardingMode, error: &
             Line::from("Choose an option:"),
             Line::from("1. Generate new keys"),
             Line::from("2. Import existing keys"),
+            Line::from("3. Log in with a bunker (NIP-46)"),
             Line::from(""),
             Line::from(format!("Your choice: {input}")),
         ]),
@@ -101,16 +168,44 @@ fn render_onboarding(f: &mut Frame, input: &str, mode: &OnboardingMode, error: &
             Line::from(""),
             Line::from("Press Enter to continue, Esc to go back"),
         ]),
+        OnboardingMode::EnterBunkerUri => Text::from(vec![
+            Line::from("Paste the bunker:// URI from your remote signer:"),
+            Line::from("(your key stays in the bunker; nrc asks it to sign)"),
+            Line::from(""),
+            Line::from(format!("URI: {}", "*".repeat(input.len()))),
+            Line::from(""),
+            Line::from("Press Enter to connect"),
+        ]),
         OnboardingMode::EnterPassword => {
             let masked = "*".repeat(input.len());
             Text::from(vec![
-                Line::from("Enter your password to unlock keys:"),
+                Line::from(if locked {
+                    "Locked. Enter your password to continue:"
+                } else {
+                    "Enter your password to unlock keys:"
+                }),
                 Line::from(""),
                 Line::from(format!("Password: {masked}")),
                 Line::from(""),
                 Line::from("Press Enter to continue"),
             ])
         }
+        OnboardingMode::SelectAccount => {
+            let mut lines = vec![Line::from("Choose an account to unlock:"), Line::from("")];
+            lines.extend(
+                accounts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, a)| Line::from(format!("{}. {}", i + 1, a.npub))),
+            );
+            lines.push(Line::from(format!(
+                "{}. Add an account",
+                accounts.len() + 1
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(format!("Your choice: {input}")));
+            Text::from(lines)
+        }
     };
 
     let content_block = Paragraph::new(content).block(Block::default().borders(Borders::ALL));
@@ -150,9 +245,14 @@ pub fn render_chat(
     messages: &[Message],
     input: &str,
     scroll_offset: usize,
+    selected_message: Option<&EventId>,
     flash: &Option<(String, std::time::Instant)>,
     error: &Option<String>,
     profiles: Option<HashMap<PublicKey, Metadata>>,
+    petnames: &HashMap<PublicKey, String>,
+    verified: &HashSet<PublicKey>,
+    relays: &[RelayInfo],
+    queued_sends: usize,
 ) {
     let size = f.area();
 
@@ -178,10 +278,17 @@ pub fn render_chat(
             .map(|(i, group)| {
                 let style = if i == selected_group_index {
                     Style::default().bg(Color::Blue).fg(Color::White)
+                } else if group.archived {
+                    Style::default().fg(Color::DarkGray)
                 } else {
                     Style::default()
                 };
-                ListItem::new(group.name.clone()).style(style)
+                let name = if group.archived {
+                    format!("{} (left)", group.name)
+                } else {
+                    group.name.clone()
+                };
+                ListItem::new(name).style(style)
             })
             .collect();
         let groups_list =
@@ -265,6 +372,13 @@ pub fn render_chat(
                 Span::styled("/d", Style::default().fg(Color::Yellow)),
                 Span::raw(")  Start a DM"),
             ]),
+            Line::from(vec![
+                Span::raw("  - "),
+                Span::styled("/group", Style::default().fg(Color::Yellow)),
+                Span::raw(" <name> <npub>...  (or "),
+                Span::styled("/g", Style::default().fg(Color::Yellow)),
+                Span::raw(")  Start a group"),
+            ]),
             Line::from(vec![
                 Span::raw("  - "),
                 Span::styled("/npub", Style::default().fg(Color::Yellow)),
@@ -282,9 +396,37 @@ pub fn render_chat(
             .skip(scroll_offset)
             .take(chat_chunks[messages_area_index].height as usize - 2);
         let message_lines: Vec<Line> = visible_messages
-            .map(|msg| {
-                let sender_name = resolve_display_name(&msg.sender, profiles.as_ref());
-                Line::from(format!("{}: {}", sender_name, msg.content))
+            .flat_map(|msg| {
+                if msg.system {
+                    return vec![Line::from(Span::styled(
+                        format!("* {}", msg.content),
+                        Style::default().fg(Color::DarkGray),
+                    ))];
+                }
+                let mut lines = Vec::new();
+                if let Some(reply) = &msg.reply_to {
+                    lines.push(quote_line(reply, petnames, profiles.as_ref()));
+                }
+                let mut sender_name =
+                    resolve_display_name(&msg.sender, petnames, profiles.as_ref());
+                // The profile's NIP-05 domain vouches for this pubkey
+                if verified.contains(&msg.sender) {
+                    sender_name.push_str(" ✔");
+                }
+                let text = format!("{}: {}", sender_name, msg.content);
+                let line = match &msg.delivery {
+                    Some(delivery) => Line::from(vec![
+                        Span::raw(format!("{text} ")),
+                        delivery_glyph(delivery.status),
+                    ]),
+                    None => Line::from(text),
+                };
+                if msg.id.is_some() && msg.id.as_ref() == selected_message {
+                    lines.push(line.style(Style::default().bg(Color::Blue).fg(Color::White)));
+                } else {
+                    lines.push(line);
+                }
+                lines
             })
             .collect();
 
@@ -374,15 +516,342 @@ pub fn render_chat(
         }
     }
 
-    // Render input area with "INPUT" label
+    // Render input area with "INPUT" label, or who we're replying to
     let input_index = chat_chunks.len() - 1;
-    let input_widget = Paragraph::new(input)
-        .style(Style::default())
-        .block(Block::default().borders(Borders::ALL).title("INPUT"));
+    let input_title = selected_message
+        .and_then(|id| messages.iter().find(|m| m.id.as_ref() == Some(id)))
+        .map(|m| {
+            let name = resolve_display_name(&m.sender, petnames, profiles.as_ref());
+            format!("REPLY TO {name} (Esc cancels)")
+        })
+        .unwrap_or_else(|| "INPUT".to_string());
+    let input_widget = Paragraph::new(input).style(Style::default()).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(input_title)
+            .title_top(relay_status_line(relays, queued_sends).right_aligned()),
+    );
     f.render_widget(input_widget, chat_chunks[input_index]);
 }
 
-fn resolve_display_name(pk: &PublicKey, profiles: Option<&HashMap<PublicKey, Metadata>>) -> String {
+/// One-line quote of the message a reply answers
+fn quote_line(
+    reply: &ReplyTo,
+    petnames: &HashMap<PublicKey, String>,
+    profiles: Option<&HashMap<PublicKey, Metadata>>,
+) -> Line<'static> {
+    const QUOTE_LEN: usize = 60;
+    let text = quote_text(
+        reply,
+        |pk| resolve_display_name(pk, petnames, profiles),
+        QUOTE_LEN,
+    );
+    Line::from(Span::styled(
+        format!("  ↳ {text}"),
+        Style::default().fg(Color::DarkGray),
+    ))
+}
+
+/// Status bar segment: connected/total relays, plus messages waiting for one
+fn relay_status_line(relays: &[RelayInfo], queued_sends: usize) -> Line<'static> {
+    let connected = connected_count(relays);
+    let color = if relays.is_empty() {
+        Color::DarkGray
+    } else if connected == relays.len() {
+        Color::Green
+    } else if connected > 0 {
+        Color::Yellow
+    } else {
+        Color::Red
+    };
+    let mut spans = vec![Span::styled(
+        format!(" ● {connected}/{} relays ", relays.len()),
+        Style::default().fg(color),
+    )];
+    if queued_sends > 0 {
+        spans.push(Span::styled(
+            format!("{queued_sends} queued "),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    Line::from(spans)
+}
+
+fn render_relays(f: &mut Frame, relays: &[RelayInfo]) {
+    use ratatui::widgets::{Row, Table};
+
+    let size = f.area();
+    let header = ["Relay", "State", "Latency", "Recv", "Sent", "Last error"];
+    let rows = relays.iter().map(|r| {
+        let (state, color) = match &r.state {
+            ConnectionStatus::Connected => ("connected".to_string(), Color::Green),
+            ConnectionStatus::Connecting => ("connecting".to_string(), Color::Yellow),
+            ConnectionStatus::Disconnected => ("disconnected".to_string(), Color::Red),
+            ConnectionStatus::Error(e) => (format!("error: {e}"), Color::Red),
+        };
+        let latency = r
+            .latency
+            .map(|d| format!("{} ms", d.as_millis()))
+            .unwrap_or_else(|| "-".to_string());
+        Row::new(vec![
+            r.url.clone(),
+            state,
+            latency,
+            r.events_received.to_string(),
+            r.events_sent.to_string(),
+            r.last_error.clone().unwrap_or_default(),
+        ])
+        .style(Style::default().fg(color))
+    });
+
+    let connected = connected_count(relays);
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(30),
+            Constraint::Length(14),
+            Constraint::Length(9),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Min(10),
+        ],
+    )
+    .header(Row::new(header).style(Style::default().fg(Color::Yellow)))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Relays ({connected}/{} connected)", relays.len())),
+    );
+
+    f.render_widget(table, size);
+}
+
+fn render_contacts(
+    f: &mut Frame,
+    contacts: &[Contact],
+    selected: usize,
+    flash: &Option<(String, std::time::Instant)>,
+    error: &Option<String>,
+) {
+    let size = f.area();
+    let items: Vec<ListItem> = contacts
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let npub = nrc::pubkey_to_bech32_safe(&c.public_key);
+            let mut spans = match (&c.petname, &c.display_name) {
+                (Some(petname), Some(name)) => vec![
+                    Span::raw(petname.clone()),
+                    Span::styled(format!(" ({name})"), Style::default().fg(Color::DarkGray)),
+                ],
+                (Some(name), None) | (None, Some(name)) => vec![Span::raw(name.clone())],
+                (None, None) => vec![Span::raw(npub.clone())],
+            };
+            if !c.followed {
+                spans.push(Span::styled(
+                    "  not followed",
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            let style = if i == selected {
+                Style::default().bg(Color::Blue).fg(Color::White)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Line::from(spans)).style(style)
+        })
+        .collect();
+
+    let footer = if let Some(err) = error {
+        Line::from(Span::styled(
+            format!(" {err} "),
+            Style::default().fg(Color::Red),
+        ))
+    } else if let Some((msg, _)) = flash {
+        Line::from(Span::styled(
+            format!(" {msg} "),
+            Style::default().fg(Color::Green),
+        ))
+    } else {
+        Line::from(" ↑/↓ select · Enter start DM · Esc back ")
+    };
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Contacts ({})", contacts.len()))
+            .title_bottom(footer),
+    );
+    f.render_widget(list, size);
+}
+
+fn render_change_password(f: &mut Frame, step: PasswordStep, input: &str, error: &Option<String>) {
+    let size = f.area();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([
+            Constraint::Min(5),    // Prompt
+            Constraint::Length(1), // Error
+        ])
+        .split(size);
+
+    let masked = "*".repeat(input.len());
+    let prompt = match step {
+        PasswordStep::Old => "Current password:",
+        PasswordStep::New => "New password (minimum 8 characters):",
+        PasswordStep::Confirm => "Confirm the new password:",
+        PasswordStep::Saving => "Re-encrypting your keys...",
+    };
+    let mut lines = vec![Line::from(prompt), Line::from("")];
+    if step != PasswordStep::Saving {
+        lines.push(Line::from(format!("Password: {masked}")));
+        lines.push(Line::from(""));
+        lines.push(Line::from("Press Enter to continue, Esc to cancel"));
+    }
+    let content = Paragraph::new(Text::from(lines)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Change password"),
+    );
+    f.render_widget(content, chunks[0]);
+
+    if let Some(error_msg) = error {
+        let error_widget =
+            Paragraph::new(error_msg.as_str()).style(Style::default().fg(Color::Red));
+        f.render_widget(error_widget, chunks[1]);
+    }
+}
+
+fn render_export_key(
+    f: &mut Frame,
+    stage: &ExportStage,
+    input: &str,
+    include_nsec: bool,
+    error: &Option<String>,
+    flash: &Option<(String, std::time::Instant)>,
+) {
+    let size = f.area();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([
+            Constraint::Min(5),    // Prompt or key
+            Constraint::Length(1), // Error or flash
+        ])
+        .split(size);
+
+    let lines = match stage {
+        ExportStage::Password | ExportStage::Confirm => {
+            let what = if include_nsec {
+                "your encrypted key and raw nsec"
+            } else {
+                "your encrypted key (ncryptsec)"
+            };
+            vec![
+                Line::from(format!("Enter your password to export {what}:")),
+                Line::from(""),
+                Line::from(format!("Password: {}", "*".repeat(input.len()))),
+                Line::from(""),
+                Line::from("Press Enter to continue, Esc to cancel"),
+            ]
+        }
+        ExportStage::Reveal { ncryptsec, nsec } => {
+            let mut lines = vec![
+                Line::from("Encrypted key (NIP-49, needs your password to use):"),
+                Line::from(Span::styled(
+                    ncryptsec.clone(),
+                    Style::default().fg(Color::Yellow),
+                )),
+                Line::from(""),
+            ];
+            if let Some(nsec) = nsec {
+                lines.push(Line::from("Secret key. Never share it:"));
+                lines.push(Line::from(Span::styled(
+                    nsec.clone(),
+                    Style::default().fg(Color::Red),
+                )));
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::from(format!(
+                "This screen and the clipboard clear after {} seconds.",
+                EXPORT_CLEAR_AFTER.as_secs()
+            )));
+            lines.push(Line::from(if nsec.is_some() {
+                "c: copy ncryptsec · n: copy nsec · Esc: close"
+            } else {
+                "c: copy ncryptsec · Esc: close"
+            }));
+            lines
+        }
+    };
+    let content = Paragraph::new(Text::from(lines))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title("Export key"));
+    f.render_widget(content, chunks[0]);
+
+    if let Some(error_msg) = error {
+        let error_widget =
+            Paragraph::new(error_msg.as_str()).style(Style::default().fg(Color::Red));
+        f.render_widget(error_widget, chunks[1]);
+    } else if let Some((msg, _)) = flash {
+        let flash_widget = Paragraph::new(msg.as_str()).style(Style::default().fg(Color::Green));
+        f.render_widget(flash_widget, chunks[1]);
+    }
+}
+
+fn render_accounts(f: &mut Frame, accounts: &[AccountInfo], current: &str, selected: usize) {
+    let size = f.area();
+    let items: Vec<ListItem> = accounts
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            let mut spans = vec![Span::raw(a.npub.clone())];
+            if a.npub == current {
+                spans.push(Span::styled(
+                    "  (current)",
+                    Style::default().fg(Color::Green),
+                ));
+            }
+            let style = if i == selected {
+                Style::default().bg(Color::Blue).fg(Color::White)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Line::from(spans)).style(style)
+        })
+        .collect();
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Accounts")
+            .title_bottom(Line::from(
+                " ↑/↓ select · Enter switch (asks for its password) · n new account · Esc back ",
+            )),
+    );
+    f.render_widget(list, size);
+}
+
+/// Clock while queued, one check once sent, two once a relay acked, red cross if it failed
+fn delivery_glyph(status: DeliveryStatus) -> Span<'static> {
+    match status {
+        DeliveryStatus::Queued => Span::styled("◷", Style::default().fg(Color::DarkGray)),
+        DeliveryStatus::Sent => Span::styled("✓", Style::default().fg(Color::DarkGray)),
+        DeliveryStatus::Acked => Span::styled("✓✓", Style::default().fg(Color::Green)),
+        DeliveryStatus::Failed => Span::styled("✗", Style::default().fg(Color::Red)),
+    }
+}
+
+fn resolve_display_name(
+    pk: &PublicKey,
+    petnames: &HashMap<PublicKey, String>,
+    profiles: Option<&HashMap<PublicKey, Metadata>>,
+) -> String {
+    // Our own name for them beats whatever they call themselves
+    if let Some(name) = petnames.get(pk) {
+        return name.clone();
+    }
     if let Some(profiles) = profiles {
         if let Some(meta) = profiles.get(pk) {
             if let Some(name) = meta.display_name.clone().filter(|s| !s.is_empty()) {
@@ -409,9 +878,29 @@ fn render_help(f: &mut Frame, _selected_section: usize) {
         Line::from(""),
         Line::from("Navigation:"),
         Line::from("  ↑/↓: Navigate lists"),
+        Line::from("  Alt+↑/↓: Pick a chat message; Enter replies to it"),
         Line::from("  Enter: Select/Join"),
         Line::from("  Esc: Go back"),
         Line::from(""),
+        Line::from("Commands:"),
+        Line::from("  /dm <npub|name@domain>: Start a DM"),
+        Line::from("  /contacts: People you follow; Enter starts a DM"),
+        Line::from("  /accounts: Switch to another account or add one"),
+        Line::from("  /password: Change the password protecting your keys"),
+        Line::from("  /export [nsec]: Show your ncryptsec (and nsec) for backup"),
+        Line::from("  /lock: Lock now; the password unlocks again"),
+        Line::from("  /nick <npub|name@domain> [name]: Set (or clear) a local nickname"),
+        Line::from("  /group <name> <npub>...: Start a group; run again to resume a failed one"),
+        Line::from("  /invite <npub>: Add someone to this group (admins)"),
+        Line::from("  /kick <npub>: Remove someone from this group (admins)"),
+        Line::from("  /leave: Leave this group"),
+        Line::from("  /commit: Apply pending member proposals (admins)"),
+        Line::from("  /retry: Resend messages that failed to send"),
+        Line::from("  /relays: Relay connection status"),
+        Line::from("  /relays add|remove <url>: Change this group's relays (admins)"),
+        Line::from("  /info: Group details and last key rotation"),
+        Line::from("  /npub: Copy your npub"),
+        Line::from(""),
         Line::from("Shortcuts:"),
         Line::from("  Ctrl+N: New group"),
         Line::from("  Ctrl+S: Settings"),
@@ -425,11 +914,80 @@ fn render_help(f: &mut Frame, _selected_section: usize) {
     f.render_widget(paragraph, size);
 }
 
-fn render_ops_dashboard(f: &mut Frame, items: &[OpsItem], selected: usize) {
+#[allow(clippy::too_many_arguments)]
+fn render_group_info(
+    f: &mut Frame,
+    name: &str,
+    description: &str,
+    epoch: u64,
+    admins: &[String],
+    members: &[String],
+    last_key_rotation: Option<i64>,
+    messages_since_rotation: u64,
+) {
+    let size = f.area();
+
+    let rotated = last_key_rotation
+        .and_then(|ts| chrono::DateTime::<chrono::Utc>::from_timestamp(ts, 0))
+        .map(|dt| {
+            dt.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| "never".to_string());
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            name.to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(description.to_string()),
+        Line::from(""),
+        Line::from(format!("Epoch: {epoch}")),
+        Line::from(format!("Last key rotation: {rotated}")),
+        Line::from(format!(
+            "Messages since rotation: {messages_since_rotation}"
+        )),
+        Line::from(""),
+        Line::from(format!("Admins ({}):", admins.len())),
+    ];
+    lines.extend(admins.iter().map(|a| Line::from(format!("  {a}"))));
+    lines.push(Line::from(""));
+    lines.push(Line::from(format!("Members ({}):", members.len())));
+    lines.extend(members.iter().map(|m| Line::from(format!("  {m}"))));
+    lines.push(Line::from(""));
+    lines.push(Line::from("Esc: Go back"));
+
+    let paragraph = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("Group Info"));
+    f.render_widget(paragraph, size);
+}
+
+fn render_ops_dashboard(
+    f: &mut Frame,
+    items: &[OpsItem],
+    selected: usize,
+    show_detail: bool,
+    flash: &Option<(String, std::time::Instant)>,
+    error: &Option<String>,
+) {
     use ratatui::widgets::{Row, Table};
 
     let size = f.area();
-    let header = ["ID", "Kind", "Status", "Updated", "Error"];
+    let detail = items.get(selected).filter(|_| show_detail);
+    let (table_area, detail_area) = if detail.is_some() {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(size);
+        (chunks[0], Some(chunks[1]))
+    } else {
+        (size, None)
+    };
+    let now = chrono::Utc::now().timestamp();
+    let header = [
+        "ID", "Kind", "Status", "Tries", "Retry in", "Updated", "Error",
+    ];
     let rows = items.iter().enumerate().map(|(i, it)| {
         let mut id = it.id.clone();
         if id.len() > 8 {
@@ -438,6 +996,11 @@ fn render_ops_dashboard(f: &mut Frame, items: &[OpsItem], selected: usize) {
         let updated = chrono::DateTime::<chrono::Utc>::from_timestamp(it.updated_at, 0)
             .map(|dt| dt.format("%H:%M:%S").to_string())
             .unwrap_or_else(|| it.updated_at.to_string());
+        let retry_in = it
+            .next_attempt_at
+            .filter(|_| it.status == "Error")
+            .map(|at| format!("{}s", (at - now).max(0)))
+            .unwrap_or_default();
         let err = it
             .last_error
             .as_ref()
@@ -454,14 +1017,54 @@ fn render_ops_dashboard(f: &mut Frame, items: &[OpsItem], selected: usize) {
         } else {
             Style::default()
         };
-        Row::new(vec![id, it.kind.clone(), it.status.clone(), updated, err]).style(style)
+        Row::new(vec![
+            id,
+            it.kind.clone(),
+            it.status.clone(),
+            it.attempts.to_string(),
+            retry_in,
+            updated,
+            err,
+        ])
+        .style(style)
     });
 
-    let table = Table::new(rows, [20, 18, 12, 10, 30])
+    // Outcome of the last retry/cancel, otherwise the key hints
+    let footer = if let Some(err) = error {
+        Line::from(Span::styled(
+            format!(" {err} "),
+            Style::default().fg(Color::Red),
+        ))
+    } else if let Some((msg, _)) = flash {
+        Line::from(Span::styled(
+            format!(" {msg} "),
+            Style::default().fg(Color::Green),
+        ))
+    } else {
+        Line::from(" ↑/↓ select · Enter details · r retry · c cancel · Esc back ")
+    };
+
+    let table = Table::new(rows, [20, 18, 12, 6, 9, 10, 30])
         .header(Row::new(header).style(Style::default().fg(Color::Yellow)))
-        .block(Block::default().borders(Borders::ALL).title("Operations"));
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Operations")
+                .title_bottom(footer),
+        );
 
-    f.render_widget(table, size);
+    f.render_widget(table, table_area);
+
+    if let (Some(item), Some(area)) = (detail, detail_area) {
+        let paragraph = Paragraph::new(item.detail.as_str())
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("{} {}", item.kind, item.id)),
+            );
+        f.render_widget(paragraph, area);
+    }
 }
 
 fn render_modal(f: &mut Frame, modal: &Modal) {
@@ -0,0 +1,74 @@
+use nostr_sdk::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::ui_state::{Message, QuotedMessage, ReplyTo};
+
+/// Shown in place of a quote when we don't have the replied-to message
+pub const MISSING_PARENT: &str = "[original message not available]";
+
+/// A kind 9 chat rumor, tagged `e` with the message it replies to. The id is set
+/// up front so the chat view can refer to the message before MLS stores it.
+pub fn chat_rumor(author: PublicKey, content: &str, reply_to: Option<EventId>) -> UnsignedEvent {
+    let mut builder = EventBuilder::new(Kind::Custom(9), content);
+    if let Some(parent) = reply_to {
+        builder = builder.tag(Tag::event(parent));
+    }
+    let mut rumor = builder.build(author);
+    rumor.ensure_id();
+    rumor
+}
+
+/// The message a rumor replies to: its first `e` tag
+pub fn reply_target(tags: &Tags) -> Option<EventId> {
+    tags.event_ids().next().copied()
+}
+
+/// Quote each reply's parent if it's among `messages` (replies that arrived before
+/// their parent get it here too). Replies left without one show `MISSING_PARENT`.
+pub fn quote_parents(messages: &mut [Message]) {
+    let wanted: HashSet<EventId> = messages
+        .iter()
+        .filter_map(|m| m.reply_to.as_ref())
+        .filter(|r| r.parent.is_none())
+        .map(|r| r.id)
+        .collect();
+    if wanted.is_empty() {
+        return;
+    }
+    let quotes: HashMap<EventId, QuotedMessage> = messages
+        .iter()
+        .filter_map(|m| {
+            let id = m.id.filter(|id| wanted.contains(id))?;
+            Some((
+                id,
+                QuotedMessage {
+                    sender: m.sender,
+                    content: m.content.clone(),
+                },
+            ))
+        })
+        .collect();
+    for reply in messages.iter_mut().filter_map(|m| m.reply_to.as_mut()) {
+        if reply.parent.is_none() {
+            reply.parent = quotes.get(&reply.id).cloned();
+        }
+    }
+}
+
+/// One line quoting a reply's parent: `name: first line`, cut at `max_chars`
+pub fn quote_text(
+    reply: &ReplyTo,
+    sender_name: impl Fn(&PublicKey) -> String,
+    max_chars: usize,
+) -> String {
+    let Some(parent) = &reply.parent else {
+        return MISSING_PARENT.to_string();
+    };
+    let mut lines = parent.content.lines();
+    let first_line = lines.next().unwrap_or_default();
+    let mut quoted: String = first_line.chars().take(max_chars).collect();
+    if first_line.chars().count() > max_chars || lines.next().is_some() {
+        quoted.push('…');
+    }
+    format!("{}: {quoted}", sender_name(&parent.sender))
+}
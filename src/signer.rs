@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use nostr_connect::client::NostrConnect;
+use nostr_sdk::prelude::*;
+
+/// How long we wait for a bunker to answer (it may ask its user to approve first)
+pub const BUNKER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Signs our Nostr events (kind 0, 443, 10051, gift wraps, AUTH): with keys we hold,
+/// or through a NIP-46 bunker that holds them. MLS leaf keys are always local.
+#[derive(Clone, Debug)]
+pub enum AccountSigner {
+    Local(Keys),
+    Bunker {
+        public_key: PublicKey,
+        // Boxed (like `uri`): a NIP-46 client is much larger than `Keys`
+        signer: Box<NostrConnect>,
+        // Our NIP-46 client key; the bunker only answers to it
+        app_keys: Keys,
+        uri: Box<NostrConnectURI>,
+    },
+}
+
+impl From<Keys> for AccountSigner {
+    fn from(keys: Keys) -> Self {
+        Self::Local(keys)
+    }
+}
+
+impl AccountSigner {
+    /// Connect to the bunker in `uri` with our client key. Resolves once the bunker
+    /// accepted us and told us whose key it holds.
+    pub async fn connect_bunker(uri: NostrConnectURI, app_keys: Keys) -> Result<Self> {
+        if !matches!(uri, NostrConnectURI::Bunker { .. }) {
+            return Err(anyhow!("Not a bunker:// URI"));
+        }
+        let signer = Box::new(NostrConnect::new(
+            uri.clone(),
+            app_keys.clone(),
+            BUNKER_TIMEOUT,
+            None,
+        )?);
+        let public_key = signer
+            .get_public_key()
+            .await
+            .map_err(|e| anyhow!("Bunker didn't answer: {e}"))?;
+        Ok(Self::Bunker {
+            public_key,
+            signer,
+            app_keys,
+            uri: Box::new(uri),
+        })
+    }
+
+    /// A bunker account we connected before; the bunker already knows our client key,
+    /// so the connection opens on first use
+    pub fn bunker(public_key: PublicKey, uri: NostrConnectURI, app_keys: Keys) -> Result<Self> {
+        let signer = Box::new(NostrConnect::new(
+            uri.clone(),
+            app_keys.clone(),
+            BUNKER_TIMEOUT,
+            None,
+        )?);
+        Ok(Self::Bunker {
+            public_key,
+            signer,
+            app_keys,
+            uri: Box::new(uri),
+        })
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        match self {
+            Self::Local(keys) => keys.public_key(),
+            Self::Bunker { public_key, .. } => *public_key,
+        }
+    }
+
+    /// Our secret key, unless a bunker holds it
+    pub fn keys(&self) -> Option<&Keys> {
+        match self {
+            Self::Local(keys) => Some(keys),
+            Self::Bunker { .. } => None,
+        }
+    }
+
+    pub fn is_bunker(&self) -> bool {
+        matches!(self, Self::Bunker { .. })
+    }
+
+    pub async fn sign(&self, unsigned: UnsignedEvent) -> Result<Event> {
+        let event = match self {
+            Self::Local(keys) => unsigned.sign(keys).await?,
+            Self::Bunker { signer, .. } => unsigned.sign(signer.as_ref()).await?,
+        };
+        Ok(event)
+    }
+
+    /// NIP-59 gift wrap; the seal inside is signed (and encrypted) by us
+    pub async fn gift_wrap(&self, receiver: &PublicKey, rumor: UnsignedEvent) -> Result<Event> {
+        let event = match self {
+            Self::Local(keys) => EventBuilder::gift_wrap(keys, receiver, rumor, None).await?,
+            Self::Bunker { signer, .. } => {
+                EventBuilder::gift_wrap(signer.as_ref(), receiver, rumor, None).await?
+            }
+        };
+        Ok(event)
+    }
+
+    pub async fn extract_rumor(&self, gift_wrap: &Event) -> Result<UnwrappedGift> {
+        let unwrapped = match self {
+            Self::Local(keys) => nip59::extract_rumor(keys, gift_wrap).await?,
+            Self::Bunker { signer, .. } => nip59::extract_rumor(signer.as_ref(), gift_wrap).await?,
+        };
+        Ok(unwrapped)
+    }
+
+    /// Let the client sign with us too
+    pub async fn install(&self, client: &Client) {
+        match self {
+            Self::Local(keys) => client.set_signer(keys.clone()).await,
+            Self::Bunker { signer, .. } => client.set_signer(signer.as_ref().clone()).await,
+        }
+    }
+}
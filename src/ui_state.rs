@@ -1,6 +1,11 @@
 use nostr_sdk::prelude::*;
 use nrc_mls_storage::groups::types as group_types;
 use openmls::group::GroupId;
+use std::time::Duration;
+
+use crate::delivery::Delivery;
+use crate::events::ConnectionStatus;
+use crate::key_storage::AccountInfo;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Page {
@@ -25,6 +30,8 @@ pub enum Page {
         input: String,
         scroll_offset: usize,
         typing_members: Vec<PublicKey>,
+        // Message picked with Alt+Up/Down; Enter replies to it
+        selected_message: Option<EventId>,
     },
 
     Help {
@@ -34,6 +41,53 @@ pub enum Page {
     OpsDashboard {
         items: Vec<OpsItem>,
         selected: usize,
+        // Detail pane with the selected op's full payload
+        show_detail: bool,
+    },
+
+    GroupInfo {
+        group_id: GroupId,
+        name: String,
+        description: String,
+        epoch: u64,
+        admins: Vec<String>,
+        members: Vec<String>,
+        // Unix seconds of our last merged self-update commit, if any
+        last_key_rotation: Option<i64>,
+        messages_since_rotation: u64,
+    },
+
+    Relays {
+        relays: Vec<RelayInfo>,
+    },
+
+    Contacts {
+        contacts: Vec<Contact>,
+        selected: usize,
+    },
+
+    Accounts {
+        accounts: Vec<AccountInfo>,
+        // npub of the account this session is logged in as
+        current: String,
+        selected: usize,
+    },
+
+    ChangePassword {
+        step: PasswordStep,
+        input: String,
+        // Entered in earlier steps, kept until the change is made
+        old_password: String,
+        new_password: String,
+        error: Option<String>,
+    },
+
+    ExportKey {
+        stage: ExportStage,
+        input: String,
+        // Also reveal the raw nsec (`/export nsec`)
+        include_nsec: bool,
+        error: Option<String>,
     },
 }
 
@@ -44,13 +98,36 @@ pub struct GroupSummary {
     pub member_count: usize,
     pub last_message: Option<Message>,
     pub unread_count: usize,
+    // We left (or were removed from) this group
+    pub archived: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Message {
+    // Inner rumor id; None for local notices
+    pub id: Option<EventId>,
     pub content: String,
     pub sender: PublicKey,
     pub timestamp: Timestamp,
+    // Local notice (member joined, group renamed, ...) rather than a chat message
+    pub system: bool,
+    // Delivery status of a message we sent from this device
+    pub delivery: Option<Delivery>,
+    // The message this one answers (its `e` tag)
+    pub reply_to: Option<ReplyTo>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplyTo {
+    pub id: EventId,
+    // None until we have the parent locally
+    pub parent: Option<QuotedMessage>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuotedMessage {
+    pub sender: PublicKey,
+    pub content: String,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -63,8 +140,13 @@ pub struct Member {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Contact {
     pub public_key: PublicKey,
+    // Kind 0 name, if we have their profile
     pub display_name: Option<String>,
     pub metadata: Option<Metadata>,
+    // Our local name for them; shown instead of the Kind 0 name
+    pub petname: Option<String>,
+    // In our kind 3 follow list (petname-only contacts are not)
+    pub followed: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -87,7 +169,33 @@ pub enum OnboardingMode {
     EnterDisplayName,
     CreatePassword,
     ImportExisting,
+    // Log in with a NIP-46 bunker:// URI
+    EnterBunkerUri,
     EnterPassword,
+    // Unlock screen with several accounts: pick one by number
+    SelectAccount,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PasswordStep {
+    Old,
+    New,
+    Confirm,
+    // Re-encrypting in the background
+    Saving,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExportStage {
+    // Re-enter the password first
+    Password,
+    // Waiting on the confirmation modal
+    Confirm,
+    // Shown until the auto-clear timeout
+    Reveal {
+        ncryptsec: String,
+        nsec: Option<String>,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -107,8 +215,8 @@ pub enum Modal {
 #[derive(Clone, Debug, PartialEq)]
 pub enum ModalAction {
     LeaveGroup(GroupId),
-    DeleteMessage(usize),
-    ClearChat(GroupId),
+    CancelOperation(String),
+    RevealKey,
 }
 
 pub enum PageType {
@@ -117,6 +225,12 @@ pub enum PageType {
     Chat(Option<GroupId>),
     Help,
     OpsDashboard,
+    GroupInfo(GroupId),
+    Relays,
+    Contacts,
+    Accounts,
+    ChangePassword,
+    ExportKey(bool),
 }
 
 impl Page {
@@ -127,6 +241,12 @@ impl Page {
             Page::Chat { group_id, .. } => PageType::Chat(Some(group_id.clone())),
             Page::Help { .. } => PageType::Help,
             Page::OpsDashboard { .. } => PageType::OpsDashboard,
+            Page::GroupInfo { group_id, .. } => PageType::GroupInfo(group_id.clone()),
+            Page::Relays { .. } => PageType::Relays,
+            Page::Contacts { .. } => PageType::Contacts,
+            Page::Accounts { .. } => PageType::Accounts,
+            Page::ChangePassword { .. } => PageType::ChangePassword,
+            Page::ExportKey { include_nsec, .. } => PageType::ExportKey(*include_nsec),
         }
     }
 }
@@ -138,4 +258,20 @@ pub struct OpsItem {
     pub status: String,
     pub updated_at: i64,
     pub last_error: Option<String>,
+    pub attempts: u32,
+    // Unix seconds of the next automatic retry, while the op is in Error
+    pub next_attempt_at: Option<i64>,
+    // Serialized OperationKind, pretty-printed
+    pub detail: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RelayInfo {
+    pub url: String,
+    pub state: ConnectionStatus,
+    // Round trip of the last ping, if the relay answered one
+    pub latency: Option<Duration>,
+    pub last_error: Option<String>,
+    pub events_received: u64,
+    pub events_sent: u64,
 }
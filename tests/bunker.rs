@@ -0,0 +1,103 @@
+use nostr_connect::prelude::*;
+use nostr_relay_builder::prelude::*;
+use nrc::key_storage::KeyStorage;
+use nrc::signer::AccountSigner;
+use tempfile::TempDir;
+
+// NIP-46 signer stand-in that approves every request
+#[derive(Debug, Clone)]
+struct ApproveAll;
+
+impl NostrConnectSignerActions for ApproveAll {
+    fn approve(&self, _public_key: &PublicKey, _req: &NostrConnectRequest) -> bool {
+        true
+    }
+}
+
+// In-process bunker holding `user`'s key, reachable through a local relay
+async fn run_bunker(user: &Keys) -> (LocalRelay, NostrConnectURI) {
+    let relay = LocalRelay::run(RelayBuilder::default()).await.unwrap();
+    let url = RelayUrl::parse(&relay.url().to_string()).unwrap();
+    let bunker = NostrConnectRemoteSigner::new(
+        NostrConnectKeys {
+            signer: Keys::generate(),
+            user: user.clone(),
+        },
+        [url],
+        Some("s3cret".to_string()),
+        None,
+    )
+    .unwrap();
+    let uri = bunker.bunker_uri();
+    tokio::spawn(async move { bunker.serve(ApproveAll).await });
+    // It only sees requests sent after it subscribed
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    (relay, uri)
+}
+
+#[tokio::test]
+async fn bunker_signs_our_nostr_events() {
+    let user = Keys::generate();
+    let (_relay, uri) = run_bunker(&user).await;
+
+    let signer = AccountSigner::connect_bunker(uri, Keys::generate())
+        .await
+        .unwrap();
+    assert_eq!(signer.public_key(), user.public_key());
+    // No secret key on our side to export
+    assert!(signer.keys().is_none());
+
+    let profile = signer
+        .sign(EventBuilder::new(Kind::Metadata, "{\"name\":\"bunkered\"}").build(user.public_key()))
+        .await
+        .unwrap();
+    assert_eq!(profile.pubkey, user.public_key());
+    assert!(profile.verify().is_ok());
+
+    // Welcomes: the seal is signed and encrypted by the bunker
+    let alice = Keys::generate();
+    let rumor = EventBuilder::new(Kind::MlsWelcome, "welcome").build(user.public_key());
+    let wrapped = signer.gift_wrap(&alice.public_key(), rumor).await.unwrap();
+    let unwrapped = nip59::extract_rumor(&alice, &wrapped).await.unwrap();
+    assert_eq!(unwrapped.sender, user.public_key());
+    assert_eq!(unwrapped.rumor.content, "welcome");
+}
+
+#[tokio::test]
+async fn bunker_account_unlocks_with_its_password() {
+    let user = Keys::generate();
+    let (_relay, uri) = run_bunker(&user).await;
+    let signer = AccountSigner::connect_bunker(uri, Keys::generate())
+        .await
+        .unwrap();
+
+    let tmp = TempDir::new().unwrap();
+    let storage = KeyStorage::new(tmp.path());
+    storage.save_account(&signer, "bunker-password").unwrap();
+
+    let npub = user.public_key().to_bech32().unwrap();
+    let accounts = storage.list_accounts().unwrap();
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].npub, npub);
+
+    let account = storage.for_account(&npub);
+    assert!(account.is_bunker().unwrap());
+    // The URI's connect secret isn't readable without the password
+    let conn = rusqlite::Connection::open(tmp.path().join("nrc.db")).unwrap();
+    let stored: String = conn
+        .query_row("SELECT bunker_uri FROM keys", [], |row| row.get(0))
+        .unwrap();
+    assert!(!stored.contains("s3cret"));
+    assert!(!stored.starts_with("bunker://"));
+    assert!(account.load_signer("wrong-password").is_err());
+
+    // Reconnects with the stored client key
+    let unlocked = account.load_signer("bunker-password").unwrap();
+    assert!(unlocked.is_bunker());
+    assert_eq!(unlocked.public_key(), user.public_key());
+    let note = unlocked
+        .sign(EventBuilder::text_note("back again").build(user.public_key()))
+        .await
+        .unwrap();
+    assert_eq!(note.pubkey, user.public_key());
+}
@@ -0,0 +1,388 @@
+use nostr_sdk::prelude::*;
+use nrc::commit_race::{commit_order, AppliedCommit, CommitLedger, MlsSnapshots};
+use nrc::key_storage::KeyStorage;
+use nrc::ops::{pair_welcomes, GroupEvolutionStep, OperationKind};
+use nrc::ui_state::OnboardingMode;
+use nrc::{App, AppEvent, Page};
+use nrc_mls::groups::{NostrGroupConfigData, NostrGroupDataUpdate};
+use nrc_mls::NostrMls;
+use nrc_mls_sqlite_storage::NostrMlsSqliteStorage;
+use openmls::group::GroupId;
+use openmls::prelude::{MlsGroup, OpenMlsProvider};
+use rusqlite::Connection;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+async fn group_event(keys: &Keys, content: &str, created_at: u64) -> Event {
+    EventBuilder::new(Kind::MlsGroupMessage, content)
+        .custom_created_at(Timestamp::from(created_at))
+        .sign(keys)
+        .await
+        .unwrap()
+}
+
+fn applied(group_id: &GroupId, event: &Event, snapshot: &str, replay_from: usize) -> AppliedCommit {
+    AppliedCommit {
+        group_id: group_id.clone(),
+        epoch: 1,
+        event_id: event.id,
+        created_at: event.created_at,
+        snapshot: PathBuf::from(snapshot),
+        replay_from,
+        op_id: None,
+        applied_at: Instant::now(),
+    }
+}
+
+#[tokio::test]
+async fn commit_order_prefers_earliest_then_lowest_id() {
+    let keys = Keys::generate();
+    let early = group_event(&keys, "a", 100).await;
+    let late = group_event(&keys, "b", 101).await;
+    assert_eq!(commit_order(&early, &late), std::cmp::Ordering::Less);
+
+    let tie_a = group_event(&keys, "c", 100).await;
+    let (low, high) = if early.id < tie_a.id {
+        (&early, &tie_a)
+    } else {
+        (&tie_a, &early)
+    };
+    assert_eq!(commit_order(low, high), std::cmp::Ordering::Less);
+}
+
+#[tokio::test]
+async fn two_admins_racing_rolls_back_the_later_commit() {
+    let alice = Keys::generate();
+    let bob = Keys::generate();
+    let group_id = GroupId::from_slice(&[3u8; 32]);
+    let other_group = GroupId::from_slice(&[4u8; 32]);
+
+    // Both admins commit on epoch 1; Alice's arrives first but Bob's is older
+    let chat_before = group_event(&alice, "hi", 90).await;
+    let alice_commit = group_event(&alice, "alice commit", 101).await;
+    let bob_commit = group_event(&bob, "bob commit", 100).await;
+    let chat_after = group_event(&bob, "later", 102).await;
+    // Meanwhile another group moves on by itself
+    let other_commit = group_event(&bob, "other commit", 99).await;
+    let other_chat = group_event(&bob, "elsewhere", 103).await;
+
+    let mut ledger = CommitLedger::new();
+    let replay_from = ledger.begin_batch();
+    ledger.record_processed(group_id.clone(), chat_before.clone());
+    ledger.record_processed(group_id.clone(), alice_commit.clone());
+    ledger.record_commit(applied(&group_id, &alice_commit, "snap-1", replay_from));
+    ledger.end_batch();
+
+    let replay_from = ledger.begin_batch();
+    ledger.record_processed(group_id.clone(), chat_after.clone());
+    ledger.record_processed(other_group.clone(), other_commit.clone());
+    ledger.record_commit(applied(
+        &other_group,
+        &other_commit,
+        "snap-2",
+        replay_from + 1,
+    ));
+    ledger.record_processed(other_group.clone(), other_chat.clone());
+    ledger.end_batch();
+
+    assert!(ledger.is_known_commit(&alice_commit.id));
+    // A later commit doesn't challenge, nor does one for another group
+    let carol_commit = group_event(&Keys::generate(), "carol commit", 105).await;
+    assert_eq!(ledger.challenged_by(&group_id, &carol_commit), None);
+    assert_eq!(ledger.challenged_by(&other_group, &bob_commit), None);
+
+    let index = ledger.challenged_by(&group_id, &bob_commit).unwrap();
+    assert_eq!(ledger.get(index).unwrap().event_id, alice_commit.id);
+
+    let (replay, undone) = ledger.rollback(index);
+    assert_eq!(undone.len(), 1);
+    assert_eq!(undone[0].event_id, alice_commit.id);
+    // Everything since the snapshot except the loser, oldest first
+    let ids: Vec<EventId> = replay.iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec![chat_before.id, chat_after.id]);
+    assert!(!ledger.is_known_commit(&alice_commit.id));
+
+    // The other group's commit survives and still replays its own events only
+    assert!(ledger.is_known_commit(&other_commit.id));
+    let index = ledger.challenged_by(&other_group, &chat_before).unwrap();
+    let (replay, undone) = ledger.rollback(index);
+    assert_eq!(undone[0].event_id, other_commit.id);
+    let ids: Vec<EventId> = replay.iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec![other_chat.id]);
+}
+
+#[tokio::test]
+async fn expired_commits_release_their_snapshots() {
+    let keys = Keys::generate();
+    let group_id = GroupId::from_slice(&[5u8; 32]);
+    let first = group_event(&keys, "first", 100).await;
+    let second = group_event(&keys, "second", 200).await;
+
+    let mut ledger = CommitLedger::new();
+    let from = ledger.begin_batch();
+    ledger.record_processed(group_id.clone(), first.clone());
+    ledger.record_commit(applied(&group_id, &first, "snap-1", from));
+    ledger.end_batch();
+
+    let from = ledger.begin_batch();
+    ledger.record_processed(group_id.clone(), second.clone());
+    let mut newer = applied(&group_id, &second, "snap-2", from);
+    newer.applied_at = Instant::now() + Duration::from_secs(60);
+    ledger.record_commit(newer);
+    ledger.end_batch();
+
+    let stale = ledger.expire(
+        Instant::now() + Duration::from_secs(30),
+        Duration::from_secs(10),
+    );
+    assert_eq!(stale, vec![PathBuf::from("snap-1")]);
+    assert!(!ledger.is_known_commit(&first.id));
+
+    // Replay now starts at the remaining commit's batch
+    let (replay, _) = ledger.rollback(0);
+    assert!(replay.is_empty());
+}
+
+#[test]
+fn snapshots_restore_one_group_only() {
+    let tmp = TempDir::new().unwrap();
+    let db = tmp.path().join("nrc.db");
+    let conn = Connection::open(&db).unwrap();
+    let raced = GroupId::from_slice(&[6u8; 32]);
+    let other = GroupId::from_slice(&[7u8; 32]);
+    let openmls_key = |g: &GroupId| serde_json::to_vec(g).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE groups (mls_group_id BLOB PRIMARY KEY, epoch INTEGER);
+         CREATE TABLE openmls_group_data (group_id BLOB, data_type TEXT, group_data BLOB,
+             PRIMARY KEY (group_id, data_type));
+         CREATE TABLE openmls_encryption_keys (public_key BLOB PRIMARY KEY, key_pair BLOB);
+         CREATE TABLE messages (id INTEGER PRIMARY KEY, mls_group_id BLOB);
+         CREATE TABLE keys (id INTEGER PRIMARY KEY, data TEXT);
+         INSERT INTO openmls_encryption_keys VALUES (x'01', x'aa');",
+    )
+    .unwrap();
+    for g in [&raced, &other] {
+        conn.execute(
+            "INSERT INTO groups VALUES (?1, 1)",
+            rusqlite::params![g.as_slice()],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO openmls_group_data VALUES (?1, 'tree', x'01')",
+            rusqlite::params![openmls_key(g)],
+        )
+        .unwrap();
+    }
+
+    let snapshots = MlsSnapshots::new(tmp.path());
+    let snap = snapshots.take(&raced).unwrap();
+    assert!(snap.exists());
+
+    // Both groups move on; an old key pair is deleted; messages and app rows arrive
+    conn.execute_batch(
+        "UPDATE groups SET epoch = 2;
+         UPDATE openmls_group_data SET group_data = x'02';
+         DELETE FROM openmls_encryption_keys;
+         INSERT INTO openmls_encryption_keys VALUES (x'02', x'bb');
+         INSERT INTO messages (mls_group_id) VALUES (x'06');
+         INSERT INTO keys (data) VALUES ('k2');",
+    )
+    .unwrap();
+
+    snapshots.restore(&raced, &snap).unwrap();
+    let epoch = |g: &GroupId| -> i64 {
+        conn.query_row(
+            "SELECT epoch FROM groups WHERE mls_group_id = ?1",
+            rusqlite::params![g.as_slice()],
+            |r| r.get(0),
+        )
+        .unwrap()
+    };
+    let tree = |g: &GroupId| -> Vec<u8> {
+        conn.query_row(
+            "SELECT group_data FROM openmls_group_data WHERE group_id = ?1",
+            rusqlite::params![openmls_key(g)],
+            |r| r.get(0),
+        )
+        .unwrap()
+    };
+    let count = |table: &str| -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |r| r.get(0))
+            .unwrap()
+    };
+    assert_eq!((epoch(&raced), tree(&raced)), (1, vec![1]));
+    assert_eq!((epoch(&other), tree(&other)), (2, vec![2]));
+    // Key pairs are only added back; messages and app tables are untouched
+    assert_eq!(count("openmls_encryption_keys"), 2);
+    assert_eq!(count("messages"), 1);
+    assert_eq!(count("keys"), 1);
+
+    snapshots.discard(&snap);
+    assert!(!snap.exists());
+}
+
+struct Member {
+    keys: Keys,
+    app: App,
+    _dir: TempDir,
+}
+
+async fn member() -> Member {
+    let dir = TempDir::new().unwrap();
+    let keys = Keys::generate();
+    let storage = NostrMlsSqliteStorage::new(dir.path().join("nrc.db")).unwrap();
+    #[allow(clippy::arc_with_non_send_sync)]
+    let storage = Arc::new(NostrMls::new(storage));
+    let key_storage = KeyStorage::new(dir.path());
+    let page = Page::Onboarding {
+        input: String::new(),
+        mode: OnboardingMode::Choose,
+        error: None,
+    };
+    let app = App::new(storage, Client::default(), keys.clone(), key_storage, page)
+        .await
+        .unwrap();
+    Member {
+        keys,
+        app,
+        _dir: dir,
+    }
+}
+
+fn key_package(m: &Member) -> Event {
+    let relays = [RelayUrl::parse("wss://relay.example.com").unwrap()];
+    let (content, tags) = m
+        .app
+        .storage
+        .create_key_package_for_event(&m.keys.public_key(), relays)
+        .unwrap();
+    EventBuilder::new(Kind::MlsKeyPackage, content)
+        .tags(tags.to_vec())
+        .sign_with_keys(&m.keys)
+        .unwrap()
+}
+
+// Build and apply our own rename commit the way the ops queue does once it's acked
+async fn rename(m: &mut Member, group_id: &GroupId, name: &str) -> Event {
+    let update = m
+        .app
+        .storage
+        .update_group_data(group_id, NostrGroupDataUpdate::new().name(name))
+        .unwrap();
+    let commit_event = update.evolution_event;
+    let op_id = m
+        .app
+        .ops_store
+        .enqueue(OperationKind::GroupEvolution {
+            mls_group_id_hex: hex::encode(group_id.as_slice()),
+            description: format!("rename to {name}"),
+            step: GroupEvolutionStep::MergeCommit {
+                commit_event: commit_event.clone(),
+            },
+        })
+        .unwrap();
+    m.app
+        .handle_event(AppEvent::OpNeedsStorageMergeCommit {
+            op_id,
+            group_id: group_id.clone(),
+            commit_event: commit_event.clone(),
+        })
+        .await
+        .unwrap();
+    commit_event
+}
+
+async fn receive(m: &mut Member, event: &Event) {
+    m.app
+        .handle_event(AppEvent::RawMessagesReceived {
+            events: vec![event.clone()],
+        })
+        .await
+        .unwrap();
+}
+
+fn state(m: &Member, group_id: &GroupId) -> (u64, String, Vec<u8>) {
+    let group = m.app.storage.get_group(group_id).unwrap().unwrap();
+    let mls_group = MlsGroup::load(m.app.storage.provider.storage(), group_id)
+        .unwrap()
+        .unwrap();
+    let secret = mls_group
+        .export_secret(m.app.storage.provider.crypto(), "nostr", b"nostr", 32)
+        .unwrap();
+    (group.epoch, group.name, secret)
+}
+
+#[tokio::test]
+async fn third_member_converges_on_the_winning_commit() {
+    let mut alice = member().await;
+    let mut bob = member().await;
+    let mut carol = member().await;
+
+    // Alice and Bob are both admins of a group with Carol
+    let key_packages = vec![key_package(&bob), key_package(&carol)];
+    let config = NostrGroupConfigData::new(
+        "team".to_string(),
+        "race".to_string(),
+        None,
+        None,
+        None,
+        vec![RelayUrl::parse("wss://relay.example.com").unwrap()],
+        vec![alice.keys.public_key(), bob.keys.public_key()],
+    );
+    let created = alice
+        .app
+        .storage
+        .create_group(&alice.keys.public_key(), key_packages.clone(), config)
+        .unwrap();
+    let group_id = created.group.mls_group_id.clone();
+    alice.app.storage.merge_pending_commit(&group_id).unwrap();
+    for welcome in pair_welcomes(&key_packages, &created.welcome_rumors) {
+        let joiner = if welcome.to == bob.keys.public_key() {
+            &bob
+        } else {
+            &carol
+        };
+        let preview = joiner
+            .app
+            .storage
+            .process_welcome(&EventId::all_zeros(), &welcome.welcome_rumor)
+            .unwrap();
+        joiner.app.storage.accept_welcome(&preview).unwrap();
+    }
+
+    // Both admins commit on the same epoch and apply their own commit
+    let alice_commit = rename(&mut alice, &group_id, "alice's team").await;
+    let bob_commit = rename(&mut bob, &group_id, "bob's team").await;
+    let (winner, loser) = if commit_order(&alice_commit, &bob_commit).is_lt() {
+        (&alice_commit, &bob_commit)
+    } else {
+        (&bob_commit, &alice_commit)
+    };
+
+    // Carol gets the losing commit first and applies it, then the winner shows up
+    receive(&mut carol, loser).await;
+    receive(&mut carol, winner).await;
+    // Each admin hears about the other's commit
+    receive(&mut alice, &bob_commit).await;
+    receive(&mut bob, &alice_commit).await;
+
+    let expected = state(
+        if winner.id == alice_commit.id {
+            &alice
+        } else {
+            &bob
+        },
+        &group_id,
+    );
+    let winning_name = if winner.id == alice_commit.id {
+        "alice's team"
+    } else {
+        "bob's team"
+    };
+    assert_eq!(expected.1, winning_name);
+    assert_eq!(state(&alice, &group_id), expected);
+    assert_eq!(state(&bob, &group_id), expected);
+    assert_eq!(state(&carol, &group_id), expected);
+}
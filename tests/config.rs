@@ -0,0 +1,138 @@
+use nrc::config::{Config, CONFIG_FILE, DEFAULT_RELAYS};
+use std::time::Duration;
+use tempfile::TempDir;
+
+#[test]
+fn config_written_with_defaults_on_first_load() {
+    let tmp = TempDir::new().unwrap();
+    let config = Config::load(tmp.path()).unwrap();
+
+    assert_eq!(config, Config::default());
+    assert_eq!(config.relays.len(), DEFAULT_RELAYS.len());
+    assert!(tmp.path().join(CONFIG_FILE).exists());
+
+    // Reloading the written file gives the same settings
+    assert_eq!(Config::load(tmp.path()).unwrap(), config);
+}
+
+#[test]
+fn config_reads_relays_and_policies() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join(CONFIG_FILE),
+        r#"
+relays = ["wss://relay.example.com"]
+
+[key_rotation]
+interval_secs = 60
+"#,
+    )
+    .unwrap();
+
+    let config = Config::load(tmp.path()).unwrap();
+    assert_eq!(config.relays, vec!["wss://relay.example.com".to_string()]);
+    assert_eq!(config.relay_urls().unwrap().len(), 1);
+    assert_eq!(
+        config.key_rotation_policy().interval,
+        Duration::from_secs(60)
+    );
+    // Unset sections keep their defaults
+    assert_eq!(
+        config.key_package_policy(),
+        Config::default().key_package_policy()
+    );
+}
+
+#[test]
+fn config_rejects_bad_relay_urls() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(tmp.path().join(CONFIG_FILE), "relays = [\"not a url\"]\n").unwrap();
+    assert!(Config::load(tmp.path()).is_err());
+
+    let config = Config::default();
+    assert!(config
+        .clone()
+        .with_relay_overrides(vec!["also bad".to_string()])
+        .is_err());
+}
+
+#[test]
+fn cli_relays_replace_configured_set() {
+    let config = Config::default()
+        .with_relay_overrides(vec!["ws://127.0.0.1:8080".to_string()])
+        .unwrap();
+    assert_eq!(config.relays, vec!["ws://127.0.0.1:8080".to_string()]);
+
+    // No flags leaves the config alone
+    let untouched = Config::default().with_relay_overrides(vec![]).unwrap();
+    assert_eq!(untouched, Config::default());
+}
+
+#[test]
+fn auth_is_opt_in_per_relay() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join(CONFIG_FILE),
+        r#"
+relays = ["wss://public.example.com", "wss://private.example.com"]
+
+[auth]
+relays = ["wss://private.example.com"]
+"#,
+    )
+    .unwrap();
+
+    let config = Config::load(tmp.path()).unwrap();
+    let private = nostr_sdk::RelayUrl::parse("wss://private.example.com").unwrap();
+    let public = nostr_sdk::RelayUrl::parse("wss://public.example.com").unwrap();
+    assert!(config.auth_enabled_for(&private));
+    assert!(!config.auth_enabled_for(&public));
+
+    // Nothing is authenticated by default
+    assert!(!Config::default().auth_enabled_for(&private));
+
+    std::fs::write(
+        tmp.path().join(CONFIG_FILE),
+        "[auth]\nrelays = [\"nope\"]\n",
+    )
+    .unwrap();
+    assert!(Config::load(tmp.path()).is_err());
+}
+
+#[test]
+fn key_log_n_is_configurable_within_bounds() {
+    let tmp = TempDir::new().unwrap();
+    assert_eq!(Config::load(tmp.path()).unwrap().keys.log_n, 16);
+
+    std::fs::write(tmp.path().join(CONFIG_FILE), "[keys]\nlog_n = 20\n").unwrap();
+    assert_eq!(Config::load(tmp.path()).unwrap().keys.log_n, 20);
+
+    std::fs::write(tmp.path().join(CONFIG_FILE), "[keys]\nlog_n = 8\n").unwrap();
+    assert!(Config::load(tmp.path()).is_err());
+}
+
+#[test]
+fn idle_lock_defaults_on_and_zero_disables_it() {
+    let tmp = TempDir::new().unwrap();
+    assert_eq!(
+        Config::load(tmp.path()).unwrap().idle_lock_timeout(),
+        Some(Duration::from_secs(15 * 60))
+    );
+
+    std::fs::write(
+        tmp.path().join(CONFIG_FILE),
+        "[lock]\nidle_timeout_secs = 120\n",
+    )
+    .unwrap();
+    assert_eq!(
+        Config::load(tmp.path()).unwrap().idle_lock_timeout(),
+        Some(Duration::from_secs(120))
+    );
+
+    std::fs::write(
+        tmp.path().join(CONFIG_FILE),
+        "[lock]\nidle_timeout_secs = 0\n",
+    )
+    .unwrap();
+    assert_eq!(Config::load(tmp.path()).unwrap().idle_lock_timeout(), None);
+}
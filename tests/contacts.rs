@@ -0,0 +1,62 @@
+use nostr_sdk::prelude::*;
+use nrc::contacts::{follow_list, ContactStore};
+use tempfile::TempDir;
+
+#[test]
+fn petnames_persist_and_clear() {
+    let tmp = TempDir::new().unwrap();
+    let alice = Keys::generate().public_key();
+
+    let store = ContactStore::new(tmp.path()).unwrap();
+    store.set_petname(&alice, Some("  Mum ")).unwrap();
+
+    // Reopened from disk
+    let store = ContactStore::new(tmp.path()).unwrap();
+    assert_eq!(
+        store.petnames().unwrap().get(&alice).map(String::as_str),
+        Some("Mum")
+    );
+
+    // Clearing the petname of someone we don't follow forgets them
+    store.set_petname(&alice, None).unwrap();
+    assert!(store.petnames().unwrap().is_empty());
+    assert!(store.list().unwrap().is_empty());
+}
+
+#[test]
+fn importing_follows_keeps_petnames() {
+    let tmp = TempDir::new().unwrap();
+    let store = ContactStore::new(tmp.path()).unwrap();
+    let alice = Keys::generate().public_key();
+    let bob = Keys::generate().public_key();
+    let carol = Keys::generate().public_key();
+
+    store.import_follows(&[alice, bob]).unwrap();
+    store.set_petname(&bob, Some("Bobby")).unwrap();
+    store.set_petname(&carol, Some("Carol from work")).unwrap();
+
+    // A newer kind 3 drops alice and bob
+    assert_eq!(store.import_follows(&[carol]).unwrap(), 1);
+
+    let contacts = store.list().unwrap();
+    assert!(!contacts.iter().any(|c| c.pubkey == alice));
+    let bob_contact = contacts.iter().find(|c| c.pubkey == bob).unwrap();
+    assert!(!bob_contact.followed);
+    assert_eq!(bob_contact.petname.as_deref(), Some("Bobby"));
+    let carol_contact = contacts.iter().find(|c| c.pubkey == carol).unwrap();
+    assert!(carol_contact.followed);
+    assert_eq!(carol_contact.petname.as_deref(), Some("Carol from work"));
+}
+
+#[test]
+fn follow_list_reads_p_tags() {
+    let keys = Keys::generate();
+    let alice = Keys::generate().public_key();
+    let bob = Keys::generate().public_key();
+    let event = EventBuilder::new(Kind::ContactList, "")
+        .tags([Tag::public_key(alice), Tag::public_key(bob)])
+        .sign_with_keys(&keys)
+        .unwrap();
+
+    assert_eq!(follow_list(&event), vec![alice, bob]);
+}
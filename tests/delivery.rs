@@ -0,0 +1,91 @@
+use nostr_sdk::prelude::*;
+use nrc::delivery::{DeliveryStatus, DeliveryStore};
+use openmls::group::GroupId;
+use tempfile::TempDir;
+
+fn relay(url: &str) -> RelayUrl {
+    RelayUrl::parse(url).unwrap()
+}
+
+#[test]
+fn delivery_moves_from_queued_to_acked() {
+    let tmp = TempDir::new().unwrap();
+    let store = DeliveryStore::new(tmp.path()).unwrap();
+    let group = GroupId::from_slice(&[1u8; 32]);
+    let event_id = EventId::all_zeros();
+
+    store.track(&event_id, &group, "op-1").unwrap();
+    let d = store.get(&event_id).unwrap().unwrap();
+    assert_eq!(d.status, DeliveryStatus::Queued);
+    assert_eq!(d.op_id, "op-1");
+
+    assert!(store.mark_sent(&event_id).unwrap());
+    assert_eq!(
+        store.get(&event_id).unwrap().unwrap().status,
+        DeliveryStatus::Sent
+    );
+
+    // Each relay's OK is recorded once
+    assert!(store
+        .record_acks(&event_id, &[relay("wss://a.example.com")])
+        .unwrap());
+    assert!(store
+        .record_acks(
+            &event_id,
+            &[relay("wss://a.example.com"), relay("wss://b.example.com")]
+        )
+        .unwrap());
+    assert!(!store
+        .record_acks(&event_id, &[relay("wss://b.example.com")])
+        .unwrap());
+
+    let d = store.get(&event_id).unwrap().unwrap();
+    assert_eq!(d.status, DeliveryStatus::Acked);
+    assert_eq!(d.acked_by.len(), 2);
+
+    // A late "picked up" or failure never downgrades an acked message
+    assert!(!store.mark_sent(&event_id).unwrap());
+    assert!(!store.mark_failed(&event_id).unwrap());
+    assert_eq!(
+        store.get(&event_id).unwrap().unwrap().status,
+        DeliveryStatus::Acked
+    );
+}
+
+#[test]
+fn failed_messages_are_listed_per_group_and_can_be_requeued() {
+    let tmp = TempDir::new().unwrap();
+    let store = DeliveryStore::new(tmp.path()).unwrap();
+    let group = GroupId::from_slice(&[1u8; 32]);
+    let other = GroupId::from_slice(&[2u8; 32]);
+    let failed = EventId::from_slice(&[1u8; 32]).unwrap();
+    let fine = EventId::from_slice(&[2u8; 32]).unwrap();
+    let elsewhere = EventId::from_slice(&[3u8; 32]).unwrap();
+
+    store.track(&failed, &group, "op-failed").unwrap();
+    store.track(&fine, &group, "op-fine").unwrap();
+    store.track(&elsewhere, &other, "op-elsewhere").unwrap();
+    store.mark_failed(&failed).unwrap();
+    store.mark_failed(&elsewhere).unwrap();
+
+    let listed = store.failed_in_group(&group).unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].event_id, failed);
+    assert_eq!(listed[0].op_id, "op-failed");
+    assert_eq!(store.for_group(&group).unwrap().len(), 2);
+
+    assert!(store.mark_queued(&failed).unwrap());
+    assert!(store.failed_in_group(&group).unwrap().is_empty());
+}
+
+#[test]
+fn acks_for_unknown_events_are_ignored() {
+    let tmp = TempDir::new().unwrap();
+    let store = DeliveryStore::new(tmp.path()).unwrap();
+
+    let unknown = EventId::all_zeros();
+    assert!(!store
+        .record_acks(&unknown, &[relay("wss://a.example.com")])
+        .unwrap());
+    assert!(store.get(&unknown).unwrap().is_none());
+}
@@ -0,0 +1,24 @@
+-- nrc_ops.db as written before schema versioning (retry columns already present)
+CREATE TABLE operations (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    status TEXT NOT NULL,
+    last_error TEXT,
+    payload TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at INTEGER
+);
+INSERT INTO operations (id, kind, status, last_error, payload, created_at, updated_at, attempts, next_attempt_at)
+VALUES (
+    'a1b2c3d4-0000-4000-8000-000000000001',
+    'CreateDm',
+    'Error',
+    'no key package found',
+    '{"type":"CreateDm","data":{"other_pubkey":"79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798","step":"FetchKeyPackage"}}',
+    1760000000,
+    1760000100,
+    3,
+    1760000200
+);
@@ -0,0 +1,40 @@
+-- App tables of nrc.db as written before schema versioning
+CREATE TABLE keys (
+    id INTEGER PRIMARY KEY,
+    npub TEXT NOT NULL UNIQUE,
+    encrypted_nsec TEXT NOT NULL
+);
+CREATE TABLE group_meta (
+    mls_group_id TEXT PRIMARY KEY,
+    archived_at INTEGER
+);
+CREATE TABLE discarded_groups (
+    mls_group_id TEXT PRIMARY KEY,
+    discarded_at INTEGER NOT NULL
+);
+CREATE TABLE group_system_messages (
+    id INTEGER PRIMARY KEY,
+    mls_group_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+CREATE TABLE group_key_rotation (
+    mls_group_id TEXT PRIMARY KEY,
+    last_rotated_at INTEGER,
+    tracked_since INTEGER NOT NULL,
+    messages_since INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE key_packages (
+    event_id TEXT PRIMARY KEY,
+    event TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    published_at INTEGER,
+    consumed_at INTEGER,
+    deleted_at INTEGER
+);
+INSERT INTO keys (npub, encrypted_nsec)
+VALUES ('npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg', 'ncryptsec1placeholder');
+INSERT INTO group_meta (mls_group_id, archived_at)
+VALUES ('0101010101010101010101010101010101010101010101010101010101010101', 1760000000);
+INSERT INTO group_system_messages (mls_group_id, content, created_at)
+VALUES ('0101010101010101010101010101010101010101010101010101010101010101', 'alice left', 1760000000);
@@ -44,6 +44,7 @@ mod tests {
             input: String::new(),
             scroll_offset: 0,
             typing_members: vec![],
+            selected_message: None,
         };
 
         App::new(storage_arc, client, keys, key_storage, initial_page)
@@ -0,0 +1,73 @@
+use nostr_sdk::prelude::*;
+use nrc::group_changes::{GroupChange, GroupSnapshot};
+use std::collections::BTreeSet;
+
+fn snapshot(name: &str, admins: &[PublicKey], members: &[PublicKey]) -> GroupSnapshot {
+    GroupSnapshot {
+        name: name.to_string(),
+        admins: admins.iter().cloned().collect::<BTreeSet<_>>(),
+        members: members.iter().cloned().collect::<BTreeSet<_>>(),
+        leaf_keys: members.iter().map(|pk| (*pk, vec![0u8])).collect(),
+    }
+}
+
+#[test]
+fn diff_reports_membership_rename_and_admin_changes() {
+    let alice = Keys::generate().public_key();
+    let bob = Keys::generate().public_key();
+    let carol = Keys::generate().public_key();
+
+    let before = snapshot("team", &[alice], &[alice, bob]);
+    let after = snapshot("core team", &[alice, carol], &[alice, carol]);
+
+    let changes = before.diff(&after);
+    assert_eq!(
+        changes,
+        vec![
+            GroupChange::MemberAdded(carol),
+            GroupChange::MemberRemoved(bob),
+            GroupChange::Renamed("core team".to_string()),
+            GroupChange::AdminAdded(carol),
+        ]
+    );
+}
+
+#[test]
+fn diff_is_empty_for_self_update() {
+    let alice = Keys::generate().public_key();
+    let bob = Keys::generate().public_key();
+
+    let before = snapshot("team", &[alice], &[alice, bob]);
+    assert!(before.diff(&before.clone()).is_empty());
+}
+
+#[test]
+fn committer_is_the_member_whose_leaf_key_rotated() {
+    let me = Keys::generate().public_key();
+    let alice = Keys::generate().public_key();
+    let bob = Keys::generate().public_key();
+    let carol = Keys::generate().public_key();
+
+    let before = snapshot("team", &[me, alice, bob], &[me, alice, bob, carol]);
+    let mut after = snapshot("team", &[me, alice, bob], &[me, alice, bob]);
+    after.leaf_keys.insert(bob, vec![1u8]);
+    assert_eq!(before.committer(&after, &me), Some(bob));
+}
+
+#[test]
+fn committer_without_an_update_path_is_the_only_other_admin() {
+    let me = Keys::generate().public_key();
+    let alice = Keys::generate().public_key();
+    let bob = Keys::generate().public_key();
+    let carol = Keys::generate().public_key();
+
+    // Adding members only: no leaf keys change
+    let before = snapshot("team", &[me, alice], &[me, alice, bob]);
+    let after = snapshot("team", &[me, alice], &[me, alice, bob, carol]);
+    assert_eq!(before.committer(&after, &me), Some(alice));
+
+    // With two other admins it could have been either
+    let before = snapshot("team", &[me, alice, bob], &[me, alice, bob]);
+    let after = snapshot("team", &[me, alice, bob], &[me, alice, bob, carol]);
+    assert_eq!(before.committer(&after, &me), None);
+}
@@ -0,0 +1,144 @@
+use nostr_sdk::prelude::*;
+use nrc::config::KeyRotationPolicy;
+use nrc::group_meta::{GroupMetaStore, HeldProposal};
+use openmls::group::GroupId;
+use std::time::Duration;
+use tempfile::TempDir;
+
+#[test]
+fn group_meta_archive_round_trip() {
+    let tmp = TempDir::new().unwrap();
+    let store = GroupMetaStore::new(tmp.path()).unwrap();
+
+    let left = GroupId::from_slice(&[1u8; 32]);
+    let active = GroupId::from_slice(&[2u8; 32]);
+    assert!(!store.is_archived(&left).unwrap());
+
+    store.archive(&left).unwrap();
+    // Archiving twice is harmless
+    store.archive(&left).unwrap();
+
+    assert!(store.is_archived(&left).unwrap());
+    assert!(!store.is_archived(&active).unwrap());
+
+    // State survives reopening the database
+    let reopened = GroupMetaStore::new(tmp.path()).unwrap();
+    assert!(reopened.is_archived(&left).unwrap());
+}
+
+#[test]
+fn group_meta_system_messages_are_per_group() {
+    let tmp = TempDir::new().unwrap();
+    let store = GroupMetaStore::new(tmp.path()).unwrap();
+
+    let team = GroupId::from_slice(&[1u8; 32]);
+    let other = GroupId::from_slice(&[2u8; 32]);
+    store
+        .add_system_message(&team, "bob joined the group")
+        .unwrap();
+    store
+        .add_system_message(&team, "Group renamed to core")
+        .unwrap();
+    store
+        .add_system_message(&other, "carol left the group")
+        .unwrap();
+
+    let lines: Vec<String> = store
+        .system_messages(&team)
+        .unwrap()
+        .into_iter()
+        .map(|m| m.content)
+        .collect();
+    assert_eq!(lines, vec!["bob joined the group", "Group renamed to core"]);
+    assert_eq!(store.system_messages(&other).unwrap().len(), 1);
+}
+
+#[test]
+fn group_meta_tracks_key_rotation() {
+    let tmp = TempDir::new().unwrap();
+    let store = GroupMetaStore::new(tmp.path()).unwrap();
+    let group = GroupId::from_slice(&[3u8; 32]);
+
+    let fresh = store.key_rotation(&group).unwrap();
+    assert_eq!(fresh.last_rotated_at, None);
+    assert_eq!(fresh.messages_since, 0);
+
+    store.count_message(&group).unwrap();
+    store.count_message(&group).unwrap();
+    let counted = store.key_rotation(&group).unwrap();
+    assert_eq!(counted.messages_since, 2);
+    assert_eq!(counted.tracked_since, fresh.tracked_since);
+
+    store.record_key_rotation(&group).unwrap();
+    let rotated = store.key_rotation(&group).unwrap();
+    assert!(rotated.last_rotated_at.is_some());
+    assert_eq!(rotated.messages_since, 0);
+}
+
+#[test]
+fn key_rotation_policy_is_due_on_age_or_message_count() {
+    let policy = KeyRotationPolicy {
+        interval: Duration::from_secs(100),
+        max_messages: 10,
+    };
+
+    // Never rotated: age counts from when we started tracking the group
+    assert!(!policy.is_due(None, 1_000, 0, 1_050));
+    assert!(policy.is_due(None, 1_000, 0, 1_100));
+    // A recent rotation resets the clock
+    assert!(!policy.is_due(Some(1_090), 1_000, 0, 1_150));
+    // Busy groups rotate before the interval
+    assert!(policy.is_due(Some(1_090), 1_000, 10, 1_095));
+}
+
+#[test]
+fn group_meta_discarded_groups_are_also_archived() {
+    let tmp = TempDir::new().unwrap();
+    let store = GroupMetaStore::new(tmp.path()).unwrap();
+
+    let abandoned = GroupId::from_slice(&[3u8; 32]);
+    let left = GroupId::from_slice(&[4u8; 32]);
+    store.archive(&left).unwrap();
+    store.discard(&abandoned).unwrap();
+
+    assert!(store.is_discarded(&abandoned).unwrap());
+    assert!(store.is_archived(&abandoned).unwrap());
+    // Leaving a group doesn't hide it
+    assert!(!store.is_discarded(&left).unwrap());
+}
+
+#[test]
+fn group_meta_held_proposals_survive_restart_until_committed() {
+    let tmp = TempDir::new().unwrap();
+    let store = GroupMetaStore::new(tmp.path()).unwrap();
+
+    let team = GroupId::from_slice(&[1u8; 32]);
+    let other = GroupId::from_slice(&[2u8; 32]);
+    let keys = Keys::generate();
+    let held = |content: &str| HeldProposal {
+        event: EventBuilder::new(Kind::MlsGroupMessage, content)
+            .sign_with_keys(&keys)
+            .unwrap(),
+        proposer: keys.public_key(),
+        removes: Keys::generate().public_key(),
+    };
+    let first = held("first");
+    let second = held("second");
+    assert!(store.hold_proposal(&team, &first).unwrap());
+    assert!(store.hold_proposal(&team, &second).unwrap());
+    // A redelivered proposal isn't held twice
+    assert!(!store.hold_proposal(&team, &first).unwrap());
+    store.hold_proposal(&other, &held("elsewhere")).unwrap();
+
+    let reopened = GroupMetaStore::new(tmp.path()).unwrap();
+    assert_eq!(
+        reopened.held_proposals(&team).unwrap(),
+        vec![first.clone(), second]
+    );
+
+    reopened.mark_proposals_committed(&team).unwrap();
+    assert!(reopened.held_proposals(&team).unwrap().is_empty());
+    assert_eq!(reopened.held_proposals(&other).unwrap().len(), 1);
+    // Nor is it held again once committed
+    assert!(!reopened.hold_proposal(&team, &first).unwrap());
+}
@@ -139,6 +139,11 @@ impl TestApp {
         self.app.handle_event(event).await
     }
 
+    async fn send_alt_up(&mut self) -> Result<()> {
+        let event = AppEvent::KeyPress(KeyEvent::new(KeyCode::Up, KeyModifiers::ALT));
+        self.app.handle_event(event).await
+    }
+
     #[allow(dead_code)]
     async fn send_ctrl_n(&mut self) -> Result<()> {
         let event = AppEvent::KeyPress(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL));
@@ -207,7 +212,7 @@ impl TestApp {
     }
 
     fn get_npub(&self) -> String {
-        self.app.keys.public_key().to_bech32().unwrap()
+        self.app.signer.public_key().to_bech32().unwrap()
     }
 
     async fn dump_event_log(&self) {
@@ -630,6 +635,82 @@ async fn test_two_users_messaging() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_reply_quotes_its_parent() -> Result<()> {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let mut alice = TestApp::new().await?;
+    let mut bob = TestApp::new().await?;
+    for (app, name, password) in [
+        (&mut alice, "Alice", "alicepass123"),
+        (&mut bob, "Bob", "bobpass123"),
+    ] {
+        app.send_key('1').await?; // Generate new
+        app.send_enter().await?;
+        app.send_enter().await?; // Go to EnterDisplayName
+        app.send_keys(name).await?;
+        app.send_enter().await?;
+        app.send_keys(password).await?;
+        app.send_enter().await?;
+        tokio::time::sleep(Duration::from_millis(3000)).await;
+    }
+
+    // Alice starts a DM once Bob's key package reached the relays
+    let bob_npub = bob.get_npub();
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    alice.send_keys(&format!("/dm {bob_npub}")).await?;
+    alice.send_enter().await?;
+    tokio::time::sleep(Duration::from_millis(2000)).await;
+    assert!(alice.has_group(), "Alice should have created the DM");
+
+    bob.process_incoming_messages().await?;
+    let group_id = match &bob.app.current_page {
+        Page::Chat { groups, .. } => groups.first().map(|g| g.id.clone()),
+        _ => None,
+    }
+    .expect("Bob should have joined the DM");
+    bob.app
+        .navigate_to(nrc::ui_state::PageType::Chat(Some(group_id)))
+        .await?;
+
+    alice.send_message("Hello Bob!").await?;
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    alice.process_incoming_messages().await?;
+    tokio::time::sleep(Duration::from_millis(5000)).await;
+    bob.process_incoming_messages().await?;
+    assert_eq!(bob.get_last_message().as_deref(), Some("Hello Bob!"));
+
+    // Bob picks Alice's message and replies to it
+    bob.send_alt_up().await?;
+    bob.send_message("Hey Alice!").await?;
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    bob.process_incoming_messages().await?;
+    tokio::time::sleep(Duration::from_millis(5000)).await;
+    alice.process_incoming_messages().await?;
+
+    let alice_messages = match &alice.app.current_page {
+        Page::Chat { messages, .. } => messages.clone(),
+        _ => vec![],
+    };
+    let hello_id = alice_messages
+        .iter()
+        .find(|m| m.content == "Hello Bob!")
+        .and_then(|m| m.id);
+    let reply = alice_messages
+        .iter()
+        .find(|m| m.content == "Hey Alice!")
+        .expect("Alice MUST receive Bob's reply");
+    let reply_to = reply.reply_to.clone().expect("Bob's message is a reply");
+    assert!(hello_id.is_some());
+    assert_eq!(Some(reply_to.id), hello_id);
+    assert_eq!(
+        reply_to.parent.map(|p| p.content).as_deref(),
+        Some("Hello Bob!")
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_dm_label_never_own_name_and_loading_ok() -> Result<()> {
     // top and bottom users with profile names
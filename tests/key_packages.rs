@@ -0,0 +1,93 @@
+use nostr_sdk::prelude::*;
+use nrc::key_packages::{
+    key_package_relays, key_package_relays_event, key_package_relays_kind, KeyPackagePolicy,
+    KeyPackageRecord, KeyPackageStore,
+};
+use std::time::Duration;
+use tempfile::TempDir;
+
+async fn key_package(keys: &Keys, content: &str) -> Event {
+    EventBuilder::new(Kind::MlsKeyPackage, content)
+        .sign(keys)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn key_package_store_lifecycle() {
+    let tmp = TempDir::new().unwrap();
+    let store = KeyPackageStore::new(tmp.path()).unwrap();
+    let keys = Keys::generate();
+
+    let first = key_package(&keys, "kp1").await;
+    let second = key_package(&keys, "kp2").await;
+    store.add(&first).unwrap();
+    store.add(&second).unwrap();
+    assert_eq!(store.active().unwrap().len(), 2);
+
+    // Only acked packages are reconciled against relays
+    assert!(store.published_events().unwrap().is_empty());
+    store.mark_published(&first.id).unwrap();
+    let published = store.published_events().unwrap();
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].id, first.id);
+
+    // A welcome consumes a package once; unknown ids aren't ours
+    assert!(store.mark_consumed(&first.id).unwrap());
+    assert!(!store.mark_consumed(&first.id).unwrap());
+    let stranger = key_package(&Keys::generate(), "other").await;
+    assert!(!store.mark_consumed(&stranger.id).unwrap());
+
+    store.mark_deleted(&[second.id]).unwrap();
+    assert!(store.active().unwrap().is_empty());
+    assert!(store.published_events().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn key_package_policy_tops_up_and_rotates() {
+    let keys = Keys::generate();
+    let old = key_package(&keys, "old").await;
+    let fresh = key_package(&keys, "fresh").await;
+    let policy = KeyPackagePolicy {
+        target_count: 3,
+        rotate_after: Duration::from_secs(100),
+    };
+
+    let empty = policy.plan(&[], 1_000);
+    assert!(empty.retire.is_empty());
+    assert_eq!(empty.publish, 3);
+
+    let active = vec![
+        KeyPackageRecord {
+            event_id: old.id,
+            created_at: 800,
+            published_at: Some(800),
+        },
+        KeyPackageRecord {
+            event_id: fresh.id,
+            created_at: 950,
+            published_at: None,
+        },
+    ];
+    let plan = policy.plan(&active, 1_000);
+    assert_eq!(plan.retire, vec![old.id]);
+    assert_eq!(plan.publish, 2);
+}
+
+#[tokio::test]
+async fn key_package_relay_list_round_trip() {
+    let keys = Keys::generate();
+    let relays = vec![
+        RelayUrl::parse("wss://inbox.nostr.wine").unwrap(),
+        RelayUrl::parse("wss://myrelay.nostr1.com").unwrap(),
+    ];
+    let event = key_package_relays_event(&relays)
+        .tag(Tag::custom(TagKind::Relay, ["not a url"]))
+        .sign(&keys)
+        .await
+        .unwrap();
+
+    assert_eq!(event.kind, key_package_relays_kind());
+    assert_eq!(event.kind.as_u16(), 10051);
+    assert_eq!(key_package_relays(&event), relays);
+}
@@ -0,0 +1,132 @@
+use nostr_sdk::prelude::*;
+use nrc::key_storage::KeyStorage;
+use tempfile::TempDir;
+
+#[test]
+fn first_account_keeps_the_datadir_and_later_ones_get_their_own() {
+    let tmp = TempDir::new().unwrap();
+    let storage = KeyStorage::new(tmp.path());
+    let personal = Keys::generate();
+    let team = Keys::generate();
+    let team_npub = team.public_key().to_bech32().unwrap();
+
+    // Known before saving, so a new account's session can open its storage
+    assert_eq!(
+        storage.account_dir(&team_npub).unwrap(),
+        tmp.path().to_path_buf()
+    );
+
+    storage.save_encrypted(&personal, "personal-pw").unwrap();
+    storage.save_encrypted(&team, "team-pw").unwrap();
+
+    let accounts = storage.list_accounts().unwrap();
+    assert_eq!(accounts.len(), 2);
+    assert_eq!(accounts[0].datadir, tmp.path().to_path_buf());
+    assert_eq!(
+        accounts[1].datadir,
+        tmp.path().join("accounts").join(&team_npub)
+    );
+    assert_eq!(
+        storage.for_account(&team_npub).account_datadir().unwrap(),
+        accounts[1].datadir
+    );
+}
+
+#[test]
+fn load_encrypted_unlocks_the_chosen_account() {
+    let tmp = TempDir::new().unwrap();
+    let storage = KeyStorage::new(tmp.path());
+    let personal = Keys::generate();
+    let team = Keys::generate();
+    let team_npub = team.public_key().to_bech32().unwrap();
+    storage.save_encrypted(&personal, "personal-pw").unwrap();
+    storage.save_encrypted(&team, "team-pw").unwrap();
+
+    // Without a choice, the first account
+    assert_eq!(
+        storage.load_encrypted("personal-pw").unwrap().public_key(),
+        personal.public_key()
+    );
+
+    let team_storage = storage.for_account(&team_npub);
+    assert!(team_storage.load_encrypted("personal-pw").is_err());
+    assert_eq!(
+        team_storage.load_encrypted("team-pw").unwrap().public_key(),
+        team.public_key()
+    );
+}
+
+#[test]
+fn saving_again_keeps_the_account_in_place() {
+    let tmp = TempDir::new().unwrap();
+    let storage = KeyStorage::new(tmp.path());
+    let personal = Keys::generate();
+    let team = Keys::generate();
+    storage.save_encrypted(&personal, "personal-pw").unwrap();
+    storage.save_encrypted(&team, "team-pw").unwrap();
+    let before = storage.list_accounts().unwrap();
+
+    storage
+        .save_encrypted(&personal, "new-personal-pw")
+        .unwrap();
+
+    // Same order (the first account stays first) and same directories
+    assert_eq!(storage.list_accounts().unwrap(), before);
+    assert!(storage.load_encrypted("new-personal-pw").is_ok());
+}
+
+#[test]
+fn change_password_re_encrypts_in_place() {
+    let tmp = TempDir::new().unwrap();
+    let storage = KeyStorage::new(tmp.path());
+    let keys = Keys::generate();
+    storage.save_encrypted(&keys, "old-password").unwrap();
+
+    let changed = storage
+        .change_password("old-password", "new-password", 16)
+        .unwrap();
+    assert_eq!(changed.public_key(), keys.public_key());
+
+    assert!(storage.load_encrypted("old-password").is_err());
+    assert_eq!(
+        storage.load_encrypted("new-password").unwrap().public_key(),
+        keys.public_key()
+    );
+}
+
+#[test]
+fn change_password_refuses_bad_input_and_leaves_keys_alone() {
+    let tmp = TempDir::new().unwrap();
+    let storage = KeyStorage::new(tmp.path());
+    let keys = Keys::generate();
+    storage.save_encrypted(&keys, "old-password").unwrap();
+
+    let wrong = storage
+        .change_password("not-the-password", "new-password", 16)
+        .unwrap_err();
+    assert!(wrong.to_string().contains("Wrong password"));
+    assert!(storage
+        .change_password("old-password", "short", 16)
+        .is_err());
+    assert!(storage
+        .change_password("old-password", "new-password", 30)
+        .is_err());
+
+    assert!(storage.load_encrypted("old-password").is_ok());
+}
+
+#[test]
+fn ncryptsec_export_decrypts_with_the_password() {
+    let tmp = TempDir::new().unwrap();
+    let storage = KeyStorage::new(tmp.path());
+    let keys = Keys::generate();
+    storage.save_encrypted(&keys, "backup-password").unwrap();
+
+    let ncryptsec = storage.ncryptsec().unwrap();
+    assert!(ncryptsec.starts_with("ncryptsec1"));
+    let restored = EncryptedSecretKey::from_bech32(&ncryptsec)
+        .unwrap()
+        .decrypt("backup-password")
+        .unwrap();
+    assert_eq!(restored, *keys.secret_key());
+}
@@ -0,0 +1,323 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use nostr_relay_builder::prelude::*;
+use nostr_sdk::prelude::*;
+use nrc::group_messages::{read_proposal, MemberProposal, ProposedChange};
+use nrc::group_meta::{GroupMetaStore, HeldProposal};
+use nrc::key_storage::KeyStorage;
+use nrc::ops::{pair_welcomes, GroupEvolutionStep, OperationKind};
+use nrc::ui_state::OnboardingMode;
+use nrc::{App, AppEvent, Page, PageType};
+use nrc_mls::groups::NostrGroupConfigData;
+use nrc_mls::NostrMls;
+use nrc_mls_sqlite_storage::NostrMlsSqliteStorage;
+use openmls::group::GroupId;
+use openmls::prelude::{MlsGroup, OpenMlsProvider};
+use tempfile::TempDir;
+
+struct Member {
+    keys: Keys,
+    mls: Arc<NostrMls<NostrMlsSqliteStorage>>,
+    dir: TempDir,
+}
+
+fn member() -> Member {
+    let dir = TempDir::new().unwrap();
+    let storage = NostrMlsSqliteStorage::new(dir.path().join("nrc.db")).unwrap();
+    #[allow(clippy::arc_with_non_send_sync)]
+    let mls = Arc::new(NostrMls::new(storage));
+    Member {
+        keys: Keys::generate(),
+        mls,
+        dir,
+    }
+}
+
+async fn app_for(m: &Member) -> App {
+    let page = Page::Onboarding {
+        input: String::new(),
+        mode: OnboardingMode::Choose,
+        error: None,
+    };
+    App::new(
+        m.mls.clone(),
+        Client::default(),
+        m.keys.clone(),
+        KeyStorage::new(m.dir.path()),
+        page,
+    )
+    .await
+    .unwrap()
+}
+
+fn key_package(m: &Member, relay: &RelayUrl) -> Event {
+    let (content, tags) = m
+        .mls
+        .create_key_package_for_event(&m.keys.public_key(), [relay.clone()])
+        .unwrap();
+    EventBuilder::new(Kind::MlsKeyPackage, content)
+        .tags(tags.to_vec())
+        .sign_with_keys(&m.keys)
+        .unwrap()
+}
+
+/// `creator` creates a group on `relay` with everyone in `joiners`, who all accept.
+fn create_group(
+    creator: &Member,
+    joiners: &[&Member],
+    admins: &[&Member],
+    relay: &RelayUrl,
+) -> GroupId {
+    let key_packages: Vec<Event> = joiners.iter().map(|m| key_package(m, relay)).collect();
+    let config = NostrGroupConfigData::new(
+        "team".to_string(),
+        "proposals".to_string(),
+        None,
+        None,
+        None,
+        vec![relay.clone()],
+        admins.iter().map(|m| m.keys.public_key()).collect(),
+    );
+    let created = creator
+        .mls
+        .create_group(&creator.keys.public_key(), key_packages.clone(), config)
+        .unwrap();
+    let group_id = created.group.mls_group_id.clone();
+    creator.mls.merge_pending_commit(&group_id).unwrap();
+    for welcome in pair_welcomes(&key_packages, &created.welcome_rumors) {
+        let joiner = joiners
+            .iter()
+            .find(|m| m.keys.public_key() == welcome.to)
+            .unwrap();
+        let preview = joiner
+            .mls
+            .process_welcome(&EventId::all_zeros(), &welcome.welcome_rumor)
+            .unwrap();
+        joiner.mls.accept_welcome(&preview).unwrap();
+    }
+    group_id
+}
+
+#[tokio::test]
+async fn leave_proposal_is_read_without_touching_state() {
+    let alice = member();
+    let bob = member();
+    let relay = RelayUrl::parse("wss://relay.example.com").unwrap();
+    let group_id = create_group(&alice, &[&bob], &[&alice], &relay);
+
+    let leave = bob.mls.leave_group(&group_id).unwrap().evolution_event;
+    let proposal = read_proposal(&alice.mls, &group_id, &leave).unwrap();
+    assert_eq!(
+        proposal,
+        Some(MemberProposal {
+            proposer: bob.keys.public_key(),
+            change: ProposedChange::Leave,
+        })
+    );
+    // Reading it twice gives the same answer: nothing was consumed
+    assert_eq!(
+        read_proposal(&alice.mls, &group_id, &leave).unwrap(),
+        proposal
+    );
+
+    // Application messages aren't proposals
+    let rumor = EventBuilder::new(Kind::Custom(9), "hi").build(alice.keys.public_key());
+    let message = alice.mls.create_message(&group_id, rumor).unwrap();
+    assert_eq!(read_proposal(&bob.mls, &group_id, &message).unwrap(), None);
+}
+
+#[tokio::test]
+async fn admin_removes_a_member_who_leaves() {
+    let relay = LocalRelay::run(RelayBuilder::default()).await.unwrap();
+    let relay_url = RelayUrl::parse(&relay.url().to_string()).unwrap();
+    let alice = member();
+    let bob = member();
+    let carol = member();
+    let group_id = create_group(&alice, &[&bob, &carol], &[&alice], &relay_url);
+    let mut app = app_for(&alice).await;
+    let mut events = app.event_rx.take().unwrap();
+
+    // Bob isn't an admin, so all he can do is propose his own removal
+    let leave = bob.mls.leave_group(&group_id).unwrap().evolution_event;
+    app.handle_event(AppEvent::RawMessagesReceived {
+        events: vec![leave.clone()],
+    })
+    .await
+    .unwrap();
+    // A redelivered proposal doesn't remove him twice
+    app.handle_event(AppEvent::RawMessagesReceived {
+        events: vec![leave],
+    })
+    .await
+    .unwrap();
+
+    // Alice's Remove commit is published and merged once the relay accepts it
+    let bob_pk = bob.keys.public_key();
+    let removed = tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(event) = events.recv().await {
+            if matches!(event, AppEvent::OpNeedsStorageMergeCommit { .. }) {
+                app.handle_event(event).await.unwrap();
+                if !app
+                    .storage
+                    .get_members(&group_id)
+                    .unwrap()
+                    .contains(&bob_pk)
+                {
+                    return true;
+                }
+            }
+        }
+        false
+    })
+    .await
+    .unwrap_or(false);
+    assert!(removed, "bob should have been removed");
+
+    let members = app.storage.get_members(&group_id).unwrap();
+    assert!(members.contains(&carol.keys.public_key()));
+    assert_eq!(members.len(), 2);
+    let removals = app.ops_store.list_unfinished("GroupEvolution").unwrap();
+    assert!(
+        removals.is_empty(),
+        "only one removal was queued: {removals:?}"
+    );
+}
+
+/// Merge whatever commits `app` publishes until `done` holds; returns the last commit.
+async fn merge_commits_until(
+    app: &mut App,
+    events: &mut tokio::sync::mpsc::UnboundedReceiver<AppEvent>,
+    done: impl Fn(&App) -> bool,
+) -> Option<Event> {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(event) = events.recv().await {
+            if let AppEvent::OpNeedsStorageMergeCommit { commit_event, .. } = &event {
+                let commit_event = commit_event.clone();
+                app.handle_event(event).await.unwrap();
+                if done(app) {
+                    return Some(commit_event);
+                }
+            }
+        }
+        None
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+#[tokio::test]
+async fn admin_proposals_are_committed_by_admins_only() {
+    let relay = LocalRelay::run(RelayBuilder::default()).await.unwrap();
+    let relay_url = RelayUrl::parse(&relay.url().to_string()).unwrap();
+    let alice = member();
+    let bob = member();
+    let carol = member();
+    let group_id = create_group(&alice, &[&bob, &carol], &[&alice, &bob], &relay_url);
+    let mut alice_app = app_for(&alice).await;
+    let mut alice_events = alice_app.event_rx.take().unwrap();
+    let mut carol_app = app_for(&carol).await;
+
+    // Bob is an admin; nrc-mls stages a commit for his proposal on everyone's side
+    let leave = bob.mls.leave_group(&group_id).unwrap().evolution_event;
+    for app in [&mut alice_app, &mut carol_app] {
+        app.handle_event(AppEvent::RawMessagesReceived {
+            events: vec![leave.clone()],
+        })
+        .await
+        .unwrap();
+    }
+
+    // Carol can't commit, so nothing of hers is left staged
+    let carol_group = MlsGroup::load(carol.mls.provider.storage(), &group_id)
+        .unwrap()
+        .unwrap();
+    assert!(carol_group.pending_commit().is_none());
+
+    // Alice publishes her commit right away, and Carol follows it
+    let bob_pk = bob.keys.public_key();
+    let commit = merge_commits_until(&mut alice_app, &mut alice_events, |app| {
+        !app.storage
+            .get_members(&group_id)
+            .unwrap()
+            .contains(&bob_pk)
+    })
+    .await
+    .expect("alice should have committed bob's leave");
+    carol_app
+        .handle_event(AppEvent::RawMessagesReceived {
+            events: vec![commit],
+        })
+        .await
+        .unwrap();
+    assert!(!carol.mls.get_members(&group_id).unwrap().contains(&bob_pk));
+    let rumor = EventBuilder::new(Kind::Custom(9), "still here").build(carol.keys.public_key());
+    carol.mls.create_message(&group_id, rumor).unwrap();
+}
+
+#[tokio::test]
+async fn commit_builds_one_removal_for_all_held_proposals() {
+    let relay = RelayUrl::parse("wss://relay.example.com").unwrap();
+    let alice = member();
+    let bob = member();
+    let carol = member();
+    let dave = member();
+    let group_id = create_group(&alice, &[&bob, &carol, &dave], &[&alice], &relay);
+
+    // Dave asked twice to remove Bob and once to remove Carol
+    let meta = GroupMetaStore::new(alice.dir.path()).unwrap();
+    for (n, removes) in [&bob, &bob, &carol].iter().enumerate() {
+        let event = EventBuilder::new(Kind::MlsGroupMessage, format!("proposal {n}"))
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        let held = HeldProposal {
+            event,
+            proposer: dave.keys.public_key(),
+            removes: removes.keys.public_key(),
+        };
+        assert!(meta.hold_proposal(&group_id, &held).unwrap());
+    }
+
+    let mut app = app_for(&alice).await;
+    // Holding them staged nothing, so Alice can still talk
+    let rumor = EventBuilder::new(Kind::Custom(9), "hi").build(alice.keys.public_key());
+    alice.mls.create_message(&group_id, rumor).unwrap();
+
+    app.navigate_to(PageType::Chat(Some(group_id.clone())))
+        .await
+        .unwrap();
+    for c in "/commit".chars() {
+        app.handle_event(AppEvent::KeyPress(KeyEvent::new(
+            KeyCode::Char(c),
+            KeyModifiers::empty(),
+        )))
+        .await
+        .unwrap();
+    }
+    app.handle_event(AppEvent::KeyPress(KeyEvent::new(
+        KeyCode::Enter,
+        KeyModifiers::empty(),
+    )))
+    .await
+    .unwrap();
+
+    let ops = app.ops_store.list_unfinished("GroupEvolution").unwrap();
+    assert_eq!(ops.len(), 1, "one commit for every held proposal: {ops:?}");
+    let OperationKind::GroupEvolution {
+        description, step, ..
+    } = &ops[0].kind
+    else {
+        panic!("not a group evolution op");
+    };
+    assert!(matches!(
+        step,
+        GroupEvolutionStep::Publish {
+            is_commit: true,
+            ..
+        }
+    ));
+    assert_eq!(description.matches("npub").count(), 2);
+    assert!(meta.held_proposals(&group_id).unwrap().is_empty());
+}
@@ -0,0 +1,133 @@
+use nrc::group_meta::GroupMetaStore;
+use nrc::key_storage::KeyStorage;
+use nrc::migrations::{self, Migration};
+use nrc::ops::{CreateDmStep, OpStatus, OperationKind, OpsStore};
+use rusqlite::Connection;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn load_fixture(path: &Path, sql: &str) {
+    Connection::open(path).unwrap().execute_batch(sql).unwrap();
+}
+
+fn version_of(path: &Path) -> u32 {
+    migrations::current_version(&Connection::open(path).unwrap()).unwrap()
+}
+
+#[test]
+fn unversioned_ops_db_is_upgraded_in_place() {
+    let tmp = TempDir::new().unwrap();
+    let db = tmp.path().join("nrc_ops.db");
+    load_fixture(&db, include_str!("fixtures/nrc_ops_unversioned.sql"));
+    assert_eq!(version_of(&db), 0);
+
+    let store = OpsStore::new(tmp.path()).unwrap();
+    assert_eq!(
+        version_of(&db),
+        migrations::latest_version(migrations::OPS_DB)
+    );
+
+    // The queued op and its retry state survive
+    let op = store.load("a1b2c3d4-0000-4000-8000-000000000001").unwrap();
+    assert_eq!(op.status, OpStatus::Error);
+    assert_eq!(op.attempts, 3);
+    assert_eq!(op.next_attempt_at, Some(1760000200));
+    assert!(matches!(
+        op.kind,
+        OperationKind::CreateDm {
+            step: CreateDmStep::FetchKeyPackage,
+            ..
+        }
+    ));
+
+    // Reopening is a no-op
+    OpsStore::new(tmp.path()).unwrap();
+    assert_eq!(store.list_all().unwrap().len(), 1);
+}
+
+#[test]
+fn unversioned_nrc_db_is_upgraded_in_place() {
+    let tmp = TempDir::new().unwrap();
+    let db = tmp.path().join("nrc.db");
+    load_fixture(&db, include_str!("fixtures/nrc_unversioned.sql"));
+
+    let meta = GroupMetaStore::new(tmp.path()).unwrap();
+    assert_eq!(
+        version_of(&db),
+        migrations::latest_version(migrations::NRC_DB)
+    );
+
+    let group = openmls::group::GroupId::from_slice(&[1u8; 32]);
+    assert!(meta.is_archived(&group).unwrap());
+    assert_eq!(meta.system_messages(&group).unwrap().len(), 1);
+
+    let keys = KeyStorage::new(tmp.path());
+    assert!(keys.keys_exist());
+    assert_eq!(
+        keys.get_first_npub().unwrap(),
+        "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg"
+    );
+}
+
+#[test]
+fn newer_schema_is_refused() {
+    let tmp = TempDir::new().unwrap();
+    OpsStore::new(tmp.path()).unwrap();
+
+    let conn = Connection::open(tmp.path().join("nrc_ops.db")).unwrap();
+    conn.execute(
+        "INSERT INTO schema_version (version, description, applied_at) VALUES (99, 'from the future', 0)",
+        [],
+    )
+    .unwrap();
+
+    let err = OpsStore::new(tmp.path())
+        .err()
+        .expect("newer schema opened");
+    assert!(err.to_string().contains("newer"), "{err}");
+}
+
+fn create_a(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("CREATE TABLE a (x INTEGER)")
+}
+
+fn create_b_then_fail(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("CREATE TABLE b (x INTEGER)")?;
+    conn.execute_batch("INSERT INTO no_such_table VALUES (1)")
+}
+
+#[test]
+fn failed_migration_leaves_database_untouched() {
+    let tmp = TempDir::new().unwrap();
+    let mut conn = Connection::open(tmp.path().join("test.db")).unwrap();
+
+    let first = [Migration {
+        version: 1,
+        description: "a",
+        up: create_a,
+    }];
+    assert_eq!(migrations::migrate(&mut conn, &first).unwrap(), 1);
+
+    let broken = [
+        Migration {
+            version: 1,
+            description: "a",
+            up: create_a,
+        },
+        Migration {
+            version: 2,
+            description: "b",
+            up: create_b_then_fail,
+        },
+        Migration {
+            version: 3,
+            description: "c",
+            up: create_a,
+        },
+    ];
+    assert!(migrations::migrate(&mut conn, &broken).is_err());
+
+    assert_eq!(migrations::current_version(&conn).unwrap(), 1);
+    assert!(!migrations::has_column(&conn, "b", "x").unwrap());
+    assert!(migrations::has_column(&conn, "a", "x").unwrap());
+}
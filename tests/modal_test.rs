@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use nostr_sdk::prelude::*;
+    use nrc::ui_state::{Modal, ModalAction, Page};
+    use nrc::{App, AppEvent};
+    use nrc_mls::NostrMls;
+    use nrc_mls_sqlite_storage::NostrMlsSqliteStorage;
+    use openmls::group::GroupId;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    async fn setup_test_app(temp_dir: &TempDir) -> App {
+        let db_path = temp_dir.path().join("test.db");
+        let keys = Keys::generate();
+        let storage = NostrMlsSqliteStorage::new(db_path.to_str().unwrap()).unwrap();
+        #[allow(clippy::arc_with_non_send_sync)]
+        let storage_arc = Arc::new(NostrMls::new(storage));
+        let client = Client::default();
+        let key_storage = nrc::key_storage::KeyStorage::new(temp_dir.path());
+
+        let initial_page = Page::Help {
+            selected_section: 0,
+        };
+
+        App::new(storage_arc, client, keys, key_storage, initial_page)
+            .await
+            .unwrap()
+    }
+
+    fn key(c: char) -> AppEvent {
+        AppEvent::KeyPress(KeyEvent::new(KeyCode::Char(c), KeyModifiers::empty()))
+    }
+
+    #[tokio::test]
+    async fn test_confirm_modal_waits_for_answer() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = setup_test_app(&temp_dir).await;
+        let group_id = GroupId::from_slice(&[9u8; 32]);
+
+        app.modal = Some(Modal::Confirm {
+            message: "Leave?".to_string(),
+            on_confirm: ModalAction::LeaveGroup(group_id),
+        });
+
+        // Unrelated keys don't dismiss the confirmation
+        app.handle_event(key('x')).await.unwrap();
+        assert!(app.modal.is_some(), "Modal should stay open");
+
+        // 'n' cancels without emitting anything
+        app.handle_event(key('n')).await.unwrap();
+        assert!(app.modal.is_none(), "Modal should be closed");
+        let mut rx = app.event_rx.take().unwrap();
+        assert!(rx.try_recv().is_err(), "Cancel must not emit events");
+    }
+
+    #[tokio::test]
+    async fn test_confirm_modal_emits_leave_group() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut app = setup_test_app(&temp_dir).await;
+        let group_id = GroupId::from_slice(&[9u8; 32]);
+
+        app.modal = Some(Modal::Confirm {
+            message: "Leave?".to_string(),
+            on_confirm: ModalAction::LeaveGroup(group_id.clone()),
+        });
+        app.handle_event(key('y')).await.unwrap();
+        assert!(app.modal.is_none(), "Modal should be closed");
+
+        let mut rx = app.event_rx.take().unwrap();
+        match rx.try_recv() {
+            Ok(AppEvent::LeaveGroup(id)) => assert_eq!(id, group_id),
+            other => panic!("Expected LeaveGroup event, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use nostr_sdk::prelude::*;
+use nrc::nip05::{Nip05Address, Nip05Resolver, NIP05_VERIFIED_TTL};
+use nrc::profiles::Profiles;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+// Local stand-in for https://<domain>/.well-known/nostr.json
+async fn serve(status: &'static str, body: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf).await;
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+    format!("http://{addr}")
+}
+
+#[test]
+fn nip05_addresses_parse() {
+    let a = Nip05Address::parse("Bob@Example.com").unwrap();
+    assert_eq!(a.name, "bob");
+    assert_eq!(a.domain, "example.com");
+    assert_eq!(a.to_string(), "bob@example.com");
+
+    // A bare domain is the root identifier
+    let root = Nip05Address::parse("example.com").unwrap();
+    assert_eq!(root.name, "_");
+    assert_eq!(root.to_string(), "example.com");
+
+    assert!(Nip05Address::parse("npub1xyz").is_err());
+    assert!(Nip05Address::parse("bob@").is_err());
+    assert!(Nip05Address::parse("b ob@example.com").is_err());
+}
+
+#[tokio::test]
+async fn nip05_lookup_and_verify() {
+    let bob = Keys::generate().public_key();
+    let body = format!(r#"{{"names":{{"bob":"{}"}}}}"#, bob.to_hex());
+    let resolver = Nip05Resolver::new().with_base_url(serve("200 OK", body).await);
+
+    let address = Nip05Address::parse("bob@example.com").unwrap();
+    assert_eq!(resolver.lookup(&address).await.unwrap(), Some(bob));
+    assert!(resolver.verify(&bob, &address).await.unwrap());
+
+    // Someone else claiming bob@example.com isn't verified
+    let mallory = Keys::generate().public_key();
+    assert!(!resolver.verify(&mallory, &address).await.unwrap());
+
+    let unknown = Nip05Address::parse("carol@example.com").unwrap();
+    assert_eq!(resolver.lookup(&unknown).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn nip05_server_errors_are_errors() {
+    let resolver =
+        Nip05Resolver::new().with_base_url(serve("404 Not Found", "{}".to_string()).await);
+    let address = Nip05Address::parse("bob@example.com").unwrap();
+    assert!(resolver.lookup(&address).await.is_err());
+}
+
+#[tokio::test]
+async fn nip05_results_expire_and_follow_the_claimed_address() {
+    let profiles = Profiles::new();
+    let pk = Keys::generate().public_key();
+    let address = Nip05Address::parse("bob@example.com").unwrap();
+    profiles
+        .cache(
+            pk,
+            Metadata::new().name("bob").nip05("bob@example.com"),
+            Timestamp::from(100),
+        )
+        .await;
+
+    let now = Timestamp::now();
+    assert_eq!(
+        profiles.nip05_to_check(&pk, now).await,
+        Some(address.clone())
+    );
+    assert!(profiles.record_nip05(pk, address, true, now).await);
+    assert!(profiles.is_verified(&pk, now).await);
+    assert_eq!(profiles.nip05_to_check(&pk, now).await, None);
+
+    // Past the TTL the marker goes away and we check again
+    let later = Timestamp::from(now.as_u64() + NIP05_VERIFIED_TTL.as_secs());
+    assert!(!profiles.is_verified(&pk, later).await);
+    assert!(profiles.nip05_to_check(&pk, later).await.is_some());
+
+    // A new Kind 0 claiming a different address isn't covered by the old check
+    profiles
+        .cache(
+            pk,
+            Metadata::new().name("bob").nip05("bob@other.example"),
+            Timestamp::from(200),
+        )
+        .await;
+    assert!(!profiles.is_verified(&pk, now).await);
+    assert_eq!(
+        profiles.nip05_to_check(&pk, now).await,
+        Some(Nip05Address::parse("bob@other.example").unwrap())
+    );
+}
@@ -1,7 +1,11 @@
 use std::time::Duration;
 
+use nostr_relay_builder::prelude::*;
 use nostr_sdk::prelude::*;
-use nrc::ops::{spawn_orchestrator, CreateDmStep, OperationKind, OpsCommand, OpsStore};
+use nrc::ops::{
+    spawn_orchestrator, CreateDmStep, CreateGroupStep, InviteMemberStep, OpStatus, OperationKind,
+    OpsCommand, OpsStore,
+};
 use nrc::AppEvent;
 use tempfile::TempDir;
 
@@ -37,7 +41,7 @@ async fn orchestrator_reemits_storage_request_on_resume() {
     spawn_orchestrator(
         store.clone(),
         client,
-        keys.clone(),
+        Some(keys.clone().into()),
         event_tx,
         ops_cmd_rx,
         tmp.path().to_path_buf(),
@@ -69,3 +73,344 @@ async fn orchestrator_reemits_storage_request_on_resume() {
         "expected OpNeedsStorageCreateGroup within timeout"
     );
 }
+
+#[tokio::test]
+async fn orchestrator_reemits_group_storage_request_on_resume() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path()).unwrap();
+
+    let keys = Keys::generate();
+    let alice = Keys::generate();
+    let bob = Keys::generate();
+    let alice_kp = EventBuilder::text_note("alice kp")
+        .sign(&alice)
+        .await
+        .unwrap();
+    let bob_kp = EventBuilder::text_note("bob kp").sign(&bob).await.unwrap();
+
+    // Persist an op that already fetched every key package
+    let op_id = store
+        .enqueue(OperationKind::CreateGroup {
+            name: "team".to_string(),
+            members: vec![alice.public_key(), bob.public_key()],
+            step: CreateGroupStep::RequestCreateGroup {
+                key_packages: vec![alice_kp.clone(), bob_kp.clone()],
+            },
+        })
+        .unwrap();
+
+    let client = Client::builder().signer(keys.clone()).build();
+    let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AppEvent>();
+    let (ops_cmd_tx, ops_cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    spawn_orchestrator(
+        store.clone(),
+        client,
+        Some(keys.clone().into()),
+        event_tx,
+        ops_cmd_rx,
+        tmp.path().to_path_buf(),
+    );
+    let _ = ops_cmd_tx.send(OpsCommand::Wake);
+
+    let got = tokio::time::timeout(Duration::from_secs(2), async move {
+        while let Some(ev) = event_rx.recv().await {
+            if let AppEvent::OpNeedsStorageCreateMultiGroup {
+                op_id: ev_id,
+                name,
+                members,
+                key_packages,
+            } = ev
+            {
+                assert_eq!(ev_id, op_id);
+                assert_eq!(name, "team");
+                assert_eq!(members, vec![alice.public_key(), bob.public_key()]);
+                assert_eq!(key_packages.len(), 2);
+                break;
+            }
+        }
+    })
+    .await;
+
+    assert!(
+        got.is_ok(),
+        "expected OpNeedsStorageCreateMultiGroup within timeout"
+    );
+}
+
+#[tokio::test]
+async fn orchestrator_reemits_merge_request_on_resume() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path()).unwrap();
+
+    let keys = Keys::generate();
+    let invitee = Keys::generate().public_key();
+    let welcome_rumor = EventBuilder::new(Kind::MlsWelcome, "welcome").build(keys.public_key());
+    let group_id = openmls::group::GroupId::from_slice(&[7u8; 32]);
+    let commit_event = EventBuilder::new(Kind::MlsGroupMessage, "commit")
+        .sign(&keys)
+        .await
+        .unwrap();
+
+    // Persist an op whose commit was already acked but not yet merged
+    let op_id = store
+        .enqueue(OperationKind::InviteMember {
+            mls_group_id_hex: hex::encode(group_id.as_slice()),
+            invitee,
+            step: InviteMemberStep::MergeCommit {
+                commit_event: commit_event.clone(),
+                welcome_rumor,
+            },
+        })
+        .unwrap();
+
+    let client = Client::builder().signer(keys.clone()).build();
+    let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AppEvent>();
+    let (ops_cmd_tx, ops_cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    spawn_orchestrator(
+        store.clone(),
+        client,
+        Some(keys.clone().into()),
+        event_tx,
+        ops_cmd_rx,
+        tmp.path().to_path_buf(),
+    );
+    let _ = ops_cmd_tx.send(OpsCommand::Wake);
+
+    let got = tokio::time::timeout(Duration::from_secs(2), async move {
+        while let Some(ev) = event_rx.recv().await {
+            if let AppEvent::OpNeedsStorageMergeCommit {
+                op_id: ev_id,
+                group_id: ev_group_id,
+                commit_event: ev_commit,
+            } = ev
+            {
+                assert_eq!(ev_id, op_id);
+                assert_eq!(ev_group_id, group_id);
+                assert_eq!(ev_commit.id, commit_event.id);
+                break;
+            }
+        }
+    })
+    .await;
+
+    assert!(
+        got.is_ok(),
+        "expected OpNeedsStorageMergeCommit within timeout"
+    );
+}
+
+#[tokio::test]
+async fn locked_orchestrator_keeps_ops_queued_until_unlocked() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path()).unwrap();
+
+    let keys = Keys::generate();
+    let other_keys = Keys::generate();
+    let key_package_event = EventBuilder::text_note("dummy kp")
+        .sign(&other_keys)
+        .await
+        .unwrap();
+    let op_id = store
+        .enqueue(OperationKind::CreateDm {
+            other_pubkey: other_keys.public_key(),
+            step: CreateDmStep::RequestCreateGroup {
+                key_package: key_package_event,
+            },
+        })
+        .unwrap();
+
+    let client = Client::builder().signer(keys.clone()).build();
+    let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AppEvent>();
+    let (ops_cmd_tx, ops_cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    // Started locked, as when the app still waits for the password
+    spawn_orchestrator(
+        store.clone(),
+        client,
+        None,
+        event_tx,
+        ops_cmd_rx,
+        tmp.path().to_path_buf(),
+    );
+    let _ = ops_cmd_tx.send(OpsCommand::Wake);
+
+    let early = tokio::time::timeout(Duration::from_millis(600), event_rx.recv()).await;
+    assert!(early.is_err(), "no operation may run while locked");
+    assert!(store.list_all().unwrap().iter().any(|op| op.id == op_id));
+
+    let _ = ops_cmd_tx.send(OpsCommand::Unlock(Box::new(keys.into())));
+    let got = tokio::time::timeout(Duration::from_secs(2), async move {
+        while let Some(ev) = event_rx.recv().await {
+            if let AppEvent::OpNeedsStorageCreateGroup { op_id: ev_id, .. } = ev {
+                assert_eq!(ev_id, op_id);
+                break;
+            }
+        }
+    })
+    .await;
+
+    assert!(
+        got.is_ok(),
+        "expected OpNeedsStorageCreateGroup once unlocked"
+    );
+}
+
+#[tokio::test]
+async fn failed_op_keeps_its_progress_for_the_retry() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path()).unwrap();
+
+    // Only alice has published a KeyPackage
+    let relay = LocalRelay::run(RelayBuilder::default()).await.unwrap();
+    let keys = Keys::generate();
+    let alice = Keys::generate();
+    let bob = Keys::generate();
+    let alice_kp = EventBuilder::new(Kind::MlsKeyPackage, "alice kp")
+        .sign(&alice)
+        .await
+        .unwrap();
+    let client = Client::builder().signer(keys.clone()).build();
+    let url = RelayUrl::parse(&relay.url().to_string()).unwrap();
+    client.add_relay(url).await.unwrap();
+    client.connect().await;
+    client.send_event(&alice_kp).await.unwrap();
+
+    let op_id = store
+        .enqueue(OperationKind::CreateGroup {
+            name: "team".to_string(),
+            members: vec![alice.public_key(), bob.public_key()],
+            step: CreateGroupStep::FetchKeyPackages {
+                key_packages: vec![],
+            },
+        })
+        .unwrap();
+
+    let (event_tx, _event_rx) = tokio::sync::mpsc::unbounded_channel::<AppEvent>();
+    let (ops_cmd_tx, ops_cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+    spawn_orchestrator(
+        store.clone(),
+        client,
+        Some(keys.into()),
+        event_tx,
+        ops_cmd_rx,
+        tmp.path().to_path_buf(),
+    );
+    let _ = ops_cmd_tx.send(OpsCommand::Wake);
+
+    // Bob's missing KeyPackage fails the attempt
+    let op = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let op = store.load(&op_id).unwrap();
+            if op.status == OpStatus::Error {
+                break op;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("expected the op to fail");
+    assert!(op.last_error.unwrap().contains("No key package found"));
+
+    // Alice's KeyPackage was saved with the failure
+    match op.kind {
+        OperationKind::CreateGroup {
+            step: CreateGroupStep::FetchKeyPackages { key_packages },
+            ..
+        } => {
+            assert_eq!(key_packages.len(), 1);
+            assert_eq!(key_packages[0].id, alice_kp.id);
+        }
+        kind => panic!("unexpected kind {kind:?}"),
+    }
+}
+
+#[tokio::test]
+async fn create_group_resumes_with_the_key_packages_it_kept() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path()).unwrap();
+
+    let relay = LocalRelay::run(RelayBuilder::default()).await.unwrap();
+    let keys = Keys::generate();
+    let alice = Keys::generate();
+    let bob = Keys::generate();
+    let alice_kp = EventBuilder::new(Kind::MlsKeyPackage, "alice kp")
+        .sign(&alice)
+        .await
+        .unwrap();
+    let bob_kp = EventBuilder::new(Kind::MlsKeyPackage, "bob kp")
+        .sign(&bob)
+        .await
+        .unwrap();
+    let client = Client::builder().signer(keys.clone()).build();
+    let url = RelayUrl::parse(&relay.url().to_string()).unwrap();
+    client.add_relay(url).await.unwrap();
+    client.connect().await;
+    client.send_event(&alice_kp).await.unwrap();
+
+    let members = vec![alice.public_key(), bob.public_key()];
+    let op_id = store
+        .enqueue(OperationKind::CreateGroup {
+            name: "team".to_string(),
+            members: members.clone(),
+            step: CreateGroupStep::FetchKeyPackages {
+                key_packages: vec![],
+            },
+        })
+        .unwrap();
+
+    let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<AppEvent>();
+    let (ops_cmd_tx, ops_cmd_rx) = tokio::sync::mpsc::unbounded_channel();
+    spawn_orchestrator(
+        store.clone(),
+        client.clone(),
+        Some(keys.into()),
+        event_tx,
+        ops_cmd_rx,
+        tmp.path().to_path_buf(),
+    );
+    let _ = ops_cmd_tx.send(OpsCommand::Wake);
+
+    // Fails on bob's missing KeyPackage
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while store.load(&op_id).unwrap().status != OpStatus::Error {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("expected the op to fail");
+
+    // Bob publishes one; `/group team bob alice` resumes the same op
+    client.send_event(&bob_kp).await.unwrap();
+    let members_again = vec![bob.public_key(), alice.public_key()];
+    assert_eq!(
+        store.resume_create_group("team", &members_again).unwrap(),
+        Some(op_id.clone())
+    );
+    assert_eq!(store.load(&op_id).unwrap().status, OpStatus::Pending);
+    assert_eq!(store.resume_create_group("other", &members).unwrap(), None);
+    let _ = ops_cmd_tx.send(OpsCommand::Wake);
+
+    let got = tokio::time::timeout(Duration::from_secs(10), async move {
+        while let Some(ev) = event_rx.recv().await {
+            if let AppEvent::OpNeedsStorageCreateMultiGroup {
+                op_id: ev_id,
+                key_packages,
+                ..
+            } = ev
+            {
+                assert_eq!(ev_id, op_id);
+                let ids: Vec<EventId> = key_packages.iter().map(|kp| kp.id).collect();
+                assert_eq!(ids, vec![alice_kp.id, bob_kp.id]);
+                break;
+            }
+        }
+    })
+    .await;
+
+    assert!(
+        got.is_ok(),
+        "expected OpNeedsStorageCreateMultiGroup once resumed"
+    );
+}
@@ -1,5 +1,9 @@
 use nostr_sdk::prelude::*;
-use nrc::ops::{CreateDmStep, OpStatus, OperationKind, OpsStore};
+use nrc::ops::{
+    pair_welcomes, CreateDmStep, CreateGroupStep, GroupEvolutionStep, OpStatus, OperationKind,
+    OpsStore, RetryPolicy, RotateGroupKeyStep,
+};
+use std::time::Duration;
 use tempfile::TempDir;
 
 #[tokio::test]
@@ -17,12 +21,13 @@ async fn ops_store_enqueue_and_load() {
     let id = store
         .enqueue(OperationKind::SendMessage {
             event: event.clone(),
+            relays: vec![],
         })
         .unwrap();
 
     let loaded = store.load(&id).unwrap();
     match loaded.kind {
-        OperationKind::SendMessage { event: e } => {
+        OperationKind::SendMessage { event: e, .. } => {
             assert_eq!(e.id, event.id);
         }
         _ => panic!("unexpected kind"),
@@ -48,3 +53,360 @@ async fn ops_store_mark_success() {
     let loaded = store.load(&op_id).unwrap();
     assert_eq!(loaded.status, OpStatus::Success);
 }
+
+#[tokio::test]
+async fn pair_welcomes_matches_by_key_package_tag() {
+    let creator = Keys::generate();
+    let alice = Keys::generate();
+    let bob = Keys::generate();
+    let alice_kp = EventBuilder::text_note("alice kp")
+        .sign(&alice)
+        .await
+        .unwrap();
+    let bob_kp = EventBuilder::text_note("bob kp").sign(&bob).await.unwrap();
+
+    // Welcomes arrive in the opposite order to the key packages
+    let bob_welcome = EventBuilder::new(Kind::MlsWelcome, "bob")
+        .tag(Tag::event(bob_kp.id))
+        .build(creator.public_key());
+    let alice_welcome = EventBuilder::new(Kind::MlsWelcome, "alice")
+        .tag(Tag::event(alice_kp.id))
+        .build(creator.public_key());
+
+    let paired = pair_welcomes(
+        &[alice_kp.clone(), bob_kp.clone()],
+        &[bob_welcome, alice_welcome],
+    );
+    assert_eq!(paired.len(), 2);
+    assert_eq!(paired[0].to, bob.public_key());
+    assert_eq!(paired[0].welcome_rumor.content, "bob");
+    assert_eq!(paired[1].to, alice.public_key());
+    assert_eq!(paired[1].welcome_rumor.content, "alice");
+}
+
+#[tokio::test]
+async fn send_message_payload_without_relays_still_loads() {
+    // Ops queued before per-group relay routing have no `relays` field
+    let keys = Keys::generate();
+    let event = EventBuilder::text_note("queued earlier")
+        .sign(&keys)
+        .await
+        .unwrap();
+    let legacy = serde_json::json!({
+        "type": "SendMessage",
+        "data": { "event": event },
+    });
+
+    let kind: OperationKind = serde_json::from_value(legacy).unwrap();
+    match kind {
+        OperationKind::SendMessage { event: e, relays } => {
+            assert_eq!(e.id, event.id);
+            assert!(relays.is_empty());
+        }
+        _ => panic!("wrong kind"),
+    }
+}
+
+#[tokio::test]
+async fn held_sends_stay_queued_while_other_ops_run() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path()).unwrap();
+
+    let keys = Keys::generate();
+    let event = EventBuilder::text_note("offline")
+        .sign(&keys)
+        .await
+        .unwrap();
+    let send_id = store
+        .enqueue(OperationKind::SendMessage {
+            event,
+            relays: vec![],
+        })
+        .unwrap();
+    let dm_id = store
+        .enqueue(OperationKind::CreateDm {
+            other_pubkey: Keys::generate().public_key(),
+            step: CreateDmStep::FetchKeyPackage,
+        })
+        .unwrap();
+    assert_eq!(store.queued_sends().unwrap(), 1);
+
+    // The older SendMessage is skipped while sends are held
+    let next = store
+        .take_next_pending_except(&["SendMessage"])
+        .unwrap()
+        .unwrap();
+    assert_eq!(next.id, dm_id);
+    store.mark_success(&dm_id).unwrap();
+    assert!(store
+        .take_next_pending_except(&["SendMessage"])
+        .unwrap()
+        .is_none());
+
+    let next = store.take_next_pending().unwrap().unwrap();
+    assert_eq!(next.id, send_id);
+    store.mark_success(&send_id).unwrap();
+    assert_eq!(store.queued_sends().unwrap(), 0);
+}
+
+#[test]
+fn retry_delay_backs_off_exponentially_with_cap() {
+    let policy = RetryPolicy {
+        base_delay: Duration::from_secs(2),
+        max_delay: Duration::from_secs(60),
+        max_attempts: 5,
+    };
+    // Full jitter keeps the exponential delay, none halves it
+    assert_eq!(policy.delay(1, 1.0), Duration::from_secs(2));
+    assert_eq!(policy.delay(2, 1.0), Duration::from_secs(4));
+    assert_eq!(policy.delay(4, 1.0), Duration::from_secs(16));
+    assert_eq!(policy.delay(4, 0.0), Duration::from_secs(8));
+    assert_eq!(policy.delay(10, 1.0), Duration::from_secs(60));
+
+    assert_eq!(policy.next_attempt_at(1, 1000, 1.0), Some(1002));
+    assert_eq!(policy.next_attempt_at(5, 1000, 1.0), None);
+}
+
+#[tokio::test]
+async fn failed_attempts_back_off_then_dead_letter() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path())
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            base_delay: Duration::from_secs(60),
+            max_delay: Duration::from_secs(600),
+            max_attempts: 2,
+        });
+
+    let op_id = store
+        .enqueue(OperationKind::CreateDm {
+            other_pubkey: Keys::generate().public_key(),
+            step: CreateDmStep::FetchKeyPackage,
+        })
+        .unwrap();
+    store.take_next_pending().unwrap().unwrap();
+
+    // First failure schedules a retry in the future, so nothing is due yet
+    assert_eq!(
+        store.mark_error(&op_id, "relay timeout").unwrap(),
+        OpStatus::Error
+    );
+    let op = store.load(&op_id).unwrap();
+    assert_eq!(op.attempts, 1);
+    assert!(op.next_attempt_at.unwrap() > chrono::Utc::now().timestamp());
+    assert!(store.take_next_pending().unwrap().is_none());
+
+    // Out of attempts: terminal
+    assert_eq!(
+        store.mark_error(&op_id, "relay timeout").unwrap(),
+        OpStatus::Failed
+    );
+    let op = store.load(&op_id).unwrap();
+    assert_eq!(op.status, OpStatus::Failed);
+    assert_eq!(op.attempts, 2);
+    assert!(op.next_attempt_at.is_none());
+
+    // A manual requeue starts over
+    store.requeue(&op_id).unwrap();
+    let op = store.load(&op_id).unwrap();
+    assert_eq!(op.status, OpStatus::Pending);
+    assert_eq!(op.attempts, 0);
+}
+
+#[test]
+fn retry_columns_added_to_existing_database() {
+    let tmp = TempDir::new().unwrap();
+    let conn = rusqlite::Connection::open(tmp.path().join("nrc_ops.db")).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE operations (
+            id TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            status TEXT NOT NULL,
+            last_error TEXT,
+            payload TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+    )
+    .unwrap();
+
+    let store = OpsStore::new(tmp.path()).unwrap();
+    assert!(store.list_all().unwrap().is_empty());
+    let id = store
+        .enqueue(OperationKind::CreateDm {
+            other_pubkey: Keys::generate().public_key(),
+            step: CreateDmStep::FetchKeyPackage,
+        })
+        .unwrap();
+    assert_eq!(store.load(&id).unwrap().attempts, 0);
+}
+
+#[tokio::test]
+async fn cancelled_op_stays_cancelled() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path()).unwrap();
+
+    let other = Keys::generate().public_key();
+    let op_id = store
+        .enqueue(OperationKind::CreateDm {
+            other_pubkey: other,
+            step: CreateDmStep::FetchKeyPackage,
+        })
+        .unwrap();
+    let mut op = store.take_next_pending().unwrap().unwrap();
+    assert_eq!(op.id, op_id);
+
+    assert!(store.cancel(&op_id).unwrap());
+    // Cancelling twice reports nothing to cancel
+    assert!(!store.cancel(&op_id).unwrap());
+
+    // The orchestrator finishing its in-flight step must not revive the op
+    op.kind = OperationKind::CreateDm {
+        other_pubkey: other,
+        step: CreateDmStep::Done,
+    };
+    store.save(&op).unwrap();
+    store.mark_success(&op_id).unwrap();
+    store.mark_error(&op_id, "late failure").unwrap();
+
+    let loaded = store.load(&op_id).unwrap();
+    assert_eq!(loaded.status, OpStatus::Cancelled);
+    assert!(matches!(
+        loaded.kind,
+        OperationKind::CreateDm {
+            step: CreateDmStep::FetchKeyPackage,
+            ..
+        }
+    ));
+    assert!(store.take_next_pending().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn finished_op_cannot_be_cancelled() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path()).unwrap();
+
+    let op_id = store
+        .enqueue(OperationKind::CreateDm {
+            other_pubkey: Keys::generate().public_key(),
+            step: CreateDmStep::FetchKeyPackage,
+        })
+        .unwrap();
+    store.mark_success(&op_id).unwrap();
+
+    assert!(!store.cancel(&op_id).unwrap());
+    assert_eq!(store.load(&op_id).unwrap().status, OpStatus::Success);
+}
+
+#[tokio::test]
+async fn list_unfinished_only_returns_open_ops_of_that_kind() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path()).unwrap();
+
+    let rotate = |byte: u8| OperationKind::RotateGroupKey {
+        mls_group_id_hex: hex::encode([byte; 32]),
+        step: RotateGroupKeyStep::RequestSelfUpdate,
+    };
+    let pending = store.enqueue(rotate(1)).unwrap();
+    let done = store.enqueue(rotate(2)).unwrap();
+    store.mark_success(&done).unwrap();
+    let failed = store.enqueue(rotate(3)).unwrap();
+    store.mark_failed(&failed, "gave up").unwrap();
+    store
+        .enqueue(OperationKind::CreateDm {
+            other_pubkey: Keys::generate().public_key(),
+            step: CreateDmStep::FetchKeyPackage,
+        })
+        .unwrap();
+
+    let mut ids: Vec<String> = store
+        .list_unfinished("RotateGroupKey")
+        .unwrap()
+        .into_iter()
+        .map(|op| op.id)
+        .collect();
+    ids.sort();
+    let mut expected = vec![pending, failed];
+    expected.sort();
+    assert_eq!(ids, expected);
+}
+
+#[tokio::test]
+async fn merged_commit_is_not_merged_or_failed_again() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path()).unwrap();
+
+    let commit_event = EventBuilder::new(Kind::MlsGroupMessage, "commit")
+        .sign_with_keys(&Keys::generate())
+        .unwrap();
+    let op_id = store
+        .enqueue(OperationKind::GroupEvolution {
+            mls_group_id_hex: hex::encode([1u8; 32]),
+            description: "remove bob".to_string(),
+            step: GroupEvolutionStep::MergeCommit { commit_event },
+        })
+        .unwrap();
+    assert!(store.load(&op_id).unwrap().awaits_merge());
+
+    // A repeated merge request after success must not dead-letter the op
+    store.mark_success(&op_id).unwrap();
+    assert!(!store.load(&op_id).unwrap().awaits_merge());
+    store.mark_failed(&op_id, "merge failed").unwrap();
+    assert_eq!(store.load(&op_id).unwrap().status, OpStatus::Success);
+
+    // Ops at other steps have nothing to merge
+    let rotation = store
+        .enqueue(OperationKind::RotateGroupKey {
+            mls_group_id_hex: hex::encode([1u8; 32]),
+            step: RotateGroupKeyStep::RequestSelfUpdate,
+        })
+        .unwrap();
+    assert!(!store.load(&rotation).unwrap().awaits_merge());
+}
+
+#[tokio::test]
+async fn created_group_is_not_created_again() {
+    let tmp = TempDir::new().unwrap();
+    let store = OpsStore::new(tmp.path()).unwrap();
+
+    let bob = Keys::generate();
+    let key_package = EventBuilder::new(Kind::MlsKeyPackage, "kp")
+        .sign_with_keys(&bob)
+        .unwrap();
+    let op_id = store
+        .enqueue(OperationKind::CreateGroup {
+            name: "team".to_string(),
+            members: vec![bob.public_key()],
+            step: CreateGroupStep::RequestCreateGroup {
+                key_packages: vec![key_package],
+            },
+        })
+        .unwrap();
+    assert!(store.load(&op_id).unwrap().awaits_group_creation());
+
+    // Once the group exists the op moves on to sending welcomes
+    let mut op = store.load(&op_id).unwrap();
+    op.kind = OperationKind::CreateGroup {
+        name: "team".to_string(),
+        members: vec![bob.public_key()],
+        step: CreateGroupStep::SendWelcomes {
+            nostr_group_id_hex: hex::encode([2u8; 32]),
+            welcomes: vec![],
+        },
+    };
+    store.save(&op).unwrap();
+    assert!(!store.load(&op_id).unwrap().awaits_group_creation());
+
+    // A stopped op doesn't create its group either
+    let cancelled = store
+        .enqueue(OperationKind::CreateGroup {
+            name: "other".to_string(),
+            members: vec![bob.public_key()],
+            step: CreateGroupStep::RequestCreateGroup {
+                key_packages: vec![],
+            },
+        })
+        .unwrap();
+    assert!(store.cancel(&cancelled).unwrap());
+    assert!(!store.load(&cancelled).unwrap().awaits_group_creation());
+}
@@ -0,0 +1,94 @@
+use nostr_sdk::prelude::*;
+use nrc::profiles::Profiles;
+use std::time::Duration;
+use tempfile::TempDir;
+
+fn named(name: &str) -> Metadata {
+    Metadata::new().name(name)
+}
+
+#[tokio::test]
+async fn newer_profile_events_win() {
+    let profiles = Profiles::new();
+    let pk = Keys::generate().public_key();
+
+    assert!(
+        profiles
+            .cache(pk, named("alice"), Timestamp::from(100))
+            .await
+    );
+    // An older event arriving late is ignored
+    assert!(
+        !profiles
+            .cache(pk, named("old alice"), Timestamp::from(50))
+            .await
+    );
+    // So is the same event from another relay
+    assert!(
+        !profiles
+            .cache(pk, named("alice"), Timestamp::from(100))
+            .await
+    );
+    assert_eq!(
+        profiles.display_name_async(&pk).await.as_deref(),
+        Some("alice")
+    );
+
+    assert!(
+        profiles
+            .cache(pk, named("alice2"), Timestamp::from(200))
+            .await
+    );
+    assert_eq!(
+        profiles.display_name_async(&pk).await.as_deref(),
+        Some("alice2")
+    );
+}
+
+#[tokio::test]
+async fn profiles_survive_a_restart() {
+    let tmp = TempDir::new().unwrap();
+    let pk = Keys::generate().public_key();
+
+    let profiles = Profiles::load(tmp.path()).unwrap();
+    profiles.cache(pk, named("bob"), Timestamp::from(100)).await;
+    drop(profiles);
+
+    let reloaded = Profiles::load(tmp.path()).unwrap();
+    assert_eq!(reloaded.display_name(&pk).as_deref(), Some("bob"));
+    let cached = reloaded.get(&pk).await.unwrap();
+    assert_eq!(cached.created_at, Timestamp::from(100));
+
+    // Newer-wins still applies against the persisted copy
+    assert!(
+        !reloaded
+            .cache(pk, named("stale bob"), Timestamp::from(10))
+            .await
+    );
+    let again = Profiles::load(tmp.path()).unwrap();
+    assert_eq!(again.display_name(&pk).as_deref(), Some("bob"));
+}
+
+#[tokio::test]
+async fn only_missing_or_stale_profiles_need_fetching() {
+    let profiles = Profiles::new();
+    let fresh = Keys::generate().public_key();
+    let unknown = Keys::generate().public_key();
+    profiles
+        .cache(fresh, named("carol"), Timestamp::from(100))
+        .await;
+
+    let ttl = Duration::from_secs(60);
+    let now = Timestamp::now();
+    assert_eq!(
+        profiles.stale(&[fresh, unknown], now, ttl).await,
+        vec![unknown]
+    );
+
+    // Once the TTL has passed since we last heard from relays, refresh it too
+    let later = Timestamp::from(now.as_u64() + 61);
+    assert_eq!(
+        profiles.stale(&[fresh, unknown], later, ttl).await,
+        vec![fresh, unknown]
+    );
+}
@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use nostr_relay_builder::prelude::*;
+use nostr_sdk::prelude::*;
+use nrc::notification_handler::spawn_notification_handler;
+use nrc::relay_auth::answer_auth_challenge;
+use nrc::relay_status::RelayActivity;
+use nrc::AppEvent;
+use tokio::sync::mpsc;
+
+// Local relay stand-in that serves nothing until the client authenticates
+async fn auth_relay() -> (LocalRelay, RelayUrl) {
+    let builder = RelayBuilder::default().nip42(RelayBuilderNip42 {
+        mode: RelayBuilderNip42Mode::Both,
+    });
+    let relay = LocalRelay::run(builder).await.unwrap();
+    let url = RelayUrl::parse(&relay.url().to_string()).unwrap();
+    (relay, url)
+}
+
+async fn connect(
+    url: &RelayUrl,
+) -> (
+    Client,
+    Keys,
+    RelayActivity,
+    mpsc::UnboundedReceiver<AppEvent>,
+) {
+    let keys = Keys::generate();
+    let client = Client::builder()
+        .signer(keys.clone())
+        .opts(ClientOptions::new().automatic_authentication(false))
+        .build();
+    client.add_relay(url.clone()).await.unwrap();
+    client.connect().await;
+
+    let (event_tx, event_rx) = mpsc::unbounded_channel();
+    let activity = RelayActivity::new();
+    spawn_notification_handler(
+        client.clone(),
+        event_tx,
+        keys.public_key(),
+        activity.clone(),
+    );
+    (client, keys, activity, event_rx)
+}
+
+async fn next_challenge(event_rx: &mut mpsc::UnboundedReceiver<AppEvent>) -> (RelayUrl, String) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(AppEvent::RelayAuthChallenge {
+                relay_url,
+                challenge,
+            }) = event_rx.recv().await
+            {
+                return (relay_url, challenge);
+            }
+        }
+    })
+    .await
+    .expect("relay should send an AUTH challenge")
+}
+
+async fn accepted(client: &Client, keys: &Keys, url: &RelayUrl) -> bool {
+    let note = EventBuilder::text_note("hello private relay")
+        .sign(keys)
+        .await
+        .unwrap();
+    client
+        .send_event(&note)
+        .await
+        .map(|output| output.success.contains(url))
+        .unwrap_or(false)
+}
+
+#[tokio::test]
+async fn answers_auth_challenge_then_relay_accepts_events() {
+    let (_relay, url) = auth_relay().await;
+    let (client, keys, activity, mut event_rx) = connect(&url).await;
+
+    // Writes are refused before AUTH
+    assert!(!accepted(&client, &keys, &url).await);
+
+    let (relay_url, challenge) = next_challenge(&mut event_rx).await;
+    assert_eq!(relay_url, url);
+    answer_auth_challenge(
+        &client,
+        &keys.clone().into(),
+        &activity,
+        &relay_url,
+        &challenge,
+    )
+    .await
+    .unwrap();
+
+    let mut ok = false;
+    for _ in 0..20 {
+        if accepted(&client, &keys, &url).await {
+            ok = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(ok, "relay should accept events once authenticated");
+}
+
+#[tokio::test]
+async fn rejected_auth_is_reported_as_network_error() {
+    let (_relay, url) = auth_relay().await;
+    let (client, keys, activity, mut event_rx) = connect(&url).await;
+
+    assert!(!accepted(&client, &keys, &url).await);
+    let (relay_url, _) = next_challenge(&mut event_rx).await;
+    answer_auth_challenge(
+        &client,
+        &keys.clone().into(),
+        &activity,
+        &relay_url,
+        "wrong challenge",
+    )
+    .await
+    .unwrap();
+
+    let error = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(AppEvent::NetworkError { error }) = event_rx.recv().await {
+                return error;
+            }
+        }
+    })
+    .await
+    .expect("a rejected AUTH should surface as NetworkError");
+    assert!(error.contains("AUTH failed"));
+
+    let last_error = activity.get(&url).last_error.unwrap_or_default();
+    assert!(last_error.starts_with("auth failed"));
+}
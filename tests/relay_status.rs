@@ -0,0 +1,78 @@
+use nostr_sdk::prelude::*;
+use nrc::events::ConnectionStatus;
+use nrc::relay_status::{connected_count, overall_status, relay_state, RelayActivity};
+use nrc::ui_state::RelayInfo;
+
+fn relay(url: &str, state: ConnectionStatus) -> RelayInfo {
+    RelayInfo {
+        url: url.to_string(),
+        state,
+        latency: None,
+        last_error: None,
+        events_received: 0,
+        events_sent: 0,
+    }
+}
+
+#[test]
+fn overall_status_follows_best_relay() {
+    assert_eq!(overall_status(&[]), ConnectionStatus::Disconnected);
+
+    let down = vec![
+        relay("wss://a.example", ConnectionStatus::Disconnected),
+        relay(
+            "wss://b.example",
+            ConnectionStatus::Error("banned".to_string()),
+        ),
+    ];
+    assert_eq!(overall_status(&down), ConnectionStatus::Disconnected);
+    assert_eq!(connected_count(&down), 0);
+
+    let mut trying = down.clone();
+    trying.push(relay("wss://c.example", ConnectionStatus::Connecting));
+    assert_eq!(overall_status(&trying), ConnectionStatus::Connecting);
+
+    let mut up = trying.clone();
+    up.push(relay("wss://d.example", ConnectionStatus::Connected));
+    assert_eq!(overall_status(&up), ConnectionStatus::Connected);
+    assert_eq!(connected_count(&up), 1);
+}
+
+#[test]
+fn relay_states_map_to_connection_status() {
+    assert_eq!(
+        relay_state(RelayStatus::Connected),
+        ConnectionStatus::Connected
+    );
+    assert_eq!(
+        relay_state(RelayStatus::Connecting),
+        ConnectionStatus::Connecting
+    );
+    assert_eq!(
+        relay_state(RelayStatus::Terminated),
+        ConnectionStatus::Disconnected
+    );
+}
+
+#[test]
+fn activity_counts_relay_messages() {
+    let activity = RelayActivity::new();
+    let url = RelayUrl::parse("wss://relay.example").unwrap();
+    let id = EventId::all_zeros();
+
+    activity.record_received(&url);
+    activity.record_message(&url, &RelayMessage::ok(id, true, ""));
+    activity.record_message(&url, &RelayMessage::ok(id, false, "blocked: spam"));
+
+    let counters = activity.get(&url);
+    assert_eq!(counters.events_received, 1);
+    assert_eq!(counters.events_sent, 1);
+    assert_eq!(
+        counters.last_error.as_deref(),
+        Some("rejected: blocked: spam")
+    );
+
+    // Relays we never heard from have empty counters
+    let other = RelayUrl::parse("wss://other.example").unwrap();
+    assert_eq!(activity.get(&other).events_received, 0);
+}
@@ -0,0 +1,81 @@
+use nostr_sdk::prelude::*;
+use nrc::replies::{chat_rumor, quote_parents, quote_text, reply_target, MISSING_PARENT};
+use nrc::ui_state::{Message, ReplyTo};
+
+fn message(id: EventId, sender: PublicKey, content: &str, reply_to: Option<EventId>) -> Message {
+    Message {
+        id: Some(id),
+        content: content.to_string(),
+        sender,
+        timestamp: Timestamp::now(),
+        system: false,
+        delivery: None,
+        reply_to: reply_to.map(|id| ReplyTo { id, parent: None }),
+    }
+}
+
+#[test]
+fn reply_rumor_carries_an_e_tag_for_its_parent() {
+    let author = Keys::generate().public_key();
+    let parent = chat_rumor(author, "first", None);
+    let parent_id = parent.id.unwrap();
+    assert_eq!(parent.kind, Kind::Custom(9));
+    assert_eq!(reply_target(&parent.tags), None);
+
+    let reply = chat_rumor(author, "answer", Some(parent_id));
+    assert_eq!(reply.kind, Kind::Custom(9));
+    assert_eq!(reply.content, "answer");
+    assert_eq!(reply_target(&reply.tags), Some(parent_id));
+    // Set before storing, so the chat view can refer to it right away
+    assert!(reply.id.is_some());
+    assert_ne!(reply.id, parent.id);
+}
+
+#[test]
+fn replies_quote_loaded_parents_and_fall_back_to_a_placeholder() {
+    let alice = Keys::generate().public_key();
+    let bob = Keys::generate().public_key();
+    let hello = chat_rumor(alice, "hello bob", None).id.unwrap();
+    let unknown = chat_rumor(alice, "never loaded", None).id.unwrap();
+    let answer = chat_rumor(bob, "hi alice", Some(hello)).id.unwrap();
+    let orphan = chat_rumor(bob, "re: something", Some(unknown)).id.unwrap();
+
+    let mut messages = vec![
+        message(hello, alice, "hello bob", None),
+        message(answer, bob, "hi alice", Some(hello)),
+        message(orphan, bob, "re: something", Some(unknown)),
+    ];
+    quote_parents(&mut messages);
+
+    let name = |pk: &PublicKey| if *pk == alice { "alice" } else { "bob" }.to_string();
+    let quoted = messages[1].reply_to.as_ref().unwrap();
+    assert_eq!(quoted.parent.as_ref().unwrap().sender, alice);
+    assert_eq!(quote_text(quoted, name, 60), "alice: hello bob");
+
+    let missing = messages[2].reply_to.as_ref().unwrap();
+    assert!(missing.parent.is_none());
+    assert_eq!(quote_text(missing, name, 60), MISSING_PARENT);
+}
+
+#[test]
+fn quotes_are_cut_to_one_short_line() {
+    let alice = Keys::generate().public_key();
+    let parent = chat_rumor(alice, "a long first line\nand a second", None)
+        .id
+        .unwrap();
+    let mut messages = vec![
+        message(parent, alice, "a long first line\nand a second", None),
+        message(EventId::all_zeros(), alice, "reply", Some(parent)),
+    ];
+    quote_parents(&mut messages);
+
+    let reply = messages[1].reply_to.as_ref().unwrap();
+    assert_eq!(
+        quote_text(reply, |_| "alice".to_string(), 6),
+        "alice: a long…"
+    );
+    assert_eq!(
+        quote_text(reply, |_| "alice".to_string(), 60),
+        "alice: a long first line…"
+    );
+}